@@ -33,6 +33,7 @@ fn main() {
             units: usvg::Units::ObjectBoundingBox,
             transform: usvg::Transform::default(),
             spread_method: usvg::SpreadMethod::Pad,
+            color_interpolation: usvg::ColorInterpolation::SRGB,
             stops: vec![
                 usvg::Stop {
                     offset: usvg::StopOffset::new(0.0),
@@ -59,7 +60,9 @@ fn main() {
         visibility: usvg::Visibility::Visible,
         fill,
         stroke: None,
+        paint_order: usvg::PaintOrder::default(),
         segments: utils::rect_to_path(Rect::new(20.0, 20.0, 160.0, 160.0)),
+        markers: Vec::new(),
     }));
 
     println!("{}", rtree.to_svgdom());
@@ -54,7 +54,9 @@ fn main() {
             visibility: usvg::Visibility::Visible,
             fill: None,
             stroke: stroke.clone(),
+            paint_order: usvg::PaintOrder::default(),
             segments: utils::rect_to_path(bbox),
+            markers: Vec::new(),
         }));
     }
 
@@ -478,6 +478,11 @@ void qtc_qpen_set_dash_array(qtc_qpen *c_pen, const double *array, int len)
     PEN_CAST->setDashPattern(dashes);
 }
 
+void qtc_qpen_set_cosmetic(qtc_qpen *c_pen, bool flag)
+{
+    PEN_CAST->setCosmetic(flag);
+}
+
 // QBrush
 
 qtc_qbrush *qtc_qbrush_create()
@@ -542,9 +547,9 @@ void qtc_qlineargradient_destroy(qtc_qlineargradient *c_lg)
 
 // QRadialGradient
 
-qtc_qradialgradient *qtc_qradialgradient_create(double cx, double cy, double fx, double fy, double r)
+qtc_qradialgradient *qtc_qradialgradient_create(double cx, double cy, double fx, double fy, double r, double fr)
 {
-    auto rg = new QRadialGradient(cx, cy, r, fx, fy);
+    auto rg = new QRadialGradient(QPointF(cx, cy), r, QPointF(fx, fy), fr);
     rg->setInterpolationMode(QGradient::ComponentInterpolation);
     return reinterpret_cast<qtc_qradialgradient*>(rg);
 }
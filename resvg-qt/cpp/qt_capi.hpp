@@ -229,6 +229,7 @@ void qtc_qpen_set_width(qtc_qpen *c_pen, double width);
 void qtc_qpen_set_miter_limit(qtc_qpen *c_pen, double limit);
 void qtc_qpen_set_dash_offset(qtc_qpen *c_pen, double offset);
 void qtc_qpen_set_dash_array(qtc_qpen *c_pen, const double *array, int len);
+void qtc_qpen_set_cosmetic(qtc_qpen *c_pen, bool flag);
 void qtc_qpen_destroy(qtc_qpen *c_pen);
 
 
@@ -251,7 +252,7 @@ void qtc_qlineargradient_destroy(qtc_qlineargradient *c_lg);
 
 
 // QRadialGradient
-qtc_qradialgradient* qtc_qradialgradient_create(double cx, double cy, double fx, double fy, double r);
+qtc_qradialgradient* qtc_qradialgradient_create(double cx, double cy, double fx, double fy, double r, double fr);
 void qtc_qradialgradient_set_color_at(qtc_qradialgradient *c_rg, double offset,
                                       uint8_t r, uint8_t g, uint8_t b, uint8_t a);
 void qtc_qradialgradient_set_spread(qtc_qradialgradient *c_rg, Spread s);
@@ -792,6 +792,9 @@ extern "C" {
         len: ::std::os::raw::c_int,
     );
 }
+extern "C" {
+    pub fn qtc_qpen_set_cosmetic(c_pen: *mut qtc_qpen, flag: bool);
+}
 extern "C" {
     pub fn qtc_qpen_destroy(c_pen: *mut qtc_qpen);
 }
@@ -847,6 +850,7 @@ extern "C" {
         fx: f64,
         fy: f64,
         r: f64,
+        fr: f64,
     ) -> *mut qtc_qradialgradient;
 }
 extern "C" {
@@ -486,6 +486,10 @@ impl Pen {
         assert!(offset.len() < i32::MAX as usize);
         unsafe { ffi::qtc_qpen_set_dash_array(self.0, offset.as_ptr(), offset.len() as i32) }
     }
+
+    pub fn set_cosmetic(&mut self, flag: bool) {
+        unsafe { ffi::qtc_qpen_set_cosmetic(self.0, flag) }
+    }
 }
 
 impl Drop for Pen {
@@ -564,8 +568,8 @@ impl Drop for LinearGradient {
 pub struct RadialGradient(*mut ffi::qtc_qradialgradient);
 
 impl RadialGradient {
-    pub fn new(cx: f64, cy: f64, fx: f64, fy: f64, r: f64) -> RadialGradient {
-        unsafe { RadialGradient(ffi::qtc_qradialgradient_create(cx, cy, fx, fy, r)) }
+    pub fn new(cx: f64, cy: f64, fx: f64, fy: f64, r: f64, fr: f64) -> RadialGradient {
+        unsafe { RadialGradient(ffi::qtc_qradialgradient_create(cx, cy, fx, fy, r, fr)) }
     }
 }
 
@@ -35,6 +35,9 @@ pub fn apply(
                             usvg::NodeKind::RadialGradient(ref rg) => {
                                 gradient::prepare_radial(rg, fill.opacity, bbox, cr);
                             }
+                            usvg::NodeKind::MeshGradient(ref mg) => {
+                                gradient::prepare_mesh(mg, fill.opacity, bbox, cr);
+                            }
                             usvg::NodeKind::Pattern(ref pattern) => {
                                 pattern::apply(&node, pattern, opt, fill.opacity, bbox, cr);
                             }
@@ -6,10 +6,12 @@
 use cairo::{
     self,
     MatrixTrait,
+    PatternTrait,
 };
 
 // self
 use super::prelude::*;
+use backend_utils::gradient;
 
 
 pub fn prepare_linear(
@@ -29,11 +31,63 @@ pub fn prepare_radial(
     bbox: Rect,
     cr: &cairo::Context
 ) {
-    let grad = cairo::RadialGradient::new(g.fx, g.fy, 0.0, g.cx, g.cy, g.r.value());
+    let grad = cairo::RadialGradient::new(g.fx, g.fy, g.fr.value(), g.cx, g.cy, g.r.value());
     prepare_base(&g.base, &grad, opacity, bbox, &g.id);
     cr.set_source(&cairo::Pattern::RadialGradient(grad));
 }
 
+pub fn prepare_mesh(
+    g: &usvg::MeshGradient,
+    opacity: usvg::Opacity,
+    bbox: Rect,
+    cr: &cairo::Context,
+) {
+    let mesh = cairo::Mesh::new();
+    for patch in g.rows.iter().flat_map(|row| row.iter()) {
+        let p = &patch.points;
+
+        mesh.begin_patch();
+        mesh.move_to(p[0].x, p[0].y);
+        for i in 0..4 {
+            let (c1, c2, end) = (p[i * 3 + 1], p[i * 3 + 2], p[(i * 3 + 3) % 12]);
+            mesh.curve_to(c1.x, c1.y, c2.x, c2.y, end.x, end.y);
+        }
+
+        let corners = [
+            cairo::MeshCorner::MeshCorner0,
+            cairo::MeshCorner::MeshCorner1,
+            cairo::MeshCorner::MeshCorner2,
+            cairo::MeshCorner::MeshCorner3,
+        ];
+
+        for (i, corner) in corners.iter().enumerate() {
+            let c = patch.colors[i];
+            mesh.set_corner_color_rgba(
+                *corner,
+                c.red as f64 / 255.0,
+                c.green as f64 / 255.0,
+                c.blue as f64 / 255.0,
+                patch.opacities[i].value() * opacity.value(),
+            );
+        }
+
+        mesh.end_patch();
+    }
+
+    let mut matrix = g.transform.to_native();
+
+    if g.units == usvg::Units::ObjectBoundingBox {
+        let m = try_opt_warn!(cairo::Matrix::from_bbox(bbox), (),
+                              "Gradient '{}' cannot be used on a zero-sized object.", g.id);
+        matrix = cairo::Matrix::multiply(&matrix, &m);
+    }
+
+    matrix.invert();
+    mesh.set_matrix(matrix);
+
+    cr.set_source(&cairo::Pattern::Mesh(mesh));
+}
+
 fn prepare_base<G>(
     g: &usvg::BaseGradient,
     grad: &G,
@@ -59,7 +113,7 @@ fn prepare_base<G>(
     matrix.invert();
     grad.set_matrix(matrix);
 
-    for stop in &g.stops {
+    for stop in gradient::prepare_stops(g).iter() {
         grad.add_color_stop_rgba(
             stop.offset.value(),
             stop.color.red as f64 / 255.0,
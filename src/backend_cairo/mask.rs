@@ -50,7 +50,7 @@ pub fn apply(
     {
         let mut data = try_opt_warn!(mask_surface.get_data().ok(), (),
                                      "Failed to borrow a surface for mask '{}'.", mask.id);
-        mask::image_to_mask(&mut data, layers.image_size());
+        mask::image_to_mask(&mut data, layers.image_size(), mask.kind);
     }
 
     if let Some(ref id) = mask.mask {
@@ -127,6 +127,14 @@ impl Render for Backend {
     ) -> Option<Rect> {
         calc_node_bbox(node, opt)
     }
+
+    fn layout_text(
+        &self,
+        node: &usvg::Node,
+        opt: &Options,
+    ) -> Option<Vec<GlyphRun>> {
+        layout_text(node, opt)
+    }
 }
 
 impl OutputImage for cairo::ImageSurface {
@@ -265,7 +273,9 @@ fn render_node(
             Some(render_group(node, opt, layers, cr))
         }
         usvg::NodeKind::Path(ref path) => {
-            Some(path::draw(&node.tree(), path, opt, cr))
+            let mut bbox = path::draw(&node.tree(), path, opt, cr);
+            render_markers(node, path, opt, layers, cr, &mut bbox);
+            Some(bbox)
         }
         usvg::NodeKind::Text(ref text) => {
             Some(text::draw(&node.tree(), text, opt, cr))
@@ -276,6 +286,10 @@ fn render_node(
         usvg::NodeKind::Group(ref g) => {
             render_group_impl(node, g, opt, layers, cr)
         }
+        usvg::NodeKind::Use(ref u) => {
+            let def_node = node.tree().defs_by_id(&u.link)?;
+            Some(render_group(&def_node, opt, layers, cr))
+        }
         _ => None,
     }
 }
@@ -359,12 +373,14 @@ fn render_group_impl(
     let curr_matrix = cr.get_matrix();
     cr.set_matrix(cairo::Matrix::identity());
     cr.set_source_surface(&*sub_surface, 0.0, 0.0);
+    cr.set_operator(convert_blend_mode(g.blend_mode));
     if !g.opacity.is_default() {
         cr.paint_with_alpha(g.opacity.value());
     } else {
         cr.paint();
     }
 
+    cr.set_operator(cairo::Operator::Over);
     cr.set_matrix(curr_matrix);
 
     // All layers must be unlinked from the main context/cr after used.
@@ -374,6 +390,63 @@ fn render_group_impl(
     Some(bbox)
 }
 
+/// Renders markers kept by `usvg::Options::keep_markers`.
+fn render_markers(
+    node: &usvg::Node,
+    path: &usvg::Path,
+    opt: &Options,
+    layers: &mut CairoLayers,
+    cr: &cairo::Context,
+    bbox: &mut Rect,
+) {
+    for instance in &path.markers {
+        let marker_node = match node.tree().defs_by_id(&instance.id) {
+            Some(n) => n,
+            None => continue,
+        };
+
+        let clip_path = match *marker_node.borrow() {
+            usvg::NodeKind::Marker(ref marker) => marker.clip_path.clone(),
+            _ => continue,
+        };
+
+        let g = usvg::Group {
+            clip_path,
+            ..usvg::Group::default()
+        };
+
+        let curr_ts = cr.get_matrix();
+        cr.transform(instance.transform.to_native());
+
+        if let Some(r) = render_group_impl(&marker_node, &g, opt, layers, cr) {
+            bbox.expand(r.transform(&instance.transform));
+        }
+
+        cr.set_matrix(curr_ts);
+    }
+}
+
+fn convert_blend_mode(mode: usvg::BlendMode) -> cairo::Operator {
+    match mode {
+        usvg::BlendMode::Normal => cairo::Operator::Over,
+        usvg::BlendMode::Multiply => cairo::Operator::Multiply,
+        usvg::BlendMode::Screen => cairo::Operator::Screen,
+        usvg::BlendMode::Overlay => cairo::Operator::Overlay,
+        usvg::BlendMode::Darken => cairo::Operator::Darken,
+        usvg::BlendMode::Lighten => cairo::Operator::Lighten,
+        usvg::BlendMode::ColorDodge => cairo::Operator::ColorDodge,
+        usvg::BlendMode::ColorBurn => cairo::Operator::ColorBurn,
+        usvg::BlendMode::HardLight => cairo::Operator::HardLight,
+        usvg::BlendMode::SoftLight => cairo::Operator::SoftLight,
+        usvg::BlendMode::Difference => cairo::Operator::Difference,
+        usvg::BlendMode::Exclusion => cairo::Operator::Exclusion,
+        usvg::BlendMode::Hue => cairo::Operator::HslHue,
+        usvg::BlendMode::Saturation => cairo::Operator::HslSaturation,
+        usvg::BlendMode::Color => cairo::Operator::HslColor,
+        usvg::BlendMode::Luminosity => cairo::Operator::HslLuminosity,
+    }
+}
+
 /// Calculates node's absolute bounding box.
 ///
 /// Note: this method can be pretty expensive.
@@ -410,7 +483,22 @@ fn _calc_node_bbox(
 
     match *node.borrow() {
         usvg::NodeKind::Path(ref path) => {
-            Some(utils::path_bbox(&path.segments, path.stroke.as_ref(), &ts2))
+            let mut bbox = utils::path_bbox_with_scaled_stroke(&path.segments, path.stroke.as_ref(), &ts2);
+
+            for instance in &path.markers {
+                if let Some(marker_node) = node.tree().defs_by_id(&instance.id) {
+                    let mut marker_ts = ts2;
+                    marker_ts.append(&instance.transform);
+
+                    for child in marker_node.children() {
+                        if let Some(c_bbox) = _calc_node_bbox(&child, opt, marker_ts, cr) {
+                            bbox.expand(c_bbox);
+                        }
+                    }
+                }
+            }
+
+            Some(bbox)
         }
         usvg::NodeKind::Text(ref text) => {
             let mut bbox = Rect::new_bbox();
@@ -433,7 +521,7 @@ fn _calc_node_bbox(
                 t.translate(block.bbox.x, block.bbox.y);
 
                 if !segments.is_empty() {
-                    let c_bbox = utils::path_bbox(&segments, block.stroke.as_ref(), &t);
+                    let c_bbox = utils::path_bbox_with_scaled_stroke(&segments, block.stroke.as_ref(), &t);
                     bbox.expand(c_bbox);
                 }
             });
@@ -455,10 +543,55 @@ fn _calc_node_bbox(
 
             Some(bbox)
         }
+        usvg::NodeKind::Use(ref u) => {
+            let def_node = node.tree().defs_by_id(&u.link)?;
+            let mut bbox = Rect::new_bbox();
+
+            for child in def_node.children() {
+                if let Some(c_bbox) = _calc_node_bbox(&child, opt, ts2, cr) {
+                    bbox.expand(c_bbox);
+                }
+            }
+
+            Some(bbox)
+        }
         _ => None
     }
 }
 
+/// Returns a text node layout.
+///
+/// Returns `None` if the `node` is not a `Text` node.
+pub fn layout_text(
+    node: &usvg::Node,
+    opt: &Options,
+) -> Option<Vec<GlyphRun>> {
+    let tree = node.tree();
+
+    if let usvg::NodeKind::Text(ref text) = *node.borrow() {
+        // Like in `calc_node_bbox`, a full size surface is required
+        // to get the same layout as during rendering.
+        let (surface, img_view) = create_surface(
+            tree.svg_node().size.to_screen_size(),
+            opt,
+        )?;
+        let cr = cairo::Context::new(&surface);
+        apply_viewbox_transform(tree.svg_node().view_box, img_view, &cr);
+
+        // Hinting depends on the current transform, so it should be the same
+        // as during rendering. The layout itself is still in the user space.
+        let mut ts = utils::abs_transform(node);
+        ts.append(&node.transform());
+        cr.transform(ts.to_native());
+
+        let mut fm = text::PangoFontMetrics::new(opt, &cr);
+        let (blocks, _) = backend_utils::text::prepare_blocks(text, &mut fm);
+        Some(backend_utils::text::blocks_to_runs(&blocks))
+    } else {
+        None
+    }
+}
+
 fn from_cairo_path(path: &cairo::Path) -> Vec<usvg::PathSegment> {
     let mut segments = Vec::new();
     for seg in path.iter() {
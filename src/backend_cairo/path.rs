@@ -32,14 +32,27 @@ pub fn draw(
         return bbox;
     }
 
-    fill::apply(tree, &path.fill, opt, bbox, cr);
-    if path.stroke.is_some() {
-        cr.fill_preserve();
+    match path.paint_order {
+        usvg::PaintOrder::FillAndStroke => {
+            fill::apply(tree, &path.fill, opt, bbox, cr);
+            if path.stroke.is_some() {
+                cr.fill_preserve();
+
+                stroke::apply(tree, &path.stroke, opt, bbox, cr);
+                stroke::draw(&path.stroke, cr);
+            } else {
+                cr.fill();
+            }
+        }
+        usvg::PaintOrder::StrokeAndFill => {
+            if path.stroke.is_some() {
+                stroke::apply(tree, &path.stroke, opt, bbox, cr);
+                stroke::draw_preserve(&path.stroke, cr);
+            }
 
-        stroke::apply(tree, &path.stroke, opt, bbox, cr);
-        cr.stroke();
-    } else {
-        cr.fill();
+            fill::apply(tree, &path.fill, opt, bbox, cr);
+            cr.fill();
+        }
     }
 
     bbox
@@ -35,6 +35,9 @@ pub fn apply(
                             usvg::NodeKind::RadialGradient(ref rg) => {
                                 gradient::prepare_radial(rg, stroke.opacity, bbox, cr);
                             }
+                            usvg::NodeKind::MeshGradient(ref mg) => {
+                                gradient::prepare_mesh(mg, stroke.opacity, bbox, cr);
+                            }
                             usvg::NodeKind::Pattern(ref pattern) => {
                                 pattern::apply(&node, pattern, opt, stroke.opacity, bbox, cr);
                             }
@@ -77,3 +80,35 @@ pub fn apply(
         }
     }
 }
+
+/// Strokes the current path.
+///
+/// A non-scaling stroke is drawn in the device space,
+/// so the width is not affected by the current transform.
+pub fn draw(stroke: &Option<usvg::Stroke>, cr: &cairo::Context) {
+    draw_impl(stroke, false, cr);
+}
+
+/// Strokes the current path and preserves it.
+pub fn draw_preserve(stroke: &Option<usvg::Stroke>, cr: &cairo::Context) {
+    draw_impl(stroke, true, cr);
+}
+
+fn draw_impl(stroke: &Option<usvg::Stroke>, preserve: bool, cr: &cairo::Context) {
+    let non_scaling = stroke.as_ref().map(|s| s.non_scaling).unwrap_or(false);
+    if non_scaling {
+        // The path is already in the device space, so only the stroke is affected.
+        cr.save();
+        cr.identity_matrix();
+    }
+
+    if preserve {
+        cr.stroke_preserve();
+    } else {
+        cr.stroke();
+    }
+
+    if non_scaling {
+        cr.restore();
+    }
+}
@@ -94,7 +94,8 @@ pub fn draw(
 ) -> Rect {
     let mut fm = PangoFontMetrics::new(opt, cr);
     let (blocks, text_bbox) = text::prepare_blocks(text_node, &mut fm);
-    text::draw_blocks(blocks, |block| draw_block(tree, block, text_bbox, opt, cr));
+    let order = text_node.paint_order;
+    text::draw_blocks(blocks, |block| draw_block(tree, block, order, text_bbox, opt, cr));
     text_bbox
 }
 
@@ -132,6 +133,7 @@ fn set_text_spacing(
 fn draw_block(
     tree: &usvg::Tree,
     block: &text::TextBlock<pango::FontDescription>,
+    order: usvg::PaintOrder,
     text_bbox: Rect,
     opt: &Options,
     cr: &cairo::Context,
@@ -172,15 +174,30 @@ fn draw_block(
     }
 
     // Draw text.
-    cr.move_to(bbox.x, bbox.y);
+    let draw_fill = || {
+        cr.move_to(bbox.x, bbox.y);
+        fill::apply(tree, &block.fill, opt, text_bbox, cr);
+        pc::update_layout(cr, &layout);
+        pc::show_layout(cr, &layout);
+    };
 
-    fill::apply(tree, &block.fill, opt, text_bbox, cr);
-    pc::update_layout(cr, &layout);
-    pc::show_layout(cr, &layout);
+    let draw_stroke = || {
+        cr.move_to(bbox.x, bbox.y);
+        stroke::apply(tree, &block.stroke, opt, text_bbox, cr);
+        pc::layout_path(cr, &layout);
+        stroke::draw(&block.stroke, cr);
+    };
 
-    stroke::apply(tree, &block.stroke, opt, text_bbox, cr);
-    pc::layout_path(cr, &layout);
-    cr.stroke();
+    match order {
+        usvg::PaintOrder::FillAndStroke => {
+            draw_fill();
+            draw_stroke();
+        }
+        usvg::PaintOrder::StrokeAndFill => {
+            draw_stroke();
+            draw_fill();
+        }
+    }
 
     cr.move_to(-bbox.x, -bbox.y);
 
@@ -268,7 +285,7 @@ fn draw_line(
         cr.fill_preserve();
 
         stroke::apply(tree, &stroke, opt, text_bbox, cr);
-        cr.stroke();
+        stroke::draw(&stroke, cr);
     } else {
         cr.fill();
     }
@@ -39,6 +39,10 @@ pub fn apply(
                             usvg::NodeKind::RadialGradient(ref rg) => {
                                 gradient::prepare_radial(rg, opacity, bbox, &mut brush);
                             }
+                            usvg::NodeKind::MeshGradient(ref mg) => {
+                                let ts = p.get_transform();
+                                gradient::prepare_mesh(mg, ts, opacity, bbox, 0.0, &mut brush);
+                            }
                             usvg::NodeKind::Pattern(ref pattern) => {
                                 let ts = p.get_transform();
                                 pattern::apply(&node, pattern, opt, ts, bbox, opacity, &mut brush);
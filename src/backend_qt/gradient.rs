@@ -2,11 +2,14 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::f64;
+
 // external
 use qt;
 
 // self
 use super::prelude::*;
+use backend_utils::gradient;
 
 
 pub fn prepare_linear(
@@ -28,13 +31,72 @@ pub fn prepare_radial(
     bbox: Rect,
     brush: &mut qt::Brush,
 ) {
-    let mut grad = qt::RadialGradient::new(g.cx, g.cy, g.fx, g.fy, g.r.value());
+    let mut grad = qt::RadialGradient::new(g.cx, g.cy, g.fx, g.fy, g.r.value(), g.fr.value());
     prepare_base(&g.base, opacity, &mut grad);
 
     brush.set_radial_gradient(grad);
     apply_ts(&g.base, bbox, &g.id, brush);
 }
 
+/// Renders a mesh gradient into an image brush.
+///
+/// Qt doesn't support mesh gradients, so patches are rasterized manually.
+///
+/// An image brush is always tiled, unlike a mesh gradient, which is transparent
+/// outside its patches. So the image covers the painted area too, which is
+/// the `bbox` expanded by the `margin`, and tiles are never visible.
+pub fn prepare_mesh(
+    g: &usvg::MeshGradient,
+    global_ts: qt::Transform,
+    opacity: usvg::Opacity,
+    bbox: Rect,
+    margin: f64,
+    brush: &mut qt::Brush,
+) {
+    let mut ts = if g.units == usvg::Units::ObjectBoundingBox {
+        try_opt_warn!(usvg::Transform::from_bbox(bbox), (),
+                      "Gradient '{}' cannot be used on a zero-sized object.", g.id)
+    } else {
+        usvg::Transform::default()
+    };
+    ts.append(&g.transform);
+
+    // A patch is always inside its control points.
+    let mut min = (bbox.x - margin, bbox.y - margin);
+    let mut max = (bbox.right() + margin, bbox.bottom() + margin);
+    for p in g.rows.iter().flat_map(|row| row.iter()).flat_map(|patch| patch.points.iter()) {
+        let (x, y) = ts.apply(p.x, p.y);
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+
+    let global_ts = usvg::Transform::from_native(&global_ts);
+    let (sx, sy) = global_ts.get_scale();
+
+    if sx.is_fuzzy_zero() || sy.is_fuzzy_zero() {
+        return;
+    }
+
+    let r = Rect::new(min.0, min.1, max.0 - min.0, max.1 - min.1);
+    let img_size = Size::new(r.width * sx, r.height * sy).to_screen_size();
+    let mut img = try_create_image!(img_size, ());
+    img.fill(0, 0, 0, 0);
+
+    // Gradient coordinates to image pixels.
+    let mut img_ts = usvg::Transform::new(sx, 0.0, 0.0, sy, 0.0, 0.0);
+    img_ts.translate(-r.x, -r.y);
+    img_ts.append(&ts);
+
+    gradient::rasterize_mesh(g, &img_ts, opacity, img_size, &mut img.data_mut());
+
+    brush.set_pattern(img);
+
+    let mut brush_ts = usvg::Transform::default();
+    brush_ts.translate(r.x, r.y);
+    brush_ts.scale(1.0 / sx, 1.0 / sy);
+    brush.set_transform(brush_ts.to_native());
+}
+
 fn prepare_base(
     g: &usvg::BaseGradient,
     opacity: usvg::Opacity,
@@ -47,7 +109,7 @@ fn prepare_base(
     };
     grad.set_spread(spread_method);
 
-    for stop in &g.stops {
+    for stop in gradient::prepare_stops(g).iter() {
         grad.set_color_at(
             stop.offset.value(),
             stop.color.red,
@@ -43,7 +43,7 @@ pub fn apply(
         super::render_group(node, opt, layers, &mut mask_p);
     }
 
-    mask::image_to_mask(&mut mask_img.data_mut(), layers.image_size());
+    mask::image_to_mask(&mut mask_img.data_mut(), layers.image_size(), mask.kind);
 
     if let Some(ref id) = mask.mask {
         if let Some(ref mask_node) = node.tree().defs_by_id(id) {
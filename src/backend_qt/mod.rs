@@ -98,6 +98,14 @@ impl Render for Backend {
     ) -> Option<Rect> {
         calc_node_bbox(node, opt)
     }
+
+    fn layout_text(
+        &self,
+        node: &usvg::Node,
+        opt: &Options,
+    ) -> Option<Vec<GlyphRun>> {
+        layout_text(node, opt)
+    }
 }
 
 impl OutputImage for qt::Image {
@@ -222,7 +230,9 @@ fn render_node(
             Some(render_group(node, opt, layers, p))
         }
         usvg::NodeKind::Path(ref path) => {
-            Some(path::draw(&node.tree(), path, opt, p))
+            let mut bbox = path::draw(&node.tree(), path, opt, p);
+            render_markers(node, path, opt, layers, p, &mut bbox);
+            Some(bbox)
         }
         usvg::NodeKind::Text(ref text) => {
             Some(text::draw(&node.tree(), text, opt, p))
@@ -233,6 +243,10 @@ fn render_node(
         usvg::NodeKind::Group(ref g) => {
             render_group_impl(node, g, opt, layers, p)
         }
+        usvg::NodeKind::Use(ref u) => {
+            let def_node = node.tree().defs_by_id(&u.link)?;
+            Some(render_group(&def_node, opt, layers, p))
+        }
         _ => None,
     }
 }
@@ -316,17 +330,82 @@ fn render_group_impl(
         p.set_opacity(g.opacity.value());
     }
 
+    p.set_composition_mode(convert_blend_mode(g.blend_mode));
+
     let curr_ts = p.get_transform();
     p.set_transform(&qt::Transform::default());
 
     p.draw_image(0.0, 0.0, &sub_img);
 
     p.set_opacity(1.0);
+    p.set_composition_mode(qt::CompositionMode::SourceOver);
     p.set_transform(&curr_ts);
 
     Some(bbox)
 }
 
+/// Renders markers kept by `usvg::Options::keep_markers`.
+fn render_markers(
+    node: &usvg::Node,
+    path: &usvg::Path,
+    opt: &Options,
+    layers: &mut QtLayers,
+    p: &mut qt::Painter,
+    bbox: &mut Rect,
+) {
+    for instance in &path.markers {
+        let marker_node = match node.tree().defs_by_id(&instance.id) {
+            Some(n) => n,
+            None => continue,
+        };
+
+        let clip_path = match *marker_node.borrow() {
+            usvg::NodeKind::Marker(ref marker) => marker.clip_path.clone(),
+            _ => continue,
+        };
+
+        let g = usvg::Group {
+            clip_path,
+            ..usvg::Group::default()
+        };
+
+        let curr_ts = p.get_transform();
+        p.apply_transform(&instance.transform.to_native());
+
+        if let Some(r) = render_group_impl(&marker_node, &g, opt, layers, p) {
+            bbox.expand(r.transform(&instance.transform));
+        }
+
+        p.set_transform(&curr_ts);
+    }
+}
+
+fn convert_blend_mode(mode: usvg::BlendMode) -> qt::CompositionMode {
+    match mode {
+        usvg::BlendMode::Normal => qt::CompositionMode::SourceOver,
+        usvg::BlendMode::Multiply => qt::CompositionMode::Multiply,
+        usvg::BlendMode::Screen => qt::CompositionMode::Screen,
+        usvg::BlendMode::Overlay => qt::CompositionMode::Overlay,
+        usvg::BlendMode::Darken => qt::CompositionMode::Darken,
+        usvg::BlendMode::Lighten => qt::CompositionMode::Lighten,
+        usvg::BlendMode::ColorDodge => qt::CompositionMode::ColorDodge,
+        usvg::BlendMode::ColorBurn => qt::CompositionMode::ColorBurn,
+        usvg::BlendMode::HardLight => qt::CompositionMode::HardLight,
+        usvg::BlendMode::SoftLight => qt::CompositionMode::SoftLight,
+        usvg::BlendMode::Difference => qt::CompositionMode::Difference,
+        usvg::BlendMode::Exclusion => qt::CompositionMode::Exclusion,
+        // Qt doesn't support non-separable blend modes.
+        // See docs/backend_requirements.md
+          usvg::BlendMode::Hue
+        | usvg::BlendMode::Saturation
+        | usvg::BlendMode::Color
+        | usvg::BlendMode::Luminosity => {
+            warn!("'{}' blend mode is not supported by the Qt backend.", mode.to_string());
+            qt::CompositionMode::SourceOver
+        }
+    }
+}
+
 /// Calculates node's absolute bounding box.
 ///
 /// Note: this method can be pretty expensive.
@@ -355,7 +434,22 @@ fn _calc_node_bbox(
 
     match *node.borrow() {
         usvg::NodeKind::Path(ref path) => {
-            Some(utils::path_bbox(&path.segments, path.stroke.as_ref(), &ts2))
+            let mut bbox = utils::path_bbox_with_scaled_stroke(&path.segments, path.stroke.as_ref(), &ts2);
+
+            for instance in &path.markers {
+                if let Some(marker_node) = node.tree().defs_by_id(&instance.id) {
+                    let mut marker_ts = ts2;
+                    marker_ts.append(&instance.transform);
+
+                    for child in marker_node.children() {
+                        if let Some(c_bbox) = _calc_node_bbox(&child, opt, marker_ts, p) {
+                            bbox.expand(c_bbox);
+                        }
+                    }
+                }
+            }
+
+            Some(bbox)
         }
         usvg::NodeKind::Text(ref text) => {
             let mut bbox = Rect::new_bbox();
@@ -375,7 +469,7 @@ fn _calc_node_bbox(
 
                 let segments = from_qt_path(&p_path);
                 if !segments.is_empty() {
-                    let c_bbox = utils::path_bbox(&segments, block.stroke.as_ref(), &t);
+                    let c_bbox = utils::path_bbox_with_scaled_stroke(&segments, block.stroke.as_ref(), &t);
                     bbox.expand(c_bbox);
                 }
             });
@@ -397,10 +491,59 @@ fn _calc_node_bbox(
 
             Some(bbox)
         }
+        usvg::NodeKind::Use(ref u) => {
+            let def_node = node.tree().defs_by_id(&u.link)?;
+            let mut bbox = Rect::new_bbox();
+
+            for child in def_node.children() {
+                if let Some(c_bbox) = _calc_node_bbox(&child, opt, ts2, p) {
+                    bbox.expand(c_bbox);
+                }
+            }
+
+            Some(bbox)
+        }
         _ => None
     }
 }
 
+/// Returns a text node layout.
+///
+/// Returns `None` if the `node` is not a `Text` node.
+pub fn layout_text(
+    node: &usvg::Node,
+    opt: &Options,
+) -> Option<Vec<GlyphRun>> {
+    let tree = node.tree();
+
+    if let usvg::NodeKind::Text(ref text) = *node.borrow() {
+        // Unlike `calc_node_bbox`, a full size image is used,
+        // so the layout is measured exactly like in the cairo backend.
+        let (mut img, img_size) = create_root_image(
+            tree.svg_node().size.to_screen_size(),
+            opt,
+        )?;
+        let mut p = qt::Painter::new(&mut img);
+        apply_viewbox_transform(tree.svg_node().view_box, img_size, &mut p);
+
+        // The same transform as during rendering.
+        // The layout itself is still in the user space.
+        let mut ts = utils::abs_transform(node);
+        ts.append(&node.transform());
+        p.apply_transform(&ts.to_native());
+
+        let (blocks, _) = {
+            let mut fm = text::QtFontMetrics::new(&mut p);
+            backend_utils::text::prepare_blocks(text, &mut fm)
+        };
+        p.end();
+
+        Some(backend_utils::text::blocks_to_runs(&blocks))
+    } else {
+        None
+    }
+}
+
 fn from_qt_path(p_path: &qt::PainterPath) -> Vec<usvg::PathSegment> {
     let mut segments = Vec::with_capacity(p_path.len() as usize);
     let p_path_len = p_path.len();
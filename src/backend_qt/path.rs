@@ -35,10 +35,23 @@ pub fn draw(
         return bbox;
     }
 
-    fill::apply(tree, &path.fill, opt, bbox, p);
-    stroke::apply(tree, &path.stroke, opt, bbox, p);
-
-    p.draw_path(&p_path);
+    match path.paint_order {
+        usvg::PaintOrder::FillAndStroke => {
+            fill::apply(tree, &path.fill, opt, bbox, p);
+            stroke::apply(tree, &path.stroke, opt, bbox, p);
+            p.draw_path(&p_path);
+        }
+        usvg::PaintOrder::StrokeAndFill => {
+            // Qt always fills first, so we have to draw the path twice.
+            fill::apply(tree, &None, opt, bbox, p);
+            stroke::apply(tree, &path.stroke, opt, bbox, p);
+            p.draw_path(&p_path);
+
+            fill::apply(tree, &path.fill, opt, bbox, p);
+            stroke::apply(tree, &None, opt, bbox, p);
+            p.draw_path(&p_path);
+        }
+    }
 
     bbox
 }
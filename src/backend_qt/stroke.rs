@@ -2,6 +2,8 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::f64;
+
 // external
 use qt;
 
@@ -41,6 +43,14 @@ pub fn apply(
                             usvg::NodeKind::RadialGradient(ref rg) => {
                                 gradient::prepare_radial(rg, opacity, bbox, &mut brush);
                             }
+                            usvg::NodeKind::MeshGradient(ref mg) => {
+                                // A stroke can extend beyond the bbox by a half of its width
+                                // multiplied by the miter limit or by sqrt(2) for square caps.
+                                let scale = stroke.miterlimit.value().max(f64::consts::SQRT_2);
+                                let margin = stroke.width.value() / 2.0 * scale;
+                                let ts = p.get_transform();
+                                gradient::prepare_mesh(mg, ts, opacity, bbox, margin, &mut brush);
+                            }
                             usvg::NodeKind::Pattern(ref pattern) => {
                                 let ts = p.get_transform();
                                 pattern::apply(&node, pattern, opt, ts, bbox, opacity, &mut brush);
@@ -69,6 +79,7 @@ pub fn apply(
 
             pen.set_miter_limit(stroke.miterlimit.value());
             pen.set_width(stroke.width.value());
+            pen.set_cosmetic(stroke.non_scaling);
 
             if let Some(ref list) = stroke.dasharray {
                 pen.set_dash_offset(stroke.dashoffset as f64);
@@ -57,13 +57,15 @@ pub fn draw(
     p: &mut qt::Painter,
 ) -> Rect {
     let (blocks, text_bbox) = text::prepare_blocks(text_node, &mut QtFontMetrics::new(p));
-    text::draw_blocks(blocks, |block| draw_block(tree, block, text_bbox, opt, p));
+    let order = text_node.paint_order;
+    text::draw_blocks(blocks, |block| draw_block(tree, block, order, text_bbox, opt, p));
     text_bbox
 }
 
 fn draw_block(
     tree: &usvg::Tree,
     block: &text::TextBlock<qt::Font>,
+    order: usvg::PaintOrder,
     text_bbox: Rect,
     opt: &Options,
     p: &mut qt::Painter,
@@ -102,10 +104,22 @@ fn draw_block(
     }
 
     // Draw text.
-    fill::apply(tree, &block.fill, opt, text_bbox, p);
-    stroke::apply(tree, &block.stroke, opt, text_bbox, p);
-
-    p.draw_text(bbox.x, bbox.y, &block.text);
+    match order {
+        usvg::PaintOrder::FillAndStroke => {
+            fill::apply(tree, &block.fill, opt, text_bbox, p);
+            stroke::apply(tree, &block.stroke, opt, text_bbox, p);
+            p.draw_text(bbox.x, bbox.y, &block.text);
+        }
+        usvg::PaintOrder::StrokeAndFill => {
+            fill::apply(tree, &None, opt, text_bbox, p);
+            stroke::apply(tree, &block.stroke, opt, text_bbox, p);
+            p.draw_text(bbox.x, bbox.y, &block.text);
+
+            fill::apply(tree, &block.fill, opt, text_bbox, p);
+            stroke::apply(tree, &None, opt, text_bbox, p);
+            p.draw_text(bbox.x, bbox.y, &block.text);
+        }
+    }
 
     // Draw line-through.
     //
@@ -0,0 +1,383 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::borrow::Cow;
+use std::f64;
+
+// external
+use usvg::ColorInterpolation;
+
+// self
+use super::prelude::*;
+
+
+/// The maximum difference between the approximated and the real color, per 8-bit channel.
+///
+/// Half of a channel step, so approximated colors differ from the real ones
+/// by one step at most.
+const MAX_LINEAR_RGB_ERROR: f64 = 0.5;
+
+/// The maximum number of times a pair of stops can be split in half.
+///
+/// Limits the number of inserted stops to 256 per pair.
+const MAX_LINEAR_RGB_DEPTH: u32 = 8;
+
+/// Prepares gradient stops for rendering.
+///
+/// Neither cairo nor Qt support gradients interpolated in linearRGB,
+/// so such gradients are approximated by a larger number of sRGB stops.
+/// Stops are inserted only where the difference is visible.
+pub fn prepare_stops(g: &usvg::BaseGradient) -> Cow<[usvg::Stop]> {
+    if g.color_interpolation == ColorInterpolation::SRGB || g.stops.len() < 2 {
+        return Cow::Borrowed(&g.stops);
+    }
+
+    let mut stops = Vec::with_capacity(g.stops.len());
+    stops.push(g.stops[0]);
+
+    for pair in g.stops.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+
+        // A hard transition doesn't need intermediate stops.
+        if start.offset.value().fuzzy_eq(&end.offset.value()) {
+            stops.push(end);
+            continue;
+        }
+
+        push_linear_rgb_stops(start, end, 0.0, 1.0, 0, &mut stops);
+    }
+
+    Cow::Owned(stops)
+}
+
+/// Approximates a linearRGB transition between two stops in the `t1..t2` range.
+///
+/// The range is split in half while the error in its middle,
+/// where it is the largest for such a smooth curve, is too big.
+/// Only the stop at `t2` is added.
+fn push_linear_rgb_stops(
+    start: usvg::Stop,
+    end: usvg::Stop,
+    t1: f64,
+    t2: f64,
+    depth: u32,
+    stops: &mut Vec<usvg::Stop>,
+) {
+    let t = (t1 + t2) / 2.0;
+    let channels = [
+        (start.color.red, end.color.red),
+        (start.color.green, end.color.green),
+        (start.color.blue, end.color.blue),
+    ];
+    let is_accurate = channels.iter().all(|&(a, b)| {
+        let real = lerp_channel(a, b, t);
+        let approx = (lerp_channel(a, b, t1) + lerp_channel(a, b, t2)) / 2.0;
+        (real - approx).abs() <= MAX_LINEAR_RGB_ERROR
+    });
+
+    if !is_accurate && depth < MAX_LINEAR_RGB_DEPTH {
+        push_linear_rgb_stops(start, end, t1, t, depth + 1, stops);
+        push_linear_rgb_stops(start, end, t, t2, depth + 1, stops);
+        return;
+    }
+
+    stops.push(usvg::Stop {
+        offset: lerp(start.offset.value(), end.offset.value(), t2).into(),
+        color: lerp_color(start.color, end.color, t2),
+        opacity: lerp(start.opacity.value(), end.opacity.value(), t2).into(),
+    });
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Interpolates two sRGB channels in linearRGB.
+///
+/// Returns an unrounded sRGB value in the 0..255 range.
+fn lerp_channel(a: u8, b: u8, t: f64) -> f64 {
+    linear_to_srgb(lerp(srgb_to_linear(a), srgb_to_linear(b), t))
+}
+
+/// Interpolates two sRGB colors in linearRGB.
+fn lerp_color(c1: usvg::Color, c2: usvg::Color, t: f64) -> usvg::Color {
+    let to_byte = |n: f64| f64_bound(0.0, n.round(), 255.0) as u8;
+
+    usvg::Color::new(
+        to_byte(lerp_channel(c1.red, c2.red, t)),
+        to_byte(lerp_channel(c1.green, c2.green, t)),
+        to_byte(lerp_channel(c1.blue, c2.blue, t)),
+    )
+}
+
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+
+    c * 255.0
+}
+
+/// Rasterizes mesh gradient patches into a premultiplied BGRA image.
+///
+/// `ts` maps gradient coordinates into pixels.
+///
+/// Used by backends without a native mesh gradients support.
+/// Patches are densely sampled instead of being tessellated,
+/// which avoids seams between them.
+pub fn rasterize_mesh(
+    g: &usvg::MeshGradient,
+    ts: &usvg::Transform,
+    opacity: usvg::Opacity,
+    img_size: ScreenSize,
+    data: &mut [u8],
+) {
+    let width = img_size.width as i64;
+    let height = img_size.height as i64;
+
+    for patch in g.rows.iter().flat_map(|row| row.iter()) {
+        let mut points = [Point::new(0.0, 0.0); 12];
+        for (i, p) in patch.points.iter().enumerate() {
+            let (x, y) = ts.apply(p.x, p.y);
+            points[i] = Point::new(x, y);
+        }
+
+        // A patch is always inside its control points.
+        let mut min = Point::new(f64::MAX, f64::MAX);
+        let mut max = Point::new(f64::MIN, f64::MIN);
+        for p in &points {
+            min = Point::new(min.x.min(p.x), min.y.min(p.y));
+            max = Point::new(max.x.max(p.x), max.y.max(p.y));
+        }
+
+        if max.x < 0.0 || max.y < 0.0 || min.x >= width as f64 || min.y >= height as f64 {
+            continue;
+        }
+
+        // An edge length is always smaller than its control polygon length,
+        // so half a pixel step will not leave any gaps.
+        // But there is no point in sampling more than a patch size.
+        let max_steps = ((max.x - min.x + max.y - min.y) * 2.0).ceil().min(4096.0).max(1.0);
+        let steps = |len: f64| f64_bound(1.0, (len * 2.0).ceil(), max_steps) as usize;
+        let steps_u = steps(edge_len(&points, 0).max(edge_len(&points, 6)));
+        let steps_v = steps(edge_len(&points, 3).max(edge_len(&points, 9)));
+
+        for j in 0..(steps_v + 1) {
+            let v = j as f64 / steps_v as f64;
+            for i in 0..(steps_u + 1) {
+                let u = i as f64 / steps_u as f64;
+
+                let p = coons_point(&points, u, v);
+                let x = p.x.floor() as i64;
+                let y = p.y.floor() as i64;
+                if x < 0 || y < 0 || x >= width || y >= height {
+                    continue;
+                }
+
+                let weights = [(1.0 - u) * (1.0 - v), u * (1.0 - v), u * v, (1.0 - u) * v];
+                let mut rgba = [0.0; 4];
+                for k in 0..4 {
+                    let c = patch.colors[k];
+                    rgba[0] += c.red as f64 * weights[k];
+                    rgba[1] += c.green as f64 * weights[k];
+                    rgba[2] += c.blue as f64 * weights[k];
+                    rgba[3] += patch.opacities[k].value() * weights[k];
+                }
+
+                let a = f64_bound(0.0, rgba[3] * opacity.value(), 1.0);
+                let to_byte = |n: f64| f64_bound(0.0, (n * a).round(), 255.0) as u8;
+
+                let idx = ((y * width + x) * 4) as usize;
+                data[idx + 0] = to_byte(rgba[2]);
+                data[idx + 1] = to_byte(rgba[1]);
+                data[idx + 2] = to_byte(rgba[0]);
+                data[idx + 3] = (a * 255.0).round() as u8;
+            }
+        }
+    }
+}
+
+/// Returns a patch edge control polygon length.
+fn edge_len(points: &[Point; 12], start: usize) -> f64 {
+    let mut len = 0.0;
+    for i in start..(start + 3) {
+        let p1 = points[i];
+        let p2 = points[(i + 1) % 12];
+        len += ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt();
+    }
+
+    len
+}
+
+/// Evaluates a Coons patch at the specified parametric coordinates.
+fn coons_point(p: &[Point; 12], u: f64, v: f64) -> Point {
+    let top = cubic(p[0], p[1], p[2], p[3], u);
+    let bottom = cubic(p[9], p[8], p[7], p[6], u);
+    let left = cubic(p[0], p[11], p[10], p[9], v);
+    let right = cubic(p[3], p[4], p[5], p[6], v);
+
+    // Bilinear interpolation of corners.
+    let w = [(1.0 - u) * (1.0 - v), u * (1.0 - v), u * v, (1.0 - u) * v];
+    let cx = w[0] * p[0].x + w[1] * p[3].x + w[2] * p[6].x + w[3] * p[9].x;
+    let cy = w[0] * p[0].y + w[1] * p[3].y + w[2] * p[6].y + w[3] * p[9].y;
+
+    Point::new(
+        (1.0 - v) * top.x + v * bottom.x + (1.0 - u) * left.x + u * right.x - cx,
+        (1.0 - v) * top.y + v * bottom.y + (1.0 - u) * left.y + u * right.y - cy,
+    )
+}
+
+fn cubic(p0: Point, p1: Point, p2: Point, p3: Point, t: f64) -> Point {
+    let mt = 1.0 - t;
+    let a = mt * mt * mt;
+    let b = 3.0 * mt * mt * t;
+    let c = 3.0 * mt * t * t;
+    let d = t * t * t;
+
+    Point::new(
+        a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+        a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+    )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(
+        color_interpolation: ColorInterpolation,
+        stops: &[(f64, usvg::Color)],
+    ) -> usvg::BaseGradient {
+        usvg::BaseGradient {
+            units: usvg::Units::ObjectBoundingBox,
+            transform: usvg::Transform::default(),
+            spread_method: usvg::SpreadMethod::Pad,
+            color_interpolation,
+            stops: stops.iter().map(|&(offset, color)| {
+                usvg::Stop {
+                    offset: offset.into(),
+                    color,
+                    opacity: usvg::Opacity::default(),
+                }
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn prepare_stops_srgb() {
+        let g = gradient(ColorInterpolation::SRGB,
+                         &[(0.0, usvg::Color::black()), (1.0, usvg::Color::white())]);
+        assert_eq!(prepare_stops(&g).len(), 2);
+    }
+
+    #[test]
+    fn prepare_stops_linear_rgb() {
+        let g = gradient(ColorInterpolation::LinearRGB,
+                         &[(0.0, usvg::Color::black()), (1.0, usvg::Color::white())]);
+        let stops = prepare_stops(&g);
+
+        assert!(stops.len() > 2);
+        // Much less than 256 uniform steps that are required for the same precision.
+        assert!(stops.len() < 32);
+        assert_eq!(stops[0].color, usvg::Color::black());
+        assert_eq!(stops[stops.len() - 1].color, usvg::Color::white());
+        assert!(stops.windows(2).all(|p| p[0].offset.value() < p[1].offset.value()));
+
+        // The middle of a linearRGB gradient is lighter than in sRGB.
+        let mid = stops.iter().find(|s| s.offset.value().fuzzy_eq(&0.5)).unwrap();
+        assert_eq!(mid.color, usvg::Color::new(188, 188, 188));
+
+        // The real color between the generated stops is within a channel step.
+        for pair in stops.windows(2) {
+            let (o1, o2) = (pair[0].offset.value(), pair[1].offset.value());
+            let real = lerp_channel(0, 255, (o1 + o2) / 2.0);
+            let approx = (pair[0].color.red as f64 + pair[1].color.red as f64) / 2.0;
+            assert!((real - approx).abs() <= 1.5, "{} {}", real, approx);
+        }
+    }
+
+    #[test]
+    fn prepare_stops_linear_rgb_same_colors() {
+        let c = usvg::Color::new(10, 100, 200);
+        let g = gradient(ColorInterpolation::LinearRGB, &[(0.0, c), (0.5, c), (0.5, c), (1.0, c)]);
+        assert_eq!(prepare_stops(&g).len(), 4);
+    }
+
+    fn square_patch(size: f64, color: usvg::Color) -> usvg::MeshPatch {
+        let corners = [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)];
+        let mut points = [Point::new(0.0, 0.0); 12];
+        for i in 0..4 {
+            let (x1, y1) = corners[i];
+            let (x2, y2) = corners[(i + 1) % 4];
+            for k in 0..3 {
+                let t = k as f64 / 3.0;
+                points[i * 3 + k] = Point::new(x1 + (x2 - x1) * t, y1 + (y2 - y1) * t);
+            }
+        }
+
+        usvg::MeshPatch {
+            points,
+            colors: [color; 4],
+            opacities: [usvg::Opacity::default(); 4],
+        }
+    }
+
+    fn rasterize(patch: usvg::MeshPatch, opacity: f64) -> Vec<u8> {
+        let g = usvg::MeshGradient {
+            id: "mg".to_string(),
+            units: usvg::Units::UserSpaceOnUse,
+            transform: usvg::Transform::default(),
+            rows: vec![vec![patch]],
+        };
+
+        let mut data = vec![0; 8 * 8 * 4];
+        rasterize_mesh(&g, &usvg::Transform::default(), opacity.into(),
+                       ScreenSize::new(8, 8), &mut data);
+        data
+    }
+
+    fn pixel(data: &[u8], x: usize, y: usize) -> &[u8] {
+        let idx = (y * 8 + x) * 4;
+        &data[idx..(idx + 4)]
+    }
+
+    #[test]
+    fn rasterize_mesh_solid() {
+        let data = rasterize(square_patch(4.0, usvg::Color::new(255, 0, 0)), 1.0);
+        assert_eq!(pixel(&data, 0, 0), &[0, 0, 255, 255]);
+        assert_eq!(pixel(&data, 2, 3), &[0, 0, 255, 255]);
+        assert_eq!(pixel(&data, 6, 6), &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn rasterize_mesh_premultiplied() {
+        let data = rasterize(square_patch(4.0, usvg::Color::new(255, 0, 0)), 0.5);
+        assert_eq!(pixel(&data, 1, 1), &[0, 0, 128, 128]);
+    }
+
+    #[test]
+    fn rasterize_mesh_interpolation() {
+        let mut patch = square_patch(6.0, usvg::Color::black());
+        patch.colors[1] = usvg::Color::new(0, 0, 255);
+        patch.colors[2] = usvg::Color::new(0, 0, 255);
+        let data = rasterize(patch, 1.0);
+
+        // Blue grows from left to right.
+        assert!(pixel(&data, 0, 2)[0] < 64);
+        assert!(pixel(&data, 2, 2)[0] < pixel(&data, 4, 2)[0]);
+        assert_eq!(pixel(&data, 5, 2)[3], 255);
+    }
+}
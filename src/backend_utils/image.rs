@@ -18,11 +18,18 @@ pub fn load_sub_svg(
     let mut sub_opt = Options {
         usvg: usvg::Options {
             path: None,
+            load_external_files: false,
             dpi: opt.usvg.dpi,
             font_family: opt.usvg.font_family.clone(),
             font_size: opt.usvg.font_size,
             languages: opt.usvg.languages.clone(),
             keep_named_groups: false,
+            keep_markers: false,
+            keep_use: false,
+            detect_size: opt.usvg.detect_size,
+            default_size: opt.usvg.default_size,
+            style_sheet: opt.usvg.style_sheet.clone(),
+            color: opt.usvg.color,
         },
         fit_to: FitTo::Original,
         background: None,
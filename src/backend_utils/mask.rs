@@ -3,13 +3,18 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 // self
-use geom::*;
+use super::prelude::*;
 
 
 /// Converts an image to an alpha mask.
+///
+/// Luminance masks use the luminance of premultiplied colors,
+/// so the alpha is taken into account too.
+/// Alpha masks use only the alpha channel.
 pub fn image_to_mask(
     data: &mut [u8],
     img_size: ScreenSize,
+    kind: usvg::MaskType,
 ) {
     let width = img_size.width;
     let height = img_size.height;
@@ -23,6 +28,13 @@ pub fn image_to_mask(
         for x in 0..width {
             let idx = (y * stride + x * 4) as usize;
 
+            if kind == usvg::MaskType::Alpha {
+                data[idx + 0] = 0;
+                data[idx + 1] = 0;
+                data[idx + 2] = 0;
+                continue;
+            }
+
             let r = data[idx + 2] as f64;
             let g = data[idx + 1] as f64;
             let b = data[idx + 0] as f64;
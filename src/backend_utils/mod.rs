@@ -3,6 +3,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 pub mod filter;
+pub mod gradient;
 pub mod image;
 pub mod mask;
 pub mod text;
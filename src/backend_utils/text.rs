@@ -8,18 +8,80 @@ use super::prelude::*;
 
 pub struct TextBlock<Font> {
     pub text: String,
+    pub char_index: usize,
     pub is_visible: bool,
     pub bbox: Rect,
+    /// Glyph offsets from the block start.
+    pub glyph_offsets: Vec<f64>,
     pub rotate: Option<f64>,
     pub fill: Option<usvg::Fill>,
     pub stroke: Option<usvg::Stroke>,
     pub font: Font,
     pub font_ascent: f64,
+    pub baseline: f64,
     pub letter_spacing: Option<f64>,
     pub word_spacing: Option<f64>,
     pub decoration: usvg::TextDecoration,
 }
 
+/// A laid out glyph.
+///
+/// Uses the same coordinate space as `GlyphRun`.
+#[derive(Clone, Debug)]
+pub struct Glyph {
+    /// Glyph character.
+    pub c: char,
+    /// Index of the glyph character in the whole `text` element.
+    pub char_index: usize,
+    /// Glyph origin on the baseline.
+    pub x: f64,
+    /// Glyph baseline.
+    pub y: f64,
+    /// Glyph advance.
+    ///
+    /// A run width difference with and without this glyph,
+    /// so it includes the kerning with the previous glyph.
+    pub advance: f64,
+    /// Glyph bounding box.
+    ///
+    /// Has the same height as the run bounding box.
+    pub bbox: Rect,
+}
+
+/// A laid out run of glyphs.
+///
+/// All coordinates are in the `text` element's user space,
+/// i.e. without the element's own transform.
+/// Text is measured with the same transform as during rendering,
+/// so the layout is the same as the rendered one.
+#[derive(Clone, Debug)]
+pub struct GlyphRun {
+    /// Run text.
+    pub text: String,
+    /// Index of the first run character in the whole `text` element.
+    ///
+    /// Characters are counted as Unicode scalar values across all chunks and spans.
+    pub char_index: usize,
+    /// Run origin on the baseline.
+    pub x: f64,
+    /// Run baseline.
+    ///
+    /// Includes the span's `baseline-shift`.
+    pub y: f64,
+    /// Run advance.
+    pub advance: f64,
+    /// Run bounding box.
+    pub bbox: Rect,
+    /// Rotation angle around the run origin.
+    pub rotate: Option<f64>,
+    /// Indicates that the run is rendered.
+    pub is_visible: bool,
+    /// Run glyphs.
+    ///
+    /// One glyph per character.
+    pub glyphs: Vec<Glyph>,
+}
+
 pub trait FontMetrics<Font> {
     fn set_font(&mut self, font: &usvg::Font);
     fn font(&self) -> Font;
@@ -41,6 +103,39 @@ pub fn draw_blocks<Font, Draw>(
     }
 }
 
+pub fn blocks_to_runs<Font>(blocks: &[TextBlock<Font>]) -> Vec<GlyphRun> {
+    blocks.iter().map(|block| {
+        let offsets = &block.glyph_offsets;
+        let glyphs = block.text.chars().enumerate().map(|(i, c)| {
+            let offset = offsets[i];
+            let next_offset = offsets.get(i + 1).cloned().unwrap_or(block.bbox.width);
+            let advance = next_offset - offset;
+            let x = block.bbox.x + offset;
+
+            Glyph {
+                c,
+                char_index: block.char_index + i,
+                x,
+                y: block.baseline,
+                advance,
+                bbox: Rect { x, width: advance, .. block.bbox },
+            }
+        }).collect();
+
+        GlyphRun {
+            text: block.text.clone(),
+            char_index: block.char_index,
+            x: block.bbox.x,
+            y: block.baseline,
+            advance: block.bbox.width,
+            bbox: block.bbox,
+            rotate: block.rotate,
+            is_visible: block.is_visible,
+            glyphs,
+        }
+    }).collect()
+}
+
 pub fn prepare_blocks<Font>(
     text_kind: &usvg::Text,
     font_metrics: &mut FontMetrics<Font>,
@@ -69,6 +164,8 @@ pub fn prepare_blocks<Font>(
                 let can_merge = !blocks.is_empty() && !has_custom_offset;
                 if can_merge {
                     let prev_idx = blocks.len() - 1;
+                    let prev_w = blocks[prev_idx].bbox.width;
+                    blocks[prev_idx].glyph_offsets.push(prev_w);
                     blocks[prev_idx].text.push(c);
                     let w = font_metrics.width(&blocks[prev_idx].text);
                     blocks[prev_idx].bbox.width = w;
@@ -85,7 +182,8 @@ pub fn prepare_blocks<Font>(
 
                     let font_ascent = font_metrics.ascent(&buf_str);
                     let width = font_metrics.width(&buf_str);
-                    let yy = y - font_ascent - tspan.baseline_shift;
+                    let baseline = y - tspan.baseline_shift;
+                    let yy = baseline - font_ascent;
                     let height = font_metrics.height();
                     let bbox = Rect { x, y: yy, width, height };
                     x += width;
@@ -101,13 +199,16 @@ pub fn prepare_blocks<Font>(
 
                     blocks.push(TextBlock {
                         text: c.to_string(),
+                        char_index: char_idx,
                         is_visible: tspan.visibility == usvg::Visibility::Visible,
                         bbox,
+                        glyph_offsets: vec![0.0],
                         rotate,
                         fill: tspan.fill.clone(),
                         stroke: tspan.stroke.clone(),
                         font: font_metrics.font(),
                         font_ascent,
+                        baseline,
                         letter_spacing: tspan.font.letter_spacing,
                         word_spacing: tspan.font.word_spacing,
                         decoration: tspan.decoration.clone(),
@@ -147,3 +248,91 @@ fn process_text_anchor(a: usvg::TextAnchor, text_width: f64) -> f64 {
         usvg::TextAnchor::End =>    text_width,
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each glyph is 10 units wide, with a kerning of 2 units between glyphs.
+    struct TestMetrics;
+
+    impl FontMetrics<()> for TestMetrics {
+        fn set_font(&mut self, _: &usvg::Font) {}
+        fn font(&self) {}
+
+        fn width(&self, text: &str) -> f64 {
+            let n = text.chars().count() as f64;
+            n * 10.0 - (n - 1.0) * 2.0
+        }
+
+        fn ascent(&self, _: &str) -> f64 { 8.0 }
+        fn height(&self) -> f64 { 12.0 }
+    }
+
+    fn layout(text_attrs: &str) -> Vec<GlyphRun> {
+        let svg = format!("
+        <svg xmlns='http://www.w3.org/2000/svg' width='100' height='100'>
+            <text x='10' y='20' font-size='10'{}>ab<tspan>c</tspan></text>
+        </svg>", text_attrs);
+
+        let tree = usvg::Tree::from_str(&svg, &usvg::Options::default()).unwrap();
+        let node = tree.root().descendants().find(|n| {
+            if let usvg::NodeKind::Text(_) = *n.borrow() { true } else { false }
+        }).unwrap();
+
+        let kind = node.borrow();
+        if let usvg::NodeKind::Text(ref text) = *kind {
+            let (blocks, _) = prepare_blocks(text, &mut TestMetrics);
+            blocks_to_runs(&blocks)
+        } else {
+            unreachable!();
+        }
+    }
+
+    #[test]
+    fn glyph_runs() {
+        let runs = layout("");
+        assert_eq!(runs.len(), 2);
+
+        assert_eq!(runs[0].text, "ab");
+        assert_eq!(runs[0].char_index, 0);
+        assert_eq!((runs[0].x, runs[0].y, runs[0].advance), (10.0, 20.0, 18.0));
+        assert_eq!((runs[0].bbox.y, runs[0].bbox.height), (12.0, 12.0));
+
+        assert_eq!(runs[1].text, "c");
+        assert_eq!(runs[1].char_index, 2);
+        assert_eq!((runs[1].x, runs[1].advance), (28.0, 10.0));
+    }
+
+    #[test]
+    fn glyphs() {
+        let runs = layout("");
+
+        let glyphs = &runs[0].glyphs;
+        assert_eq!(glyphs.len(), 2);
+        assert_eq!((glyphs[0].c, glyphs[0].char_index), ('a', 0));
+        assert_eq!((glyphs[0].x, glyphs[0].y, glyphs[0].advance), (10.0, 20.0, 10.0));
+        assert_eq!((glyphs[1].c, glyphs[1].char_index), ('b', 1));
+        // The kerning with 'a' is a part of the 'b' advance.
+        assert_eq!((glyphs[1].x, glyphs[1].advance), (20.0, 8.0));
+        assert_eq!((glyphs[1].bbox.x, glyphs[1].bbox.width), (20.0, 8.0));
+        assert_eq!((glyphs[1].bbox.y, glyphs[1].bbox.height), (12.0, 12.0));
+
+        let glyphs = &runs[1].glyphs;
+        assert_eq!(glyphs.len(), 1);
+        assert_eq!((glyphs[0].c, glyphs[0].char_index), ('c', 2));
+        assert_eq!((glyphs[0].x, glyphs[0].advance), (28.0, 10.0));
+    }
+
+    #[test]
+    fn glyphs_with_anchor() {
+        let runs = layout(" text-anchor='middle'");
+
+        // The chunk width is 28.
+        assert_eq!(runs[0].x, -4.0);
+        assert_eq!(runs[0].glyphs[0].x, -4.0);
+        assert_eq!(runs[0].glyphs[1].x, 6.0);
+        assert_eq!(runs[1].glyphs[0].x, 14.0);
+    }
+}
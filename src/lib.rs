@@ -59,6 +59,8 @@ pub mod prelude {
     pub use Options;
     pub use Render;
     pub use OutputImage;
+    pub use Glyph;
+    pub use GlyphRun;
 }
 
 
@@ -66,6 +68,7 @@ use std::path;
 
 pub use options::*;
 pub use geom::*;
+pub use backend_utils::text::{Glyph, GlyphRun};
 
 /// Shorthand names for modules.
 mod short {
@@ -109,6 +112,23 @@ pub trait Render {
         node: &usvg::Node,
         opt: &Options,
     ) -> Option<Rect>;
+
+    /// Returns a text node layout.
+    ///
+    /// Text is split into runs of glyphs that share the same position,
+    /// the same way it will be rendered.
+    ///
+    /// Returns `None` if the `node` is not a `Text` node
+    /// or if the backend doesn't support text layout querying.
+    ///
+    /// The default implementation always returns `None`.
+    fn layout_text(
+        &self,
+        _node: &usvg::Node,
+        _opt: &Options,
+    ) -> Option<Vec<GlyphRun>> {
+        None
+    }
 }
 
 /// A generic interface for output image.
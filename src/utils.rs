@@ -67,95 +67,6 @@ pub fn abs_transform(
     root_ts
 }
 
-/// Calculates path's bounding box.
-///
-/// Width and/or height can be zero.
-pub fn path_bbox(
-    segments: &[usvg::PathSegment],
-    stroke: Option<&usvg::Stroke>,
-    ts: &usvg::Transform,
-) -> Rect {
-    debug_assert!(!segments.is_empty());
-
-    use lyon_geom;
-
-    let mut prev_x = 0.0;
-    let mut prev_y = 0.0;
-    let mut minx = 0.0;
-    let mut miny = 0.0;
-    let mut maxx = 0.0;
-    let mut maxy = 0.0;
-
-    if let Some(usvg::PathSegment::MoveTo { x, y }) = TransformedPath::new(segments, *ts).next() {
-        let x = x as f32;
-        let y = y as f32;
-
-        prev_x = x;
-        prev_y = y;
-        minx = x;
-        miny = y;
-        maxx = x;
-        maxy = y;
-    }
-
-    for seg in TransformedPath::new(segments, *ts) {
-        match seg {
-              usvg::PathSegment::MoveTo { x, y }
-            | usvg::PathSegment::LineTo { x, y } => {
-                let x = x as f32;
-                let y = y as f32;
-                prev_x = x;
-                prev_y = y;
-
-                     if x > maxx { maxx = x; }
-                else if x < minx { minx = x; }
-
-                     if y > maxy { maxy = y; }
-                else if y < miny { miny = y; }
-            }
-            usvg::PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
-                let x = x as f32;
-                let y = y as f32;
-
-                let curve = lyon_geom::CubicBezierSegment {
-                    from: lyon_geom::math::Point::new(prev_x, prev_y),
-                    ctrl1: lyon_geom::math::Point::new(x1 as f32, y1 as f32),
-                    ctrl2: lyon_geom::math::Point::new(x2 as f32, y2 as f32),
-                    to: lyon_geom::math::Point::new(x, y),
-                };
-
-                prev_x = x;
-                prev_y = y;
-
-                let r = curve.bounding_rect();
-
-                let right = r.max_x();
-                let bottom = r.max_y();
-                if r.min_x() < minx { minx = r.min_x(); }
-                if right > maxx { maxx = right; }
-                if r.min_y() < miny { miny = r.min_y(); }
-                if bottom > maxy { maxy = bottom; }
-            }
-            usvg::PathSegment::ClosePath => {}
-        }
-    }
-
-    // TODO: find a better way
-    // It's an approximation, but it's better than nothing.
-    if let Some(ref stroke) = stroke {
-        let w = (stroke.width.value() / 2.0) as f32;
-        minx -= w;
-        miny -= w;
-        maxx += w;
-        maxy += w;
-    }
-
-    let width = maxx - minx;
-    let height = maxy - miny;
-
-    (minx as f64, miny as f64, width as f64, height as f64).into()
-}
-
 /// Calculates path's length.
 ///
 /// Length from the first segment to the first MoveTo, ClosePath or slice end.
@@ -241,53 +152,283 @@ pub fn transform_path(segments: &mut [usvg::PathSegment], ts: &usvg::Transform)
 }
 
 
-/// An iterator over transformed path segments.
-pub struct TransformedPath<'a> {
-    segments: &'a [usvg::PathSegment],
-    ts: usvg::Transform,
-    idx: usize,
+/// A text content of a `Text` node.
+#[derive(Clone, Debug)]
+pub struct TextContent {
+    /// Node ID.
+    ///
+    /// Can be empty.
+    pub id: String,
+    /// Concatenated text of all chunks and spans.
+    pub text: String,
+    /// Node's absolute transform.
+    ///
+    /// Does not include the node's transform itself, just like `abs_transform`.
+    pub transform: usvg::Transform,
+    /// Node's absolute bounding box.
+    ///
+    /// `None` when the text has no visible glyphs.
+    pub bbox: Option<Rect>,
 }
 
-impl<'a> TransformedPath<'a> {
-    /// Creates a new `TransformedPath` iterator.
-    pub fn new(segments: &'a [usvg::PathSegment], ts: usvg::Transform) -> Self {
-        TransformedPath { segments, ts, idx: 0 }
-    }
+/// Collects the text content of all `Text` nodes in the tree.
+///
+/// Nodes inside `defs` are ignored, unless they are referenced by a `Use` node.
+/// Such nodes are returned for each `Use` with its transform applied.
+/// Nodes are returned in the rendering order.
+///
+/// Note: this method can be pretty expensive, since a bbox calculation
+/// requires a text layout.
+pub fn collect_text(
+    tree: &usvg::Tree,
+    backend: &Render,
+    opt: &Options,
+) -> Vec<TextContent> {
+    let mut list = Vec::new();
+    walk_rendered(&tree.root(), None, &mut |node, use_ts| {
+        if let usvg::NodeKind::Text(ref text) = *node.borrow() {
+            let mut s = String::new();
+            for chunk in &text.chunks {
+                for span in &chunk.spans {
+                    s.push_str(&span.text);
+                }
+            }
+
+            list.push(TextContent {
+                id: text.id.clone(),
+                text: s,
+                transform: used_transform(use_ts, abs_transform(node)),
+                bbox: calc_used_bbox(node, use_ts, backend, opt),
+            });
+        }
+    });
+
+    list
 }
 
-impl<'a> Iterator for TransformedPath<'a> {
-    type Item = usvg::PathSegment;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.idx == self.segments.len() {
-            return None;
-        }
+/// A clickable area of a hyperlink.
+#[derive(Clone, Debug)]
+pub struct LinkArea {
+    /// Link target URL.
+    pub href: String,
+    /// Link browsing context, like `_blank`.
+    pub target: Option<String>,
+    /// Link's absolute bounding box.
+    ///
+    /// In the root `viewBox` coordinates. Use `view_box_to_transform`
+    /// to map it into the image coordinates.
+    pub bbox: Rect,
+}
 
-        if self.ts.is_default() {
-            self.idx += 1;
-            return self.segments.get(self.idx - 1).cloned();
+/// Collects the areas of all hyperlinks in the tree.
+///
+/// Nodes inside `defs` are ignored, unless they are referenced by a `Use` node.
+/// Links without a visible content are ignored too.
+/// Links are returned in the rendering order, so when areas overlap,
+/// the last one should win.
+///
+/// Note: this method can be pretty expensive, since a bbox calculation
+/// may require a text layout.
+pub fn collect_links(
+    tree: &usvg::Tree,
+    backend: &Render,
+    opt: &Options,
+) -> Vec<LinkArea> {
+    let mut list = Vec::new();
+    walk_rendered(&tree.root(), None, &mut |node, use_ts| {
+        if let usvg::NodeKind::Group(ref g) = *node.borrow() {
+            let link = match g.link {
+                Some(ref link) => link,
+                None => return,
+            };
+
+            let bbox = match calc_used_bbox(node, use_ts, backend, opt) {
+                Some(bbox) => bbox,
+                None => return,
+            };
+
+            list.push(LinkArea {
+                href: link.href.clone(),
+                target: link.target.clone(),
+                bbox,
+            });
         }
+    });
 
-        let seg = match self.segments[self.idx] {
-            usvg::PathSegment::MoveTo { x, y } => {
-                let (x, y) = self.ts.apply(x, y);
-                usvg::PathSegment::MoveTo { x, y }
-            }
-            usvg::PathSegment::LineTo { x, y } => {
-                let (x, y) = self.ts.apply(x, y);
-                usvg::PathSegment::LineTo { x, y }
+    list
+}
+
+/// Visits all rendered nodes in the rendering order.
+///
+/// `Defs` are skipped, but the content referenced by a `Use` node is visited
+/// with the absolute `Use` transform, which should be prepended to the node transform.
+/// The content of the `defs` is not transformed by itself.
+fn walk_rendered<F>(parent: &usvg::Node, use_ts: Option<usvg::Transform>, f: &mut F)
+    where F: FnMut(&usvg::Node, Option<usvg::Transform>)
+{
+    for node in parent.children() {
+        match *node.borrow() {
+            usvg::NodeKind::Defs => {}
+            usvg::NodeKind::Use(ref u) => {
+                if let Some(def_node) = node.tree().defs_by_id(&u.link) {
+                    let mut ts = used_transform(use_ts, abs_transform(&node));
+                    ts.append(&u.transform);
+                    walk_rendered(&def_node, Some(ts), f);
+                }
             }
-            usvg::PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
-                let (x1, y1) = self.ts.apply(x1, y1);
-                let (x2, y2) = self.ts.apply(x2, y2);
-                let (x,  y)  = self.ts.apply(x, y);
-                usvg::PathSegment::CurveTo { x1, y1, x2, y2, x, y }
+            _ => {
+                f(&node, use_ts);
+                walk_rendered(&node, use_ts, f);
             }
-            usvg::PathSegment::ClosePath => usvg::PathSegment::ClosePath,
-        };
+        }
+    }
+}
+
+fn used_transform(use_ts: Option<usvg::Transform>, ts: usvg::Transform) -> usvg::Transform {
+    match use_ts {
+        Some(mut use_ts) => {
+            use_ts.append(&ts);
+            use_ts
+        }
+        None => ts,
+    }
+}
 
-        self.idx += 1;
+/// Calculates a node bbox with the `Use` transform applied.
+fn calc_used_bbox(
+    node: &usvg::Node,
+    use_ts: Option<usvg::Transform>,
+    backend: &Render,
+    opt: &Options,
+) -> Option<Rect> {
+    let bbox = backend.calc_node_bbox(node, opt).filter(|r| r.is_valid())?;
+    match use_ts {
+        Some(ts) => Some(path_bbox(&rect_to_path(bbox), None, &ts)),
+        None => Some(bbox),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use usvg::FuzzyEq;
+
+    /// A backend that treats every node as a 10x10 box at its origin.
+    struct TestBackend;
+
+    impl Render for TestBackend {
+        fn render_to_image(&self, _: &usvg::Tree, _: &Options) -> Option<Box<OutputImage>> {
+            None
+        }
+
+        fn render_node_to_image(&self, _: &usvg::Node, _: &Options) -> Option<Box<OutputImage>> {
+            None
+        }
+
+        fn calc_node_bbox(&self, node: &usvg::Node, _: &Options) -> Option<Rect> {
+            let mut ts = abs_transform(node);
+            ts.append(&node.transform());
+            Some(path_bbox(&rect_to_path(Rect::new(0.0, 0.0, 10.0, 10.0)), None, &ts))
+        }
+    }
+
+    fn parse(content: &str, opt: &Options) -> usvg::Tree {
+        let text = format!("<svg xmlns='http://www.w3.org/2000/svg' \
+                                 xmlns:xlink='http://www.w3.org/1999/xlink' \
+                                 width='200' height='200'>{}</svg>", content);
+        usvg::Tree::from_str(&text, &opt.usvg).unwrap()
+    }
+
+    fn assert_rect(r: Option<Rect>, x: f64, y: f64, width: f64, height: f64) {
+        let r = r.unwrap();
+        assert!(r.fuzzy_eq(&Rect::new(x, y, width, height)), "{:?}", r);
+    }
+
+    #[test]
+    fn collect_text() {
+        let opt = Options::default();
+        let tree = parse("
+            <defs>
+                <text id='hidden'>Hidden</text>
+            </defs>
+            <text id='text1'>Hello <tspan>world</tspan></text>
+            <g transform='translate(10 20)' opacity='0.5'>
+                <text id='text2' transform='scale(2)'>Text</text>
+            </g>", &opt);
+
+        let list = super::collect_text(&tree, &TestBackend, &opt);
+        assert_eq!(list.len(), 2);
+
+        assert_eq!(list[0].id, "text1");
+        assert_eq!(list[0].text, "Hello world");
+        assert_eq!(list[0].transform, usvg::Transform::default());
+        assert_rect(list[0].bbox, 0.0, 0.0, 10.0, 10.0);
+
+        assert_eq!(list[1].id, "text2");
+        assert_eq!(list[1].text, "Text");
+        assert_eq!(list[1].transform, usvg::Transform::new_translate(10.0, 20.0));
+        assert_rect(list[1].bbox, 10.0, 20.0, 20.0, 20.0);
+    }
+
+    #[test]
+    fn collect_text_from_use() {
+        let mut opt = Options::default();
+        opt.usvg.keep_use = true;
+        let tree = parse("
+            <defs>
+                <g id='g1'>
+                    <text id='text1'>Text</text>
+                </g>
+            </defs>
+            <use xlink:href='#g1' x='10'/>
+            <g transform='translate(0 20)' opacity='0.5'>
+                <use xlink:href='#g1' transform='scale(2)'/>
+            </g>", &opt);
+
+        let list = super::collect_text(&tree, &TestBackend, &opt);
+        assert_eq!(list.len(), 2);
+
+        assert_eq!(list[0].text, "Text");
+        assert_eq!(list[0].transform, usvg::Transform::new_translate(10.0, 0.0));
+        assert_rect(list[0].bbox, 10.0, 0.0, 10.0, 10.0);
+
+        assert_eq!(list[1].text, "Text");
+        assert_eq!(list[1].transform, usvg::Transform::new(2.0, 0.0, 0.0, 2.0, 0.0, 20.0));
+        assert_rect(list[1].bbox, 0.0, 20.0, 20.0, 20.0);
+    }
 
-        Some(seg)
+    #[test]
+    fn collect_links() {
+        let mut opt = Options::default();
+        opt.usvg.keep_use = true;
+        let tree = parse("
+            <defs>
+                <g id='g1'>
+                    <a xlink:href='used.html'>
+                        <rect width='10' height='10'/>
+                    </a>
+                </g>
+            </defs>
+            <a xlink:href='https://example.com/' target='_blank' transform='translate(0 50)'>
+                <rect width='10' height='10'/>
+            </a>
+            <use xlink:href='#g1' x='10'/>
+            <use xlink:href='#g1' x='30'/>", &opt);
+
+        let list = super::collect_links(&tree, &TestBackend, &opt);
+        assert_eq!(list.len(), 3);
+
+        assert_eq!(list[0].href, "https://example.com/");
+        assert_eq!(list[0].target, Some("_blank".to_string()));
+        assert_rect(Some(list[0].bbox), 0.0, 50.0, 10.0, 10.0);
+
+        assert_eq!(list[1].href, "used.html");
+        assert_eq!(list[1].target, None);
+        assert_rect(Some(list[1].bbox), 10.0, 0.0, 10.0, 10.0);
+
+        assert_eq!(list[2].href, "used.html");
+        assert_rect(Some(list[2].bbox), 30.0, 0.0, 10.0, 10.0);
     }
 }
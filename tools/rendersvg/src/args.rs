@@ -265,6 +265,7 @@ pub fn parse() -> Result<(Args, resvg::Options), String> {
             font_size: args.font_size as f64,
             languages,
             keep_named_groups,
+            .. usvg::Options::default()
         },
         fit_to,
         background: args.background,
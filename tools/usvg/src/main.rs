@@ -92,6 +92,15 @@ struct Args {
     #[options(no_short)]
     keep_named_groups: bool,
 
+    #[options(no_short)]
+    keep_markers: bool,
+
+    #[options(no_short)]
+    keep_use: bool,
+
+    #[options(no_short)]
+    load_external_files: bool,
+
     #[options(no_short, meta = "DPI", default = "96", parse(try_from_str = "parse_dpi"))]
     dpi: u32,
 
@@ -162,6 +171,9 @@ OPTIONS:
     -V, --version               Prints version information
     -c                          Prints the output SVG to the stdout
         --keep-named-groups     Disables removing of groups with non-empty ID
+        --keep-markers          Disables converting of markers into groups
+        --keep-use              Disables copying of elements referenced by 'use'
+        --load-external-files   Allows loading SVG files next to the input file
         --dpi DPI               Sets the resolution
                                 [default: 96] [possible values: 10..4000]
         --font-family FAMILY    Sets the default font family
@@ -227,6 +239,10 @@ fn process(args: &Args) -> Result<(), String> {
         font_size: args.font_size as f64,
         languages,
         keep_named_groups: args.keep_named_groups,
+        keep_markers: args.keep_markers,
+        keep_use: args.keep_use,
+        load_external_files: args.load_external_files,
+        .. usvg::Options::default()
     };
 
     let input_str = match in_svg {
@@ -62,8 +62,13 @@ pub fn convert_clip(
     }
 }
 
+/// Converts a mask.
+///
+/// `mode` is the `mask-mode` of the masked element. When it differs from the `mask-type`,
+/// a mask copy with a generated ID will be created.
 pub fn convert_mask(
     node: &svgdom::Node,
+    mode: Option<tree::MaskType>,
     state: &State,
     tree: &mut tree::Tree,
 ) -> Option<String> {
@@ -71,8 +76,16 @@ pub fn convert_mask(
         return None;
     }
 
-    if tree.defs_by_id(node.id().as_str()).is_some() {
-        return Some(node.id().clone());
+    let own_kind = convert_mask_type(node);
+    let kind = mode.unwrap_or(own_kind);
+    let id = if kind == own_kind {
+        node.id().clone()
+    } else {
+        gen_mask_id(node, kind)
+    };
+
+    if tree.defs_by_id(id.as_str()).is_some() {
+        return Some(id);
     }
 
     let rect = Rect::new(
@@ -90,7 +103,7 @@ pub fn convert_mask(
 
     let mut mask = None;
     if let Some(&AValue::FuncLink(ref link)) = attrs.get_value(AId::Mask) {
-        mask = convert_mask(link, state, tree);
+        mask = convert_mask(link, None, state, tree);
 
         // Linked `mask` must be valid.
         if mask.is_none() {
@@ -104,9 +117,10 @@ pub fn convert_mask(
                                               tree::Units::UserSpaceOnUse);
 
     let mut mask = tree.append_to_defs(tree::NodeKind::Mask(tree::Mask {
-        id: node.id().clone(),
+        id: id.clone(),
         units,
         content_units,
+        kind,
         rect,
         mask,
     }));
@@ -114,7 +128,7 @@ pub fn convert_mask(
     super::convert_children(node, state, &mut mask, tree);
 
     if mask.has_children() {
-        Some(node.id().clone())
+        Some(id)
     } else {
         mask.detach();
         None
@@ -128,3 +142,39 @@ fn convert_element_units(attrs: &svgdom::Attributes, aid: AId, def: tree::Units)
         _ => def,
     }
 }
+
+/// Resolves the `mask-mode` property of the masked element.
+///
+/// `match-source` means that the `mask-type` of the mask itself should be used.
+pub fn resolve_mask_mode(node: &svgdom::Node) -> Option<tree::MaskType> {
+    match node.attributes().get_value("mask-mode") {
+        Some(AValue::String(s)) if s == "alpha" => Some(tree::MaskType::Alpha),
+        Some(AValue::String(s)) if s == "luminance" => Some(tree::MaskType::Luminance),
+        _ => None,
+    }
+}
+
+fn convert_mask_type(node: &svgdom::Node) -> tree::MaskType {
+    // `mask-type` is not supported by `svgdom`.
+    match node.attributes().get_value("mask-type") {
+        Some(AValue::String(s)) if s == "alpha" => tree::MaskType::Alpha,
+        _ => tree::MaskType::Luminance,
+    }
+}
+
+/// Generates an ID for a mask copy with a different type.
+///
+/// Copies are shared between all elements with the same `mask-mode`.
+fn gen_mask_id(node: &svgdom::Node, kind: tree::MaskType) -> String {
+    let mut idx = 1;
+    loop {
+        let id = format!("{}-{}{}", node.id(), kind.to_string(), idx);
+
+        // Make sure that we don't use an ID from the original document.
+        if !node.root().descendants().any(|n| *n.id() == id) {
+            return id;
+        }
+
+        idx += 1;
+    }
+}
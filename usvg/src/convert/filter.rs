@@ -223,11 +223,12 @@ fn convert_fe_flood(fe: &svgdom::Node) -> tree::FilterKind {
     let attrs = fe.attributes();
 
     let color = attrs.get_color(AId::FloodColor).unwrap_or(tree::Color::black());
-    let opacity = fe.convert_opacity(AId::FloodOpacity);
+    let opacity = fe.convert_opacity(AId::FloodOpacity).value()
+                * fe.convert_color_alpha(AId::FloodColor).value();
 
     tree::FilterKind::FeFlood(tree::FeFlood {
         color,
-        opacity,
+        opacity: opacity.into(),
     })
 }
 
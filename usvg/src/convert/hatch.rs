@@ -0,0 +1,412 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! SVG 2 hatches support.
+//!
+//! `svgdom` doesn't know anything about hatches, so elements
+//! are detected by name and SVG 2 attributes are parsed manually.
+//!
+//! A hatch is converted into an equivalent pattern with a `pitch` wide tile.
+//! Hatch paths are repeated every `pitch` horizontally and every path period
+//! vertically, so they will stay continuous across the tile edges.
+//!
+//! Since a hatch is infinite vertically, the tile is as tall as the painted shape
+//! in the hatch coordinates. Therefore, each shape gets its own pattern in user space.
+//!
+//! The text bounding box is unknown during conversion, so hatches with
+//! `objectBoundingBox` units, like the default `hatchUnits`, cannot be used on text.
+//! Other hatches use a tile that covers the whole canvas instead.
+
+use std::f64;
+
+// external
+use svgdom;
+
+// self
+use tree;
+use super::prelude::*;
+use super::paint_server::ServerOrColor;
+use super::{
+    shapes,
+    style,
+    units,
+};
+
+
+/// The maximum number of hatch path copies in one direction.
+const MAX_COPIES: i32 = 1000;
+
+/// Checks that a node is a `hatch` element.
+pub fn is_hatch(node: &svgdom::Node) -> bool {
+    has_name(node, "hatch")
+}
+
+fn is_hatch_path(node: &svgdom::Node) -> bool {
+    // `hatchPath` was used by the older SVG 2 drafts.
+    has_name(node, "hatchpath")
+}
+
+fn has_name(node: &svgdom::Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().to_string().eq_ignore_ascii_case(name)
+}
+
+pub fn convert(
+    node: &svgdom::Node,
+    state: &State,
+    tree: &mut tree::Tree,
+) -> Option<ServerOrColor> {
+    let units = convert_units(node, "hatchUnits", tree::Units::ObjectBoundingBox);
+    let content_units = convert_units(node, "hatchContentUnits", tree::Units::UserSpaceOnUse);
+
+    let bbox = state.shape_bbox;
+    let uses_bbox = units == tree::Units::ObjectBoundingBox
+                    || content_units == tree::Units::ObjectBoundingBox;
+    if uses_bbox && bbox.is_none() {
+        warn!("Hatch '{}' with 'objectBoundingBox' units can be used only on shapes.", node.id());
+        return None;
+    }
+
+    // Content is scaled by the object bounding box size.
+    let (kx, ky) = match (content_units, bbox) {
+        (tree::Units::ObjectBoundingBox, Some(bbox)) => (bbox.object.width, bbox.object.height),
+        _ => (1.0, 1.0),
+    };
+
+    let mut x = node.convert_length(AId::X, units, state, Length::zero());
+    let mut y = node.convert_length(AId::Y, units, state, Length::zero());
+    let mut pitch = match parse_length(node, "pitch") {
+        Some(length) => units::convert_length(length, node, AId::Width, units, state),
+        None => 0.0,
+    };
+
+    if let (tree::Units::ObjectBoundingBox, Some(bbox)) = (units, bbox) {
+        let r = bbox.object;
+        x = r.x + x * r.width;
+        y = r.y + y * r.height;
+        pitch *= r.width;
+    }
+
+    // A zero pitch disables the hatch.
+    // Also, content in the bounding box units cannot be used on a zero-sized shape.
+    if !(pitch > 0.0 && kx > 0.0 && ky > 0.0) {
+        return None;
+    }
+
+    let mut transform = node.attributes().get_transform(AId::Transform);
+    transform.rotate(resolve_rotate(node));
+
+    // The tile must cover the whole painted area in the hatch coordinates.
+    let paint_rect = match bbox {
+        Some(bbox) => bbox.paint,
+        None => state.view_box,
+    };
+    let (top, bottom) = vertical_range(paint_rect, &transform)?;
+    let height = bottom - top;
+
+    // A tile in content units.
+    let content_pitch = pitch / kx;
+    let content_height = height / ky;
+    let content_dy = (y - top) / ky;
+
+    let paths_node = node.href_iter().find(|n| n.children().any(|c| is_hatch_path(&c)))?;
+
+    let mut paths = Vec::new();
+    for child in paths_node.children().filter(is_hatch_path) {
+        if let Some(segments) = convert_hatch_path(&child, content_dy, state) {
+            paths.push((child, segments));
+        }
+    }
+
+    if paths.is_empty() {
+        return None;
+    }
+
+    let id = gen_pattern_id(node, tree);
+    let mut patt = tree.append_to_defs(tree::NodeKind::Pattern(tree::Pattern {
+        id: id.clone(),
+        units: tree::Units::UserSpaceOnUse,
+        content_units,
+        transform,
+        rect: Rect::new(x, top, pitch, height),
+        view_box: None,
+    }));
+
+    for (child, segments) in paths {
+        // Only a stroke is allowed.
+        let stroke = style::resolve_stroke(&child, true, state, tree);
+        if stroke.is_none() {
+            continue;
+        }
+
+        let segments = match segments {
+            Some(segments) => {
+                repeat_segments(&segments, content_pitch, content_height)
+            }
+            None => {
+                let offset = resolve_offset(&child, state);
+                let line = [
+                    tree::PathSegment::MoveTo { x: offset, y: 0.0 },
+                    tree::PathSegment::LineTo { x: offset, y: content_height },
+                ];
+                repeat_segments(&line, content_pitch, content_height)
+            }
+        };
+
+        patt.append_kind(tree::NodeKind::Path(tree::Path {
+            id: String::new(),
+            transform: tree::Transform::default(),
+            visibility: tree::Visibility::Visible,
+            fill: None,
+            stroke,
+            paint_order: tree::PaintOrder::default(),
+            segments,
+            markers: Vec::new(),
+        }));
+    }
+
+    Some(ServerOrColor::Server {
+        id,
+        units: tree::Units::UserSpaceOnUse,
+    })
+}
+
+/// Returns a vertical range of a rect in the pattern coordinates.
+fn vertical_range(rect: Rect, ts: &tree::Transform) -> Option<(f64, f64)> {
+    let ts = invert_transform(ts)?;
+    let mut top = f64::MAX;
+    let mut bottom = f64::MIN;
+    for &(x, y) in &[(rect.x, rect.y), (rect.right(), rect.y),
+                     (rect.right(), rect.bottom()), (rect.x, rect.bottom())] {
+        let (_, y) = ts.apply(x, y);
+        top = top.min(y);
+        bottom = bottom.max(y);
+    }
+
+    if bottom - top > 0.0 {
+        Some((top, bottom))
+    } else {
+        None
+    }
+}
+
+fn invert_transform(ts: &tree::Transform) -> Option<tree::Transform> {
+    let det = ts.a * ts.d - ts.b * ts.c;
+    if det.is_fuzzy_zero() {
+        return None;
+    }
+
+    Some(tree::Transform::new(
+        ts.d / det,
+        -ts.b / det,
+        -ts.c / det,
+        ts.a / det,
+        (ts.c * ts.f - ts.d * ts.e) / det,
+        (ts.b * ts.e - ts.a * ts.f) / det,
+    ))
+}
+
+/// Generates a unique pattern ID.
+///
+/// Each shape gets its own pattern, so the hatch ID is used only by the first one.
+fn gen_pattern_id(node: &svgdom::Node, tree: &tree::Tree) -> String {
+    let id = node.id().clone();
+    let doc_root = node.ancestors().last().unwrap_or_else(|| node.clone());
+    let is_free = |new_id: &str| {
+           tree.defs_by_id(new_id).is_none()
+        && !doc_root.descendants().any(|n| n != *node && *n.id() == new_id)
+    };
+
+    let mut new_id = id.clone();
+    let mut idx = 1;
+    while !is_free(&new_id) {
+        new_id = format!("{}-{}", id, idx);
+        idx += 1;
+    }
+
+    new_id
+}
+
+fn convert_units(node: &svgdom::Node, name: &str, def: tree::Units) -> tree::Units {
+    match node.attributes().get_value(name) {
+        Some(AValue::String(s)) if s == "userSpaceOnUse" => tree::Units::UserSpaceOnUse,
+        Some(AValue::String(s)) if s == "objectBoundingBox" => tree::Units::ObjectBoundingBox,
+        _ => def,
+    }
+}
+
+fn parse_length(node: &svgdom::Node, name: &str) -> Option<Length> {
+    match node.attributes().get_value(name) {
+        Some(AValue::String(s)) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Resolves the `rotate` attribute.
+///
+/// `svgdom` parses `rotate` as a `text` attribute, so it can be a numbers list.
+fn resolve_rotate(node: &svgdom::Node) -> f64 {
+    match node.attributes().get_value(AId::Rotate) {
+        Some(AValue::Number(n)) => *n,
+        Some(AValue::NumberList(list)) => list.first().cloned().unwrap_or(0.0),
+        Some(AValue::String(s)) => s.trim().parse().unwrap_or(0.0),
+        _ => 0.0,
+    }
+}
+
+fn resolve_offset(node: &svgdom::Node, state: &State) -> f64 {
+    match node.attributes().get_value(AId::Offset) {
+        Some(AValue::Number(n)) => *n,
+        Some(AValue::Length(len)) => {
+            units::convert_length(*len, node, AId::X, tree::Units::UserSpaceOnUse, state)
+        }
+        _ => 0.0,
+    }
+}
+
+/// Converts a hatch path into segments relative to the tile.
+///
+/// `dy` is the hatch origin offset from the tile top.
+///
+/// Returns `Some(None)` for a hatch path without the `d` attribute,
+/// which is an infinite vertical line.
+fn convert_hatch_path(
+    node: &svgdom::Node,
+    dy: f64,
+    state: &State,
+) -> Option<Option<Vec<tree::PathSegment>>> {
+    if !node.has_attribute(AId::D) {
+        return Some(None);
+    }
+
+    let mut segments = shapes::convert_path(node)?;
+    let dx = resolve_offset(node, state);
+    for seg in &mut segments {
+        match *seg {
+            tree::PathSegment::MoveTo { ref mut x, ref mut y } |
+            tree::PathSegment::LineTo { ref mut x, ref mut y } => {
+                *x += dx;
+                *y += dy;
+            }
+            tree::PathSegment::CurveTo {
+                ref mut x1, ref mut y1, ref mut x2, ref mut y2, ref mut x, ref mut y
+            } => {
+                *x1 += dx;
+                *y1 += dy;
+                *x2 += dx;
+                *y2 += dy;
+                *x += dx;
+                *y += dy;
+            }
+            tree::PathSegment::ClosePath => {}
+        }
+    }
+
+    Some(Some(segments))
+}
+
+/// Returns a distance between the first and the last points along the Y axis.
+///
+/// A path is repeated with this period to be continuous.
+fn vertical_period(segments: &[tree::PathSegment]) -> f64 {
+    let first = segments.first().and_then(segment_y);
+    let last = segments.iter().rev().filter_map(segment_y).next();
+    match (first, last) {
+        (Some(y1), Some(y2)) => (y2 - y1).abs(),
+        _ => 0.0,
+    }
+}
+
+fn segment_y(seg: &tree::PathSegment) -> Option<f64> {
+    match *seg {
+        tree::PathSegment::MoveTo { y, .. } |
+        tree::PathSegment::LineTo { y, .. } |
+        tree::PathSegment::CurveTo { y, .. } => Some(y),
+        tree::PathSegment::ClosePath => None,
+    }
+}
+
+/// Repeats segments to cover the whole tile.
+///
+/// Copies are placed every `pitch` horizontally and every path period vertically,
+/// so parts that are clipped by one tile edge will be rendered by the opposite one.
+fn repeat_segments(
+    segments: &[tree::PathSegment],
+    pitch: f64,
+    height: f64,
+) -> Vec<tree::PathSegment> {
+    let (min_x, min_y, max_x, max_y) = segments_bounds(segments);
+    let period = vertical_period(segments);
+
+    let cols = copies_range(min_x, max_x, pitch, pitch);
+    let rows = if period.is_fuzzy_zero() {
+        (0, 0)
+    } else {
+        copies_range(min_y, max_y, height, period)
+    };
+
+    let mut new_segments = Vec::new();
+    for row in rows.0..(rows.1 + 1) {
+        for col in cols.0..(cols.1 + 1) {
+            let dx = col as f64 * pitch;
+            let dy = row as f64 * period;
+            for seg in segments {
+                new_segments.push(match *seg {
+                    tree::PathSegment::MoveTo { x, y } => {
+                        tree::PathSegment::MoveTo { x: x + dx, y: y + dy }
+                    }
+                    tree::PathSegment::LineTo { x, y } => {
+                        tree::PathSegment::LineTo { x: x + dx, y: y + dy }
+                    }
+                    tree::PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                        tree::PathSegment::CurveTo {
+                            x1: x1 + dx, y1: y1 + dy,
+                            x2: x2 + dx, y2: y2 + dy,
+                            x: x + dx, y: y + dy,
+                        }
+                    }
+                    tree::PathSegment::ClosePath => tree::PathSegment::ClosePath,
+                });
+            }
+        }
+    }
+
+    new_segments
+}
+
+/// Returns a range of copy indexes that will intersect the `0..size` range.
+fn copies_range(min: f64, max: f64, size: f64, period: f64) -> (i32, i32) {
+    let first = ((-max / period).floor() as i32).max(-MAX_COPIES);
+    let last = (((size - min) / period).ceil() as i32).min(MAX_COPIES);
+    (first, last)
+}
+
+/// Returns segments bounds including control points.
+fn segments_bounds(segments: &[tree::PathSegment]) -> (f64, f64, f64, f64) {
+    let mut bounds = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    {
+        let mut add = |x: f64, y: f64| {
+            bounds.0 = bounds.0.min(x);
+            bounds.1 = bounds.1.min(y);
+            bounds.2 = bounds.2.max(x);
+            bounds.3 = bounds.3.max(y);
+        };
+
+        for seg in segments {
+            match *seg {
+                tree::PathSegment::MoveTo { x, y } |
+                tree::PathSegment::LineTo { x, y } => {
+                    add(x, y);
+                }
+                tree::PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                    add(x1, y1);
+                    add(x2, y2);
+                    add(x, y);
+                }
+                tree::PathSegment::ClosePath => {}
+            }
+        }
+    }
+
+    bounds
+}
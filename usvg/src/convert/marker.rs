@@ -9,69 +9,87 @@ use utils;
 use tree;
 use tree::prelude::*;
 use tree::PathSegment as Segment;
+use tree::{
+    MarkerKind,
+    MarkerOrientation,
+};
 use super::prelude::*;
 use super::use_node;
+use super::ContextElement;
 
 
-pub fn convert(
-    node: &svgdom::Node,
-    segments: &[tree::PathSegment],
-    state: &State,
-    parent: &mut tree::Node,
-    tree: &mut tree::Tree,
-) {
-    // `marker-*` attributes can only be set on `path`, `line`, `polyline` and `polygon`.
+const MARKER_ATTRIBUTES: &[(AId, MarkerKind)] = &[
+    (AId::MarkerStart, MarkerKind::Start),
+    (AId::MarkerMid, MarkerKind::Middle),
+    (AId::MarkerEnd, MarkerKind::End),
+];
+
+/// Checks that the shape has any markers.
+pub fn is_valid(node: &svgdom::Node) -> bool {
+    // `marker-*` attributes can be set on all shapes since SVG 2.
     match node.tag_id() {
           Some(EId::Path)
         | Some(EId::Line)
         | Some(EId::Polyline)
-        | Some(EId::Polygon) => {}
-        _ => return,
+        | Some(EId::Polygon)
+        | Some(EId::Rect)
+        | Some(EId::Circle)
+        | Some(EId::Ellipse) => {}
+        _ => return false,
     }
 
     // `marker-*` attributes cannot be set on shapes inside the `clipPath`.
     if node.ancestors().any(|n| n.is_tag_name(EId::ClipPath)) {
-        return;
+        return false;
     }
 
-    let list = [
-        (AId::MarkerStart, MarkerKind::Start),
-        (AId::MarkerMid, MarkerKind::Middle),
-        (AId::MarkerEnd, MarkerKind::End),
-    ];
-
-    for (aid, kind) in &list {
-        let mut marker = None;
-        for n in node.ancestors() {
-            let attrs = n.attributes();
-            if let Some(&AValue::FuncLink(ref link)) = attrs.get_value(*aid) {
-                if link.is_tag_name(EId::Marker) {
-                    marker = Some(link.clone());
-                }
+    MARKER_ATTRIBUTES.iter().any(|&(aid, _)| find_marker(node, aid).is_some())
+}
+
+fn find_marker(node: &svgdom::Node, aid: AId) -> Option<svgdom::Node> {
+    let mut marker = None;
+    for n in node.ancestors() {
+        let attrs = n.attributes();
+        if let Some(&AValue::FuncLink(ref link)) = attrs.get_value(aid) {
+            if link.is_tag_name(EId::Marker) {
+                marker = Some(link.clone());
             }
         }
+    }
+
+    marker
+}
+
+/// Converts shape markers.
+///
+/// Markers are appended to the `parent` as groups.
+/// Markers kept by `Options::keep_markers` are returned instead.
+pub fn convert(
+    node: &svgdom::Node,
+    segments: &[tree::PathSegment],
+    context: &ContextElement,
+    state: &State,
+    parent: &mut tree::Node,
+    tree: &mut tree::Tree,
+) -> Vec<tree::MarkerInstance> {
+    let mut instances = Vec::new();
+
+    if !is_valid(node) {
+        return instances;
+    }
 
-        if let Some(marker) = marker {
+    for &(aid, kind) in MARKER_ATTRIBUTES {
+        if let Some(marker) = find_marker(node, aid) {
             // Check for recursive marker.
             if state.current_root == marker {
                 continue;
             }
 
-            resolve(node, segments, &marker, *kind, state, parent, tree);
+            resolve(node, segments, &marker, kind, context, state, parent, tree, &mut instances);
         }
     }
-}
-
-#[derive(Clone, Copy)]
-enum MarkerKind {
-    Start,
-    Middle,
-    End,
-}
 
-enum MarkerOrientation {
-    Auto,
-    Angle(f64),
+    instances
 }
 
 fn resolve(
@@ -79,11 +97,14 @@ fn resolve(
     segments: &[tree::PathSegment],
     marker_node: &svgdom::Node,
     marker_kind: MarkerKind,
+    context: &ContextElement,
     state: &State,
     parent: &mut tree::Node,
     tree: &mut tree::Tree,
+    instances: &mut Vec<tree::MarkerInstance>,
 ) {
     let stroke_scale = try_opt!(stroke_scale(shape_node, marker_node, state), ());
+    let stroke_width = shape_node.resolve_length(AId::StrokeWidth, state, 1.0);
 
     let r = convert_rect(marker_node, state);
     if !r.is_valid() {
@@ -97,42 +118,15 @@ fn resolve(
         }
     );
 
-    let has_overflow = {
-        let attrs = marker_node.attributes();
-        let overflow = attrs.get_str_or(AId::Overflow, "hidden");
-        overflow == "hidden" || overflow == "scroll"
-    };
-
-    let clip_path = if has_overflow {
-        let clip_rect = if let Some(vbox) = view_box {
-            Rect::new(vbox.rect.x, vbox.rect.y, vbox.rect.width, vbox.rect.height)
-        } else {
-            Rect::new(0.0, 0.0, r.width, r.height)
-        };
-
-        let id = use_node::gen_clip_path_id(shape_node, tree);
+    // The content of markers with a context paint depends on the shape,
+    // so they cannot be shared.
+    let keep = state.opt.keep_markers && !has_context_paint(marker_node);
 
-        let mut clip_path = tree.append_to_defs(
-            tree::NodeKind::ClipPath(tree::ClipPath {
-                id: id.clone(),
-                units: tree::Units::UserSpaceOnUse,
-                transform: tree::Transform::default(),
-                clip_path: None,
-            })
-        );
-
-        clip_path.append_kind(tree::NodeKind::Path(tree::Path {
-            id: String::new(),
-            transform: tree::Transform::default(),
-            visibility: tree::Visibility::Visible,
-            fill: Some(tree::Fill::default()),
-            stroke: None,
-            segments: utils::rect_to_path(clip_rect),
-        }));
-
-        Some(id)
-    } else {
+    let clip_path = if keep {
+        convert_definition(marker_node, r, view_box, state, tree);
         None
+    } else {
+        convert_clip_path(marker_node, r, view_box, state, tree)
     };
 
     let draw_marker = |x: f64, y: f64, idx: usize| {
@@ -140,6 +134,13 @@ fn resolve(
 
         let angle = match convert_orientation(&*marker_node.attributes()) {
             MarkerOrientation::Auto => calc_vertex_angle(&segments, idx),
+            MarkerOrientation::AutoStartReverse => {
+                let angle = calc_vertex_angle(&segments, idx);
+                match marker_kind {
+                    MarkerKind::Start => (angle + 180.0) % 360.0,
+                    _ => angle,
+                }
+            }
             MarkerOrientation::Angle(angle) => angle,
         };
 
@@ -158,6 +159,18 @@ fn resolve(
 
         ts.translate(-r.x, -r.y);
 
+        if keep {
+            instances.push(tree::MarkerInstance {
+                id: marker_node.id().clone(),
+                kind: marker_kind,
+                x,
+                y,
+                angle,
+                stroke_width,
+                transform: ts,
+            });
+            return;
+        }
 
         // TODO: do not create a group when no clipPath
         let mut g_node = parent.append_kind(tree::NodeKind::Group(tree::Group {
@@ -167,10 +180,14 @@ fn resolve(
             clip_path: clip_path.clone(),
             mask: None,
             filter: None,
+            blend_mode: tree::BlendMode::Normal,
+            isolate: false,
+            link: None,
         }));
 
         let mut marker_state = state.clone();
         marker_state.current_root = marker_node.clone();
+        marker_state.context_element = Some(context.clone());
         super::convert_children(marker_node, &marker_state, &mut g_node, tree);
 
         if !g_node.has_children() {
@@ -181,6 +198,67 @@ fn resolve(
     draw_markers(&segments, marker_kind, draw_marker);
 }
 
+/// Converts a marker into a `Marker`, which is shared by all instances.
+fn convert_definition(
+    marker_node: &svgdom::Node,
+    rect: Rect,
+    view_box: Option<tree::ViewBox>,
+    state: &State,
+    tree: &mut tree::Tree,
+) {
+    if tree.defs_by_id(marker_node.id().as_str()).is_some() {
+        return;
+    }
+
+    let clip_path = convert_clip_path(marker_node, rect, view_box, state, tree);
+
+    let units = match marker_node.attributes().get_str_or(AId::MarkerUnits, "strokeWidth") {
+        "userSpaceOnUse" => tree::MarkerUnits::UserSpaceOnUse,
+        _ => tree::MarkerUnits::StrokeWidth,
+    };
+
+    let mut marker = tree.append_to_defs(tree::NodeKind::Marker(tree::Marker {
+        id: marker_node.id().clone(),
+        units,
+        rect,
+        view_box,
+        orientation: convert_orientation(&*marker_node.attributes()),
+        clip_path,
+    }));
+
+    let mut marker_state = state.clone();
+    marker_state.current_root = marker_node.clone();
+    super::convert_children(marker_node, &marker_state, &mut marker, tree);
+}
+
+fn convert_clip_path(
+    marker_node: &svgdom::Node,
+    rect: Rect,
+    view_box: Option<tree::ViewBox>,
+    state: &State,
+    tree: &mut tree::Tree,
+) -> Option<String> {
+    if !use_node::has_overflow_clip(marker_node) {
+        return None;
+    }
+
+    let clip_rect = if let Some(vbox) = view_box {
+        Rect::new(vbox.rect.x, vbox.rect.y, vbox.rect.width, vbox.rect.height)
+    } else {
+        Rect::new(0.0, 0.0, rect.width, rect.height)
+    };
+
+    let clip_rect = use_node::apply_clip_property(marker_node, clip_rect, state);
+    Some(use_node::gen_clip_path(marker_node, clip_rect, tree))
+}
+
+/// Checks that the marker content uses `context-fill` or `context-stroke`.
+fn has_context_paint(marker_node: &svgdom::Node) -> bool {
+    marker_node.descendants().any(|n| {
+        n.has_attribute("usvg-fill-context") || n.has_attribute("usvg-stroke-context")
+    })
+}
+
 fn stroke_scale(
     path_node: &svgdom::Node,
     marker_node: &svgdom::Node,
@@ -294,7 +372,7 @@ fn calc_vertex_angle(segments: &[Segment], idx: usize) -> f64 {
             }
             (Segment::CurveTo { x2, y2, x, y, .. }, Segment::ClosePath) => {
                 let (px, py) = get_prev_vertex(segments, idx);
-                let (nx, ny) = get_subpath_start(segments, idx);
+                let (nx, ny) = get_closing_vertex(segments, idx, x, y);
                 calc_curves_angle(
                     px, py, x2, y2,
                     x, y,
@@ -367,6 +445,15 @@ fn calc_vertex_angle(segments: &[Segment], idx: usize) -> f64 {
                 calc_angle(px, py, x, y,
                            x, y, nx, ny)
             }
+            (Segment::CurveTo { x2, y2, x, y, .. }, Segment::ClosePath) => {
+                let (px, py) = get_prev_vertex(segments, idx);
+                let (nx, ny) = get_closing_vertex(segments, idx, x, y);
+                calc_curves_angle(
+                    px, py, x2, y2,
+                    x, y,
+                    nx, ny, nx, ny,
+                )
+            }
             (_, Segment::ClosePath) => {
                 let (px, py) = get_prev_vertex(segments, idx);
                 let (nx, ny) = get_subpath_start(segments, idx);
@@ -444,6 +531,33 @@ fn get_subpath_start(segments: &[Segment], idx: usize) -> (f64, f64) {
     return (0.0, 0.0)
 }
 
+/// Returns the next vertex of a closing segment.
+///
+/// When the subpath already ends at its start, the closing segment has a zero length
+/// and the direction of the first subpath segment is used instead.
+fn get_closing_vertex(segments: &[Segment], idx: usize, x: f64, y: f64) -> (f64, f64) {
+    let (sx, sy) = get_subpath_start(segments, idx);
+    if !(x.fuzzy_eq(&sx) && y.fuzzy_eq(&sy)) {
+        return (sx, sy);
+    }
+
+    let start_idx = segments[..idx].iter()
+        .rposition(|seg| if let Segment::MoveTo { .. } = *seg { true } else { false })
+        .unwrap_or(0);
+
+    match segments.get(start_idx + 1) {
+        Some(&Segment::LineTo { x, y }) => (x, y),
+        Some(&Segment::CurveTo { x1, y1, x, y, .. }) => {
+            if x1.fuzzy_eq(&sx) && y1.fuzzy_eq(&sy) {
+                (x, y)
+            } else {
+                (x1, y1)
+            }
+        }
+        _ => (sx, sy),
+    }
+}
+
 fn get_prev_vertex(segments: &[Segment], idx: usize) -> (f64, f64) {
     match segments[idx - 1] {
         Segment::MoveTo { x, y } => (x, y),
@@ -476,6 +590,9 @@ fn convert_orientation(attrs: &svgdom::Attributes) -> MarkerOrientation {
         Some(AValue::String(s)) if s == "auto" => {
             MarkerOrientation::Auto
         }
+        Some(AValue::String(s)) if s == "auto-start-reverse" => {
+            MarkerOrientation::AutoStartReverse
+        }
         _ => {
             MarkerOrientation::Angle(0.0)
         }
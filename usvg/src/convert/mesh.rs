@@ -0,0 +1,462 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! SVG 2 mesh gradients support.
+//!
+//! `svgdom` doesn't know anything about mesh gradients, so elements
+//! are detected by name and the `path` attribute is parsed manually.
+//!
+//! Bicubic meshes are converted into bilinear ones by splitting each patch
+//! into smaller patches with bicubically interpolated corner colors.
+
+// external
+use svgdom;
+
+// self
+use tree;
+use super::prelude::*;
+use super::paint_server::ServerOrColor;
+
+
+/// The number of sub-patches per patch side for bicubic meshes.
+const BICUBIC_SUBDIVISIONS: usize = 4;
+
+/// Checks that a node is a `meshgradient` element.
+pub fn is_mesh_gradient(node: &svgdom::Node) -> bool {
+    // `meshGradient` was used by the older SVG 2 drafts and Inkscape 0.91.
+    has_name(node, "meshgradient")
+}
+
+fn has_name(node: &svgdom::Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().to_string().eq_ignore_ascii_case(name)
+}
+
+pub fn convert(
+    node: &svgdom::Node,
+    state: &State,
+    tree: &mut tree::Tree,
+) -> Option<ServerOrColor> {
+    let attrs = node.attributes();
+
+    let units = match attrs.get_str(AId::GradientUnits) {
+        Some("userSpaceOnUse") => tree::Units::UserSpaceOnUse,
+        _ => tree::Units::ObjectBoundingBox,
+    };
+
+    let x = node.convert_length(AId::X, units, state, Length::zero());
+    let y = node.convert_length(AId::Y, units, state, Length::zero());
+    let transform = attrs.get_transform(AId::GradientTransform);
+
+    let mut rows = match convert_rows(node, Point::new(x, y)) {
+        Some(rows) => rows,
+        None => {
+            warn!("Mesh gradient '{}' is invalid. Skipped.", node.id());
+            return None;
+        }
+    };
+
+    if attrs.get_str(AId::Type) == Some("bicubic") {
+        rows = subdivide_bicubic(&rows);
+    }
+
+    tree.append_to_defs(
+        tree::NodeKind::MeshGradient(tree::MeshGradient {
+            id: node.id().clone(),
+            units,
+            transform,
+            rows,
+        })
+    );
+
+    Some(ServerOrColor::Server {
+        id: node.id().clone(),
+        units,
+    })
+}
+
+/// A partially resolved patch.
+///
+/// Corners and edges shared with the previous patches are resolved first,
+/// the rest are read from `stop` elements.
+#[derive(Default)]
+struct PatchBuilder {
+    points: [Option<Point>; 12],
+    colors: [Option<(tree::Color, tree::Opacity)>; 4],
+}
+
+fn convert_rows(node: &svgdom::Node, start: Point) -> Option<Vec<Vec<tree::MeshPatch>>> {
+    let mut rows: Vec<Vec<tree::MeshPatch>> = Vec::new();
+    for row_node in node.children().filter(|n| has_name(n, "meshrow")) {
+        let mut row: Vec<tree::MeshPatch> = Vec::new();
+        for patch_node in row_node.children().filter(|n| has_name(n, "meshpatch")) {
+            let mut patch = PatchBuilder::default();
+
+            if let Some(above) = rows.last().and_then(|r| r.get(row.len())) {
+                // The top edge is the bottom edge of the patch above.
+                patch.points[0] = Some(above.points[9]);
+                patch.points[1] = Some(above.points[8]);
+                patch.points[2] = Some(above.points[7]);
+                patch.points[3] = Some(above.points[6]);
+                patch.colors[0] = Some((above.colors[3], above.opacities[3]));
+                patch.colors[1] = Some((above.colors[2], above.opacities[2]));
+            }
+
+            if let Some(left) = row.last() {
+                // The left edge is the right edge of the previous patch.
+                patch.points[0] = Some(left.points[3]);
+                patch.points[11] = Some(left.points[4]);
+                patch.points[10] = Some(left.points[5]);
+                patch.points[9] = Some(left.points[6]);
+                patch.colors[0] = Some((left.colors[1], left.opacities[1]));
+                patch.colors[3] = Some((left.colors[2], left.opacities[2]));
+            }
+
+            if rows.is_empty() && row.is_empty() {
+                patch.points[0] = Some(start);
+            }
+
+            // Shared edges are omitted.
+            let has_top = !rows.is_empty();
+            let has_left = !row.is_empty();
+            let edges = (0..4).filter(|&i| !(i == 0 && has_top) && !(i == 3 && has_left));
+
+            let stops = patch_node.children().filter(|n| n.is_tag_name(EId::Stop));
+            for (edge, stop) in edges.zip(stops) {
+                resolve_edge(&stop, edge, &mut patch)?;
+            }
+
+            row.push(build_patch(&patch)?);
+        }
+
+        if row.is_empty() {
+            continue;
+        }
+
+        // All rows must have the same number of patches.
+        if let Some(first) = rows.first() {
+            if first.len() != row.len() {
+                return None;
+            }
+        }
+
+        rows.push(row);
+    }
+
+    if rows.is_empty() {
+        return None;
+    }
+
+    Some(rows)
+}
+
+/// Resolves a patch edge and its start corner color from a `stop` element.
+fn resolve_edge(stop: &svgdom::Node, edge: usize, patch: &mut PatchBuilder) -> Option<()> {
+    let start_idx = edge * 3;
+    let end_idx = (edge * 3 + 3) % 12;
+    let start = patch.points[start_idx]?;
+
+    let path = match stop.attributes().get_value(AId::Path) {
+        Some(AValue::String(path)) => path.clone(),
+        _ => return None,
+    };
+
+    let (ctrl1, ctrl2, end) = parse_edge(&path, start)?;
+    let end = match patch.points[end_idx] {
+        // Shared corners are already known and have a priority.
+        Some(p) => p,
+        None => end?,
+    };
+
+    let (ctrl1, ctrl2) = match (ctrl1, ctrl2) {
+        (Some(c1), Some(c2)) => (c1, c2),
+        // A line.
+        _ => {
+            let third = |t: f64| {
+                Point::new(start.x + (end.x - start.x) * t, start.y + (end.y - start.y) * t)
+            };
+
+            (third(1.0 / 3.0), third(2.0 / 3.0))
+        }
+    };
+
+    patch.points[start_idx + 1] = Some(ctrl1);
+    patch.points[start_idx + 2] = Some(ctrl2);
+    patch.points[end_idx] = Some(end);
+
+    if patch.colors[edge].is_none() {
+        let color = stop.attributes().get_color(AId::StopColor)
+                        .unwrap_or(svgdom::Color::black());
+
+        let opacity = stop.convert_opacity(AId::StopOpacity).value()
+                    * stop.convert_color_alpha(AId::StopColor).value();
+
+        patch.colors[edge] = Some((color, opacity.into()));
+    }
+
+    Some(())
+}
+
+fn build_patch(patch: &PatchBuilder) -> Option<tree::MeshPatch> {
+    let mut points = [Point::new(0.0, 0.0); 12];
+    for (i, p) in patch.points.iter().enumerate() {
+        points[i] = (*p)?;
+    }
+
+    let mut colors = [tree::Color::black(); 4];
+    let mut opacities = [tree::Opacity::default(); 4];
+    for (i, c) in patch.colors.iter().enumerate() {
+        let (color, opacity) = (*c)?;
+        colors[i] = color;
+        opacities[i] = opacity;
+    }
+
+    Some(tree::MeshPatch { points, colors, opacities })
+}
+
+/// Parses a patch edge, like `c 25,-25 75,25 100,0`.
+///
+/// Only a single `l`, `L`, `c` or `C` segment is allowed.
+/// The end point can be omitted when it's already known.
+///
+/// Returns control points, if any, and the end point.
+fn parse_edge(text: &str, start: Point) -> Option<(Option<Point>, Option<Point>, Option<Point>)> {
+    let text = text.trim();
+    let cmd = text.chars().next()?;
+
+    let mut numbers = Vec::new();
+    for n in text[cmd.len_utf8()..].split(|c: char| c == ',' || c.is_whitespace()) {
+        if !n.is_empty() {
+            let n: f64 = n.parse().ok()?;
+            numbers.push(n);
+        }
+    }
+
+    let is_relative = cmd.is_lowercase();
+    let to_point = |i: usize| {
+        let x = *numbers.get(i)?;
+        let y = *numbers.get(i + 1)?;
+        if is_relative {
+            Some(Point::new(start.x + x, start.y + y))
+        } else {
+            Some(Point::new(x, y))
+        }
+    };
+
+    match cmd {
+        'l' | 'L' => {
+            Some((None, None, to_point(0)))
+        }
+        'c' | 'C' => {
+            Some((Some(to_point(0)?), Some(to_point(2)?), to_point(4)))
+        }
+        _ => None,
+    }
+}
+
+type Rgba = [f64; 4];
+
+/// Splits patches of a bicubic mesh.
+///
+/// Corner color derivatives are estimated from the neighbor corners,
+/// like in the Catmull-Rom spline, and colors are interpolated
+/// using the bicubic Hermite interpolation.
+///
+/// Since patch edges are cubic curves, an isoparametric curve of a Coons patch
+/// is a cubic curve too, so sub-patches represent the original patch exactly.
+fn subdivide_bicubic(rows: &[Vec<tree::MeshPatch>]) -> Vec<Vec<tree::MeshPatch>> {
+    let rows_count = rows.len();
+    let cols_count = rows[0].len();
+
+    // Corner colors grid.
+    let mut grid = vec![vec![[0.0; 4]; cols_count + 1]; rows_count + 1];
+    for (r, row) in rows.iter().enumerate() {
+        for (c, patch) in row.iter().enumerate() {
+            for &(i, dr, dc) in &[(0, 0, 0), (1, 0, 1), (2, 1, 1), (3, 1, 0)] {
+                grid[r + dr][c + dc] = to_rgba(patch, i);
+            }
+        }
+    }
+
+    let derivative = |prev: Option<Rgba>, curr: Rgba, next: Option<Rgba>| {
+        let mut d = [0.0; 4];
+        for k in 0..4 {
+            d[k] = match (prev, next) {
+                (Some(prev), Some(next)) => (next[k] - prev[k]) / 2.0,
+                (None, Some(next)) => next[k] - curr[k],
+                (Some(prev), None) => curr[k] - prev[k],
+                (None, None) => 0.0,
+            };
+        }
+
+        d
+    };
+
+    // Derivatives along rows (u) and columns (v).
+    let mut du = grid.clone();
+    let mut dv = grid.clone();
+    for r in 0..(rows_count + 1) {
+        for c in 0..(cols_count + 1) {
+            let left = if c > 0 { Some(grid[r][c - 1]) } else { None };
+            let right = grid[r].get(c + 1).cloned();
+            du[r][c] = derivative(left, grid[r][c], right);
+
+            let top = if r > 0 { Some(grid[r - 1][c]) } else { None };
+            let bottom = grid.get(r + 1).map(|row| row[c]);
+            dv[r][c] = derivative(top, grid[r][c], bottom);
+        }
+    }
+
+    let n = BICUBIC_SUBDIVISIONS;
+    let mut new_rows = Vec::with_capacity(rows_count * n);
+    for (r, row) in rows.iter().enumerate() {
+        for j in 0..n {
+            let mut new_row = Vec::with_capacity(cols_count * n);
+            for (c, patch) in row.iter().enumerate() {
+                let corners = [grid[r][c], grid[r][c + 1], grid[r + 1][c + 1], grid[r + 1][c]];
+                let corners_du = [du[r][c], du[r][c + 1], du[r + 1][c + 1], du[r + 1][c]];
+                let corners_dv = [dv[r][c], dv[r][c + 1], dv[r + 1][c + 1], dv[r + 1][c]];
+                let color_at = |u: f64, v: f64| {
+                    hermite_color(&corners, &corners_du, &corners_dv, u, v)
+                };
+
+                for i in 0..n {
+                    let u0 = i as f64 / n as f64;
+                    let u1 = (i + 1) as f64 / n as f64;
+                    let v0 = j as f64 / n as f64;
+                    let v1 = (j + 1) as f64 / n as f64;
+
+                    let mut sub_patch = sub_patch_geometry(&patch.points, u0, u1, v0, v1);
+                    let uv = [(u0, v0), (u1, v0), (u1, v1), (u0, v1)];
+                    for (k, &(u, v)) in uv.iter().enumerate() {
+                        let (color, opacity) = from_rgba(color_at(u, v));
+                        sub_patch.colors[k] = color;
+                        sub_patch.opacities[k] = opacity;
+                    }
+
+                    new_row.push(sub_patch);
+                }
+            }
+
+            new_rows.push(new_row);
+        }
+    }
+
+    new_rows
+}
+
+fn to_rgba(patch: &tree::MeshPatch, i: usize) -> Rgba {
+    let c = patch.colors[i];
+    [c.red as f64, c.green as f64, c.blue as f64, patch.opacities[i].value() * 255.0]
+}
+
+fn from_rgba(rgba: Rgba) -> (tree::Color, tree::Opacity) {
+    let to_byte = |n: f64| f64_bound(0.0, n.round(), 255.0) as u8;
+    let color = tree::Color::new(to_byte(rgba[0]), to_byte(rgba[1]), to_byte(rgba[2]));
+    (color, (f64_bound(0.0, rgba[3], 255.0) / 255.0).into())
+}
+
+/// Evaluates a bicubic Hermite patch with zero twist vectors.
+///
+/// Corners are in the top-left, top-right, bottom-right and bottom-left order.
+fn hermite_color(corners: &[Rgba; 4], du: &[Rgba; 4], dv: &[Rgba; 4], u: f64, v: f64) -> Rgba {
+    let h = |t: f64| {
+        let t2 = t * t;
+        let t3 = t2 * t;
+        // Start value, end value, start derivative and end derivative weights.
+        (2.0 * t3 - 3.0 * t2 + 1.0, -2.0 * t3 + 3.0 * t2, t3 - 2.0 * t2 + t, t3 - t2)
+    };
+
+    let (u_start, u_end, u_start_d, u_end_d) = h(u);
+    let (v_start, v_end, v_start_d, v_end_d) = h(v);
+
+    // Corner index, value weights and derivative weights.
+    let weights = [
+        (0, u_start, v_start, u_start_d, v_start_d),
+        (1, u_end, v_start, u_end_d, v_start_d),
+        (2, u_end, v_end, u_end_d, v_end_d),
+        (3, u_start, v_end, u_start_d, v_end_d),
+    ];
+
+    let mut rgba = [0.0; 4];
+    for &(i, wu, wv, wdu, wdv) in &weights {
+        for k in 0..4 {
+            rgba[k] += corners[i][k] * wu * wv
+                     + du[i][k] * wdu * wv
+                     + dv[i][k] * wu * wdv;
+        }
+    }
+
+    rgba
+}
+
+/// Returns a part of a Coons patch. Colors are left unset.
+fn sub_patch_geometry(p: &[Point; 12], u0: f64, u1: f64, v0: f64, v1: f64) -> tree::MeshPatch {
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+    let mut points = [Point::new(0.0, 0.0); 12];
+    let edges = [
+        // top, left to right
+        (u0, v0, u1, v0),
+        // right, top to bottom
+        (u1, v0, u1, v1),
+        // bottom, right to left
+        (u1, v1, u0, v1),
+        // left, bottom to top
+        (u0, v1, u0, v0),
+    ];
+
+    for (i, &(su, sv, eu, ev)) in edges.iter().enumerate() {
+        let at = |t: f64| coons_point(p, lerp(su, eu, t), lerp(sv, ev, t));
+        let (q0, q1, q2, q3) = (at(0.0), at(1.0 / 3.0), at(2.0 / 3.0), at(1.0));
+
+        // A cubic curve passing through four points with uniform parameters.
+        let ctrl = |a: f64, b: f64, c: f64, d: f64| {
+            Point::new(
+                (a * q0.x + b * q1.x + c * q2.x + d * q3.x) / 6.0,
+                (a * q0.y + b * q1.y + c * q2.y + d * q3.y) / 6.0,
+            )
+        };
+
+        points[i * 3] = q0;
+        points[i * 3 + 1] = ctrl(-5.0, 18.0, -9.0, 2.0);
+        points[i * 3 + 2] = ctrl(2.0, -9.0, 18.0, -5.0);
+    }
+
+    tree::MeshPatch {
+        points,
+        colors: [tree::Color::black(); 4],
+        opacities: [tree::Opacity::default(); 4],
+    }
+}
+
+/// Evaluates a Coons patch at the specified parametric coordinates.
+fn coons_point(p: &[Point; 12], u: f64, v: f64) -> Point {
+    let top = cubic(p[0], p[1], p[2], p[3], u);
+    let bottom = cubic(p[9], p[8], p[7], p[6], u);
+    let left = cubic(p[0], p[11], p[10], p[9], v);
+    let right = cubic(p[3], p[4], p[5], p[6], v);
+
+    // Bilinear interpolation of corners.
+    let w = [(1.0 - u) * (1.0 - v), u * (1.0 - v), u * v, (1.0 - u) * v];
+    let cx = w[0] * p[0].x + w[1] * p[3].x + w[2] * p[6].x + w[3] * p[9].x;
+    let cy = w[0] * p[0].y + w[1] * p[3].y + w[2] * p[6].y + w[3] * p[9].y;
+
+    Point::new(
+        (1.0 - v) * top.x + v * bottom.x + (1.0 - u) * left.x + u * right.x - cx,
+        (1.0 - v) * top.y + v * bottom.y + (1.0 - u) * left.y + u * right.y - cy,
+    )
+}
+
+fn cubic(p0: Point, p1: Point, p2: Point, p3: Point, t: f64) -> Point {
+    let mt = 1.0 - t;
+    let a = mt * mt * mt;
+    let b = 3.0 * mt * mt * t;
+    let c = 3.0 * mt * t * t;
+    let d = t * t * t;
+
+    Point::new(
+        a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+        a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+    )
+}
@@ -2,6 +2,8 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::f64;
+
 // external
 use svgdom::{
     self,
@@ -15,6 +17,7 @@ use tree;
 use tree::prelude::*;
 use short::*;
 use geom::*;
+use utils;
 use {
     Error,
     Options,
@@ -24,8 +27,10 @@ pub use self::svgdom_ext::IsDefault;
 
 mod clip_and_mask;
 mod filter;
+mod hatch;
 mod image;
 mod marker;
+mod mesh;
 mod paint_server;
 mod path;
 mod preprocess;
@@ -61,8 +66,13 @@ use self::svgdom_ext::*;
 #[derive(Clone)]
 pub struct State<'a> {
     current_root: svgdom::Node,
+    context_element: Option<ContextElement>,
     size: Size,
     view_box: Rect,
+    /// A shape whose fill and stroke are being resolved.
+    ///
+    /// Used by paint servers that depend on the shape bounding box during conversion.
+    shape_bbox: Option<ShapeBBox>,
     opt: &'a Options,
 }
 
@@ -72,6 +82,24 @@ impl<'a> State<'a> {
     }
 }
 
+/// Bounding boxes of a shape.
+#[derive(Clone, Copy)]
+pub struct ShapeBBox {
+    /// The object bounding box.
+    pub object: Rect,
+    /// An area that can be painted by the fill or the stroke.
+    pub paint: Rect,
+}
+
+/// An element that instantiates a marker or a `use`.
+///
+/// Its fill and stroke are used by `context-fill` and `context-stroke`.
+#[derive(Clone)]
+pub struct ContextElement {
+    pub fill: Option<tree::Fill>,
+    pub stroke: Option<tree::Stroke>,
+}
+
 
 /// Converts an input `Document` into a `Tree`.
 ///
@@ -95,7 +123,17 @@ pub fn convert_doc(
         return Ok(gen_empty_tree());
     };
 
-    let size = resolve_svg_size(&svg, opt)?;
+    let mut detect_size = false;
+    let size = match resolve_svg_size(&svg, opt) {
+        Ok(size) => size,
+        Err(Error::InvalidSize) if opt.detect_size && svg.get_viewbox().is_none() => {
+            // Use a default size for now. The actual one will be set after the conversion.
+            // So percentage lengths will be resolved against the default size.
+            detect_size = true;
+            opt.default_size
+        }
+        Err(e) => return Err(e),
+    };
 
     let view_box = {
         let attrs = svg.attributes();
@@ -121,8 +159,10 @@ pub fn convert_doc(
 
     let state = State {
         current_root: svg.clone(),
+        context_element: None,
         size,
         view_box: view_box.rect,
+        shape_bbox: None,
         opt: &opt,
     };
 
@@ -134,14 +174,118 @@ pub fn convert_doc(
     ungroup_groups(&mut tree, opt);
     remove_unused_defs(&mut tree);
 
+    if detect_size {
+        let rect = match calc_content_bbox(&tree.root(), tree::Transform::default()) {
+            Some(bbox) if bbox.is_valid() => bbox,
+            _ => opt.default_size.to_rect(0.0, 0.0),
+        };
+
+        if let tree::NodeKind::Svg(ref mut svg) = *tree.root().borrow_mut() {
+            svg.size = rect.size();
+            svg.view_box.rect = rect;
+        }
+    }
+
     Ok(tree)
 }
 
+/// Calculates the bounding box of paths and images.
+///
+/// Text is ignored, since we don't know the font metrics.
+/// Elements inside `Defs` are taken into account only via `Use` and markers.
+fn calc_content_bbox(node: &tree::Node, ts: tree::Transform) -> Option<Rect> {
+    let mut ts = ts;
+    ts.append(&node.transform());
+
+    match *node.borrow() {
+        tree::NodeKind::Path(ref path) => {
+            if path.segments.is_empty() {
+                return None;
+            }
+
+            let mut bbox = Some(utils::path_bbox_with_scaled_stroke(&path.segments, path.stroke.as_ref(), &ts));
+
+            for instance in &path.markers {
+                if let Some(marker_node) = node.tree().defs_by_id(&instance.id) {
+                    let mut marker_ts = ts;
+                    marker_ts.append(&instance.transform);
+
+                    for child in marker_node.children() {
+                        bbox = merge_bbox(bbox, calc_content_bbox(&child, marker_ts));
+                    }
+                }
+            }
+
+            bbox
+        }
+        tree::NodeKind::Image(ref img) => {
+            let segments = utils::rect_to_path(img.view_box.rect);
+            Some(utils::path_bbox(&segments, None, &ts))
+        }
+        tree::NodeKind::Svg(_) | tree::NodeKind::Group(_) => {
+            let mut bbox: Option<Rect> = None;
+            for child in node.children() {
+                bbox = merge_bbox(bbox, calc_content_bbox(&child, ts));
+            }
+
+            bbox
+        }
+        tree::NodeKind::Use(ref u) => {
+            let def_node = node.tree().defs_by_id(&u.link)?;
+            let mut bbox: Option<Rect> = None;
+            for child in def_node.children() {
+                bbox = merge_bbox(bbox, calc_content_bbox(&child, ts));
+            }
+
+            bbox
+        }
+        _ => None,
+    }
+}
+
+fn calc_shape_bbox(
+    node: &svgdom::Node,
+    segments: &[tree::PathSegment],
+    state: &State,
+) -> ShapeBBox {
+    let object = utils::path_bbox(segments, None, &tree::Transform::default());
+
+    // A miter join can be up to `miterlimit` times longer than the half of the stroke width.
+    // The stroke can be disabled, but a larger area is harmless.
+    let width = node.resolve_length(AId::StrokeWidth, state, 1.0).max(0.0);
+    let miterlimit = node.resolve_length(AId::StrokeMiterlimit, state, 4.0);
+    let margin = width / 2.0 * miterlimit.max(f64::consts::SQRT_2);
+    let paint = Rect::new(
+        object.x - margin,
+        object.y - margin,
+        object.width + margin * 2.0,
+        object.height + margin * 2.0,
+    );
+
+    ShapeBBox { object, paint }
+}
+
+fn merge_bbox(bbox: Option<Rect>, c_bbox: Option<Rect>) -> Option<Rect> {
+    match (bbox, c_bbox) {
+        (Some(bbox), Some(c_bbox)) => {
+            let x = bbox.x.min(c_bbox.x);
+            let y = bbox.y.min(c_bbox.y);
+            let right = bbox.right().max(c_bbox.right());
+            let bottom = bbox.bottom().max(c_bbox.bottom());
+            Some(Rect::new(x, y, right - x, bottom - y))
+        }
+        (bbox, None) => bbox,
+        (None, c_bbox) => c_bbox,
+    }
+}
+
 fn resolve_svg_size(svg: &svgdom::Node, opt: &Options) -> Result<Size, Error> {
     let mut state = State {
         current_root: svg.clone(),
+        context_element: None,
         size: Size::new(100.0, 100.0),
         view_box: Rect::new(0.0, 0.0, 100.0, 100.0),
+        shape_bbox: None,
         opt,
     };
 
@@ -325,7 +469,10 @@ fn convert_group(
     let clip_path = resolve_link!(AId::ClipPath, clip_and_mask::convert_clip);
 
     let mask = if !state.is_in_clip_path() {
-        resolve_link!(AId::Mask, clip_and_mask::convert_mask)
+        let mode = clip_and_mask::resolve_mask_mode(node);
+        resolve_link!(AId::Mask, |link, state, tree| {
+            clip_and_mask::convert_mask(link, mode, state, tree)
+        })
     } else {
         None
     };
@@ -352,10 +499,27 @@ fn convert_group(
         }
     }
 
+    // Blending is pointless inside a `clipPath`.
+    let (blend_mode, isolate) = if !state.is_in_clip_path() {
+        (convert_blend_mode(node), convert_isolation(node))
+    } else {
+        (tree::BlendMode::Normal, false)
+    };
+
+    // Links are pointless inside a `clipPath`.
+    let link = if !state.is_in_clip_path() {
+        convert_hyperlink(node)
+    } else {
+        None
+    };
+
     let required =    opacity.value().fuzzy_ne(&1.0)
                    || clip_path.is_some()
                    || mask.is_some()
                    || filter.is_some()
+                   || blend_mode != tree::BlendMode::Normal
+                   || isolate
+                   || link.is_some()
                    || !node.attributes().get_transform(AId::Transform).is_default()
                    || state.opt.keep_named_groups
                    || force;
@@ -368,6 +532,9 @@ fn convert_group(
             clip_path,
             mask,
             filter,
+            blend_mode,
+            isolate,
+            link,
         }));
 
         GroupKind::Keep(g)
@@ -376,6 +543,58 @@ fn convert_group(
     }
 }
 
+/// Converts a link of the `a` element, which was converted into a group.
+fn convert_hyperlink(node: &svgdom::Node) -> Option<tree::Hyperlink> {
+    let href = match node.attributes().get_value("usvg-href") {
+        Some(&AValue::String(ref href)) => href.clone(),
+        _ => return None,
+    };
+
+    let target = node.attributes().get_str(AId::Target).map(|s| s.to_string());
+
+    Some(tree::Hyperlink { href, target })
+}
+
+// `mix-blend-mode` and `isolation` are not supported by `svgdom`,
+// so they are stored as strings.
+
+fn convert_blend_mode(node: &svgdom::Node) -> tree::BlendMode {
+    let value = match node.attributes().get_value("mix-blend-mode") {
+        Some(AValue::String(s)) => s.trim().to_string(),
+        _ => return tree::BlendMode::Normal,
+    };
+
+    match value.as_str() {
+        "multiply"      => tree::BlendMode::Multiply,
+        "screen"        => tree::BlendMode::Screen,
+        "overlay"       => tree::BlendMode::Overlay,
+        "darken"        => tree::BlendMode::Darken,
+        "lighten"       => tree::BlendMode::Lighten,
+        "color-dodge"   => tree::BlendMode::ColorDodge,
+        "color-burn"    => tree::BlendMode::ColorBurn,
+        "hard-light"    => tree::BlendMode::HardLight,
+        "soft-light"    => tree::BlendMode::SoftLight,
+        "difference"    => tree::BlendMode::Difference,
+        "exclusion"     => tree::BlendMode::Exclusion,
+        "hue"           => tree::BlendMode::Hue,
+        "saturation"    => tree::BlendMode::Saturation,
+        "color"         => tree::BlendMode::Color,
+        "luminosity"    => tree::BlendMode::Luminosity,
+        "normal"        => tree::BlendMode::Normal,
+        _ => {
+            warn!("Unknown 'mix-blend-mode' value: '{}'. Fallback to normal.", value);
+            tree::BlendMode::Normal
+        }
+    }
+}
+
+fn convert_isolation(node: &svgdom::Node) -> bool {
+    match node.attributes().get_value("isolation") {
+        Some(AValue::String(s)) => s.trim() == "isolate",
+        _ => false,
+    }
+}
+
 fn remove_empty_groups(tree: &mut tree::Tree) {
     fn rm(parent: tree::Node) -> bool {
         let mut changed = false;
@@ -423,6 +642,9 @@ fn ungroup_groups(tree: &mut tree::Tree, opt: &Options) {
     fn ungroup(parent: tree::Node, opt: &Options) -> bool {
         let mut changed = false;
 
+        // Groups inside `defs` contain a shared `use` content and must be preserved.
+        let is_defs = if let tree::NodeKind::Defs = *parent.borrow() { true } else { false };
+
         let mut curr_node = parent.first_child();
         while let Some(mut node) = curr_node {
             curr_node = node.next_sibling();
@@ -435,7 +657,11 @@ fn ungroup_groups(tree: &mut tree::Tree, opt: &Options) {
                 && g.clip_path.is_none()
                 && g.mask.is_none()
                 && g.filter.is_none()
+                && g.link.is_none()
+                && g.blend_mode == tree::BlendMode::Normal
+                && !g.isolate
                 && !opt.keep_named_groups
+                && !is_defs
             } else {
                 false
             };
@@ -459,6 +685,9 @@ fn ungroup_groups(tree: &mut tree::Tree, opt: &Options) {
                         tree::NodeKind::Group(ref mut g) => {
                             prepend_ts(&mut g.transform, ts);
                         }
+                        tree::NodeKind::Use(ref mut u) => {
+                            prepend_ts(&mut u.transform, ts);
+                        }
                         _ => {}
                     }
 
@@ -516,6 +745,18 @@ fn remove_unused_defs(tree: &mut tree::Tree) {
                 tree::NodeKind::Path(ref path) => {
                     check_paint_id!(path.fill, id);
                     check_paint_id!(path.stroke, id);
+
+                    if path.markers.iter().any(|m| m.id == id) {
+                        return true;
+                    }
+                }
+                tree::NodeKind::Marker(ref marker) => {
+                    check_id!(marker.clip_path, id);
+                }
+                tree::NodeKind::Use(ref u) => {
+                    if u.link == id {
+                        return true;
+                    }
                 }
                 tree::NodeKind::Text(ref text) => {
                     for chunk in &text.chunks {
@@ -567,8 +808,12 @@ fn convert_path(
 
     let has_bbox = path::has_bbox(&segments);
     let attrs = node.attributes();
-    let fill = style::resolve_fill(node, has_bbox, state, tree);
-    let stroke = style::resolve_stroke(node, has_bbox, state, tree);
+    let shape_state = State {
+        shape_bbox: Some(calc_shape_bbox(node, &segments, state)),
+        .. state.clone()
+    };
+    let fill = style::resolve_fill(node, has_bbox, &shape_state, tree);
+    let stroke = style::resolve_stroke(node, has_bbox, &shape_state, tree);
     let transform = attrs.get_transform(AId::Transform);
     let mut visibility = convert_visibility(node);
 
@@ -578,17 +823,96 @@ fn convert_path(
         visibility = tree::Visibility::Hidden
     }
 
-    parent.append_kind(tree::NodeKind::Path(tree::Path {
+    let paint_order = style::resolve_paint_order(node);
+
+    let path = tree::Path {
         id: node.id().clone(),
         transform,
         visibility,
         fill,
         stroke,
+        paint_order: paint_order.fill_and_stroke(),
         segments: segments.clone(), // TODO: remove
-    }));
+        markers: Vec::new(),
+    };
+
+    if visibility != tree::Visibility::Visible || !marker::is_valid(node) {
+        parent.append_kind(tree::NodeKind::Path(path));
+        return;
+    }
+
+    let context = ContextElement {
+        fill: path.fill.clone(),
+        stroke: path.stroke.clone(),
+    };
+
+    match paint_order.position(style::PaintOrderKind::Markers) {
+        0 => {
+            let markers = marker::convert(node, &segments, &context, state, parent, tree);
+            if !markers.is_empty() {
+                // Kept markers are rendered after the path,
+                // so they require a separate path.
+                parent.append_kind(tree::NodeKind::Path(markers_path(&path, markers)));
+            }
+
+            parent.append_kind(tree::NodeKind::Path(path));
+        }
+        1 => {
+            // Markers should be painted between fill and stroke,
+            // so we have to split the path into two.
+            let mut first = path.clone();
+            let mut second = path;
+            second.id = String::new();
+            if paint_order.0[0] == style::PaintOrderKind::Fill {
+                first.stroke = None;
+                second.fill = None;
+            } else {
+                first.fill = None;
+                second.stroke = None;
+            }
+
+            let first_node = if first.fill.is_some() || first.stroke.is_some() {
+                Some(parent.append_kind(tree::NodeKind::Path(first)))
+            } else {
+                None
+            };
+
+            let markers = marker::convert(node, &segments, &context, state, parent, tree);
+            if !markers.is_empty() {
+                match first_node {
+                    Some(mut first_node) => set_markers(&mut first_node, markers),
+                    None => {
+                        parent.append_kind(tree::NodeKind::Path(markers_path(&second, markers)));
+                    }
+                }
+            }
+
+            if second.fill.is_some() || second.stroke.is_some() {
+                parent.append_kind(tree::NodeKind::Path(second));
+            }
+        }
+        _ => {
+            let mut path_node = parent.append_kind(tree::NodeKind::Path(path));
+            let markers = marker::convert(node, &segments, &context, state, parent, tree);
+            set_markers(&mut path_node, markers);
+        }
+    }
+}
+
+fn set_markers(node: &mut tree::Node, markers: Vec<tree::MarkerInstance>) {
+    if let tree::NodeKind::Path(ref mut path) = *node.borrow_mut() {
+        path.markers = markers;
+    }
+}
 
-    if visibility == tree::Visibility::Visible {
-        marker::convert(node, &segments, state, parent, tree);
+/// Creates a path without a fill and a stroke, that is used only to render markers.
+fn markers_path(path: &tree::Path, markers: Vec<tree::MarkerInstance>) -> tree::Path {
+    tree::Path {
+        id: String::new(),
+        fill: None,
+        stroke: None,
+        markers,
+        .. path.clone()
     }
 }
 
@@ -10,6 +10,12 @@ use svgdom;
 // self
 use tree;
 use super::prelude::*;
+use super::{
+    hatch,
+    mesh,
+    units,
+    use_node,
+};
 
 
 pub enum ServerOrColor {
@@ -28,11 +34,17 @@ pub fn convert(
     state: &State,
     tree: &mut tree::Tree,
 ) -> Option<ServerOrColor> {
+    // Hatches are converted for each shape.
+    if hatch::is_hatch(node) {
+        return hatch::convert(node, state, tree);
+    }
+
     // Check for existing.
     if let Some(exist_node) = tree.defs_by_id(node.id().as_str()) {
         let units = match *exist_node.borrow() {
             tree::NodeKind::LinearGradient(ref lg) => lg.units,
             tree::NodeKind::RadialGradient(ref rg) => rg.units,
+            tree::NodeKind::MeshGradient(ref mg) => mg.units,
             tree::NodeKind::Pattern(ref patt) => patt.units,
             _ => return None, // Unreachable.
         };
@@ -43,6 +55,10 @@ pub fn convert(
         });
     }
 
+    if mesh::is_mesh_gradient(node) {
+        return mesh::convert(node, state, tree);
+    }
+
     // Unwrap is safe, because we already checked for is_paint_server().
     match node.tag_id().unwrap() {
         EId::LinearGradient => convert_linear(node, state, tree),
@@ -85,6 +101,7 @@ fn convert_linear(
                 units,
                 transform,
                 spread_method,
+                color_interpolation: convert_color_interpolation(node),
                 stops,
             }
         })
@@ -126,7 +143,19 @@ fn convert_radial(
     let cy = resolve_number(node, AId::Cy, units, state, Length::new(50.0, Unit::Percent));
     let fx = resolve_number(node, AId::Fx, units, state, Length::new_number(cx));
     let fy = resolve_number(node, AId::Fy, units, state, Length::new_number(cy));
-    let (fx, fy) = prepare_focal(cx, cy, r, fx, fy);
+    let fr = resolve_focal_radius(node, units, state);
+
+    // The focal circle cannot be larger than the end one,
+    // so the gradient is collapsed into the last stop color, like with a zero `r`.
+    if !(fr < r) {
+        let stop = stops.last().unwrap();
+        return Some(ServerOrColor::Color {
+            color: stop.color,
+            opacity: stop.opacity,
+        });
+    }
+
+    let (fx, fy) = prepare_focal(cx, cy, r, fx, fy, fr);
     let transform = {
         let n = resolve_attr(node, AId::GradientTransform);
         let attrs = n.attributes();
@@ -141,10 +170,12 @@ fn convert_radial(
             r: r.into(),
             fx,
             fy,
+            fr: fr.into(),
             base: tree::BaseGradient {
                 units,
                 transform,
                 spread_method,
+                color_interpolation: convert_color_interpolation(node),
                 stops,
             }
         })
@@ -156,6 +187,15 @@ fn convert_radial(
     })
 }
 
+fn convert_color_interpolation(node: &svgdom::Node) -> tree::ColorInterpolation {
+    node.find_str(AId::ColorInterpolation, "sRGB", |value| {
+        match value {
+            "linearRGB" => tree::ColorInterpolation::LinearRGB,
+            _ =>           tree::ColorInterpolation::SRGB,
+        }
+    })
+}
+
 fn convert_pattern(
     node: &svgdom::Node,
     state: &State,
@@ -201,7 +241,40 @@ fn convert_pattern(
         view_box,
     }));
 
-    super::convert_children(&node_with_children, state, &mut patt, tree);
+    // Pattern content is always clipped by the tile,
+    // so we have to handle only the `clip` property.
+    let clip_rect = if node.has_attribute(AId::Clip) && use_node::has_overflow_clip(node) {
+        match view_box {
+            Some(vbox) => Some(vbox.rect),
+            None if content_units == tree::Units::UserSpaceOnUse => {
+                Some(Rect::new(0.0, 0.0, rect.width, rect.height))
+            }
+            None => {
+                warn!("'clip' on a pattern with 'objectBoundingBox' content units \
+                       is not supported.");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(clip_rect) = clip_rect {
+        let clip_rect = use_node::apply_clip_property(node, clip_rect, state);
+        let id = use_node::gen_clip_path(node, clip_rect, tree);
+        let mut g = patt.append_kind(tree::NodeKind::Group(tree::Group {
+            clip_path: Some(id),
+            ..tree::Group::default()
+        }));
+
+        super::convert_children(&node_with_children, state, &mut g, tree);
+
+        if !g.has_children() {
+            g.detach();
+        }
+    } else {
+        super::convert_children(&node_with_children, state, &mut patt, tree);
+    }
 
     if !patt.has_children() {
         return None;
@@ -295,10 +368,13 @@ fn convert_stops(grad: &svgdom::Node) -> Vec<tree::Stop> {
             let color = stop.attributes().get_color(AId::StopColor)
                             .unwrap_or(svgdom::Color::black());
 
+            let opacity = stop.convert_opacity(AId::StopOpacity).value()
+                        * stop.convert_color_alpha(AId::StopColor).value();
+
             stops.push(tree::Stop {
                 offset: offset.into(),
                 color,
-                opacity: stop.convert_opacity(AId::StopOpacity),
+                opacity: opacity.into(),
             });
         }
     }
@@ -510,21 +586,53 @@ fn resolve_filter_attr(
     node
 }
 
-/// Prepares the radial gradient focal radius.
+/// Resolves the SVG 2 `fr` attribute.
+///
+/// `svgdom` doesn't support it, so it's stored as a string
+/// and has to be resolved manually.
+fn resolve_focal_radius(node: &svgdom::Node, units: tree::Units, state: &State) -> f64 {
+    let mut value = None;
+    for link in node.href_iter() {
+        if link.tag_id() != Some(EId::RadialGradient) {
+            break;
+        }
+
+        if let Some(AValue::String(s)) = link.attributes().get_value("fr") {
+            value = Some(s.clone());
+            break;
+        }
+    }
+
+    let length: Length = match value.and_then(|s| s.parse().ok()) {
+        Some(length) => length,
+        None => return 0.0,
+    };
+
+    let fr = units::convert_length(length, node, AId::R, units, state);
+
+    // A negative value is an error.
+    if fr.is_finite() && fr > 0.0 { fr } else { 0.0 }
+}
+
+/// Prepares the radial gradient focal point.
 ///
-/// According to the SVG spec:
+/// According to the SVG 1.1 spec:
 ///
 /// If the point defined by `fx` and `fy` lies outside the circle defined by
 /// `cx`, `cy` and `r`, then the user agent shall set the focal point to the
 /// intersection of the line from (`cx`, `cy`) to (`fx`, `fy`) with the circle
 /// defined by `cx`, `cy` and `r`.
-fn prepare_focal(cx: f64, cy: f64, r: f64, fx: f64, fy: f64) -> (f64, f64) {
-    let max_r = r - r * 0.001;
+///
+/// Since backends use two-circle gradients, the focal point is moved
+/// so the whole focal circle is inside the end circle.
+/// `fr` must be smaller than `r`.
+fn prepare_focal(cx: f64, cy: f64, r: f64, fx: f64, fy: f64, fr: f64) -> (f64, f64) {
+    let max_len = r - fr;
 
     let mut line = Line::new(cx, cy, fx, fy);
 
-    if line.length() > max_r {
-        line.set_length(max_r);
+    if line.length() > max_len {
+        line.set_length(max_len);
     }
 
     (line.x2, line.y2)
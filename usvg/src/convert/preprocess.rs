@@ -2,6 +2,8 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::path;
+
 // external
 use svgdom::{
     self,
@@ -17,6 +19,9 @@ use svgdom::{
 // self
 use geom::*;
 use short::*;
+use tree;
+use utils;
+use Options;
 use super::svgdom_ext::*;
 
 
@@ -30,7 +35,7 @@ use super::svgdom_ext::*;
 /// In worst case scenario clear the `doc`.
 ///
 /// Must not panic!
-pub fn prepare_doc(doc: &mut svgdom::Document) {
+pub fn prepare_doc(doc: &mut svgdom::Document, opt: &Options) {
     let mut svg = if let Some(svg) = doc.svg_element() {
         svg
     } else {
@@ -44,6 +49,20 @@ pub fn prepare_doc(doc: &mut svgdom::Document) {
     let svg = &mut svg;
 
     resolve_root_style_attributes(doc, svg);
+
+    if let Some(color) = opt.color {
+        svg.set_attribute((AId::Color, color));
+    }
+
+    if opt.load_external_files {
+        let root_dir = opt.path.as_ref().and_then(|path| utils::file_dir(path));
+        if let (Some(path), Some(root_dir)) = (opt.path.as_ref(), root_dir) {
+            let path = canonicalize(path);
+            let mut files = vec![path.clone()];
+            resolve_external_use(doc, &path, &root_dir, opt, &mut files);
+        }
+    }
+
     resolve_use(doc);
     resolve_inherit(doc);
     resolve_current_color(doc);
@@ -63,7 +82,8 @@ fn fix_recursive_links(doc: &Document) {
 }
 
 fn fix_patterns(doc: &Document) {
-    for pattern_node in doc.root().descendants().filter(|n| n.is_tag_name(EId::Pattern)) {
+    let is_pattern = |n: &Node| n.is_tag_name(EId::Pattern) || super::hatch::is_hatch(n);
+    for pattern_node in doc.root().descendants().filter(is_pattern) {
         for mut node in pattern_node.descendants() {
             let mut check_attr = |aid: AId| {
                 let av = node.attributes().get_value(aid).cloned();
@@ -182,6 +202,7 @@ fn regroup_elements(doc: &mut Document, parent: &Node) {
            node.has_attribute(AId::ClipPath)
         || node.has_attribute(AId::Mask)
         || node.has_attribute(AId::Filter)
+        || node.has_attribute("mix-blend-mode")
     }
 
     let g_attrs = [AId::ClipPath, AId::Mask, AId::Filter, AId::Opacity];
@@ -233,12 +254,22 @@ fn regroup_elements(doc: &mut Document, parent: &Node) {
                 g_node.set_attribute(ts.clone());
                 ids.push(AId::Transform);
             }
+
+            // Non-SVG attributes that are resolved on the group.
+            for name in &["mix-blend-mode", "mask-mode"] {
+                if let Some(attr) = attrs.get(*name) {
+                    g_node.set_attribute(attr.clone());
+                }
+            }
         }
 
         for id in &ids {
             node.remove_attribute(*id);
         }
 
+        node.remove_attribute("mix-blend-mode");
+        node.remove_attribute("mask-mode");
+
         node.insert_before(g_node.clone());
         node.detach();
         g_node.append(node.clone());
@@ -288,6 +319,13 @@ fn resolve_current_color(doc: &Document) {
         for id in &ids {
             match resolve_color(&node, *id) {
                 Some(v) => {
+                    // Remember that the paint came from `currentColor`.
+                    match *id {
+                        AId::Fill => node.set_attribute(("usvg-fill-current-color", 1)),
+                        AId::Stroke => node.set_attribute(("usvg-stroke-current-color", 1)),
+                        _ => {}
+                    }
+
                     let av = node.attributes().get_value(*id).cloned().unwrap();
                     match av {
                         AValue::CurrentColor => {
@@ -335,6 +373,7 @@ fn _resolve_inherit(node: &mut Node, aid: AId) {
             let attrs = n.attributes();
             if let Some(attr) = attrs.get(aid) {
                 node.try_set_attribute(attr);
+                copy_color_alpha(&n, node, aid);
                 return;
             }
         }
@@ -343,6 +382,7 @@ fn _resolve_inherit(node: &mut Node, aid: AId) {
             let attrs = parent.attributes();
             if let Some(attr) = attrs.get(aid) {
                 node.try_set_attribute(attr);
+                copy_color_alpha(&parent, node, aid);
                 return;
             }
         }
@@ -358,6 +398,16 @@ fn _resolve_inherit(node: &mut Node, aid: AId) {
     }
 }
 
+/// Copies the color alpha set by the CSS preprocessor, like `usvg-fill-alpha`.
+fn copy_color_alpha(from: &Node, to: &mut Node, aid: AId) {
+    let name = format!("usvg-{}-alpha", aid);
+    let value = from.attributes().get_value(name.as_str()).cloned();
+    match value {
+        Some(value) => to.set_attribute((name.as_str(), value)),
+        None => to.remove_attribute(name.as_str()),
+    }
+}
+
 /// Resolves the root `svg` element attributes.
 ///
 /// In the `usvg`, the root `svg` element can't have any style attributes,
@@ -455,14 +505,6 @@ fn _resolve_use(
                     continue;
                 }
 
-                // TODO: this
-                // We don't support 'use' elements linked to 'svg' element.
-                if link.is_tag_name(EId::Svg) {
-                    warn!("'use' element linked to an 'svg' element is not supported. Skipped.");
-                    rm_nodes.push(node.clone());
-                    continue;
-                }
-
                 // Check that none of the linked node's children reference current `use` node
                 // via other `use` node.
                 //
@@ -527,12 +569,331 @@ fn __resolve_use(
         for child in new_node.children() {
             use_node.append(child);
         }
+    } else if linked_node.is_tag_name(EId::Svg) {
+        let mut new_node = doc.copy_node_deep(linked_node.clone());
+
+        // 'width' and 'height' on 'use' override the ones on the referenced 'svg'.
+        for aid in &[AId::Width, AId::Height] {
+            if use_node.has_attribute(*aid) {
+                use_node.copy_attribute_to(*aid, &mut new_node);
+            }
+        }
+
+        use_node.append(new_node);
     } else {
         let new_node = doc.copy_node_deep(linked_node.clone());
         use_node.append(new_node);
     }
 }
 
+/// The maximum nesting level of external SVG files.
+const MAX_EXTERNAL_USE_DEPTH: usize = 8;
+
+/// Imports elements referenced by `use` elements with an external link,
+/// like `xlink:href="shapes.svg#rect1"`.
+///
+/// A referenced element and all the elements it depends on will be copied
+/// into the `defs` element of the current document. After that, the `use`
+/// will be linked to the copy and resolved just like a local one.
+///
+/// Relative paths are resolved relative to the current file, but only files
+/// inside the `root_dir`, which is the directory of the main document, can be loaded.
+///
+/// Each external file is loaded only once and each referenced element
+/// is imported only once, no matter how many `use` elements reference it.
+fn resolve_external_use(
+    doc: &mut Document,
+    doc_path: &path::Path,
+    root_dir: &path::Path,
+    opt: &Options,
+    files: &mut Vec<path::PathBuf>,
+) {
+    let doc_dir = doc_path.parent().unwrap_or(path::Path::new("."));
+
+    let use_nodes: Vec<_> = doc.root().descendants()
+        .filter(|n| n.is_tag_name(EId::Use))
+        .collect();
+
+    // Already loaded files. `None` indicates a file that failed to load.
+    let mut ext_docs: Vec<(path::PathBuf, Option<Document>)> = Vec::new();
+    // Already imported elements by a file and an ID.
+    let mut imported: Vec<(path::PathBuf, String, Node)> = Vec::new();
+
+    for mut use_node in use_nodes {
+        let href = match use_node.attributes().get_value(AId::Href) {
+            Some(&AValue::String(ref href)) => href.clone(),
+            _ => continue,
+        };
+
+        let (file, id) = match href.find('#') {
+            Some(idx) => (&href[..idx], &href[idx + 1..]),
+            None => (href.as_str(), ""),
+        };
+
+        if file.is_empty() {
+            warn!("'use' element with a link to '{}' is not supported. Skipped.", href);
+            continue;
+        }
+
+        let path = match utils::resolve_local_path(file, doc_dir, root_dir) {
+            Some(path) => path,
+            None => {
+                warn!("'{}' doesn't exist or is outside of the document directory. Skipped.", file);
+                continue;
+            }
+        };
+
+        // A link to the current file. Like `xlink:href="current.svg#rect1"`.
+        if files.last() == Some(&path) {
+            let link = if id.is_empty() {
+                doc.svg_element()
+            } else {
+                doc.root().descendants().find(|n| *n.id() == id)
+            };
+
+            if let Some(link) = link {
+                use_node.set_attribute((AId::Href, AValue::Link(link)));
+            }
+
+            continue;
+        }
+
+        if let Some(&(_, _, ref new_link)) = imported.iter().find(|v| v.0 == path && v.1 == id) {
+            use_node.set_attribute((AId::Href, AValue::Link(new_link.clone())));
+            continue;
+        }
+
+        if files.contains(&path) {
+            warn!("Recursive external 'use' detected: '{}'. Skipped.", href);
+            continue;
+        }
+
+        if files.len() >= MAX_EXTERNAL_USE_DEPTH {
+            warn!("External 'use' nesting is too deep: '{}'. Skipped.", href);
+            continue;
+        }
+
+        if !ext_docs.iter().any(|v| v.0 == path) {
+            let ext_doc = tree::load_svg_file(&path)
+                .and_then(|text| tree::parse_svgdom(&text, Some(path.as_path()), opt));
+            let ext_doc = match ext_doc {
+                Ok(mut ext_doc) => {
+                    // An external file can have external links too.
+                    files.push(path.clone());
+                    resolve_external_use(&mut ext_doc, &path, root_dir, opt, files);
+                    files.pop();
+
+                    Some(ext_doc)
+                }
+                Err(_) => {
+                    warn!("Failed to load '{}'. Skipped.", path.display());
+                    None
+                }
+            };
+
+            ext_docs.push((path.clone(), ext_doc));
+        }
+
+        // A file that failed to load is already reported.
+        let ext_doc = match ext_docs.iter().find(|v| v.0 == path) {
+            Some(&(_, Some(ref ext_doc))) => ext_doc,
+            _ => continue,
+        };
+
+        // A link without a fragment references the whole file.
+        let link = if id.is_empty() {
+            ext_doc.svg_element()
+        } else {
+            ext_doc.root().descendants().find(|n| *n.id() == id)
+        };
+
+        let link = match link {
+            Some(link) => link,
+            None => {
+                warn!("'{}' doesn't have an element with ID '{}'. Skipped.", file, id);
+                continue;
+            }
+        };
+
+        let ext_dir = path.parent().unwrap_or(path::Path::new("."));
+        if let Some(new_link) = import_node(doc, &link, ext_dir, doc_dir) {
+            use_node.set_attribute((AId::Href, AValue::Link(new_link.clone())));
+            imported.push((path.clone(), id.to_string(), new_link));
+        }
+    }
+}
+
+/// Copies a node from an another document into the `defs` element
+/// of the current one, including all the elements it links to.
+///
+/// Relative image paths are resolved relative to the `ext_dir`
+/// and then made relative to the `doc_dir`, when possible.
+///
+/// Returns a copy of the `node`.
+fn import_node(
+    doc: &mut Document,
+    node: &Node,
+    ext_dir: &path::Path,
+    doc_dir: &path::Path,
+) -> Option<Node> {
+    if !node.is_svg_element() {
+        return None;
+    }
+
+    // Collect all the subtrees we should copy.
+    let mut roots = vec![node.clone()];
+    let mut i = 0;
+    while i < roots.len() {
+        let root = roots[i].clone();
+        for n in root.descendants() {
+            for (_, attr) in n.attributes().iter().svg() {
+                let link = match attr.value {
+                      AValue::Link(ref link)
+                    | AValue::FuncLink(ref link)
+                    | AValue::Paint(ref link, _) => link.clone(),
+                    _ => continue,
+                };
+
+                if !roots.iter().any(|r| link.ancestors().any(|n| n == *r)) {
+                    roots.push(link);
+                }
+            }
+        }
+
+        i += 1;
+    }
+
+    let mut defs = match doc.svg_element()?.children().find(|n| n.is_tag_name(EId::Defs)) {
+        Some(defs) => defs,
+        None => {
+            let defs = doc.create_element(EId::Defs);
+            doc.svg_element()?.prepend(defs.clone());
+            defs
+        }
+    };
+
+    // Copy the structure first, so we can remap links afterwards.
+    let mut map = Vec::new();
+    for root in &roots {
+        if let Some(new_root) = copy_structure(doc, root, &mut map) {
+            defs.append(new_root);
+        }
+    }
+
+    for &(ref old_node, ref new_node) in &map {
+        let mut new_node = new_node.clone();
+        if !old_node.is_element() {
+            continue;
+        }
+
+        // Non-SVG attributes, like `pitch` on a `hatch`, are stored as is.
+        for attr in old_node.attributes().iter().filter(|a| !a.is_svg()) {
+            new_node.set_attribute(attr.clone());
+        }
+
+        for (aid, attr) in old_node.attributes().iter().svg() {
+            if aid == AId::Id {
+                continue;
+            }
+
+            let find = |link: &Node| {
+                map.iter().find(|&&(ref old, _)| old == link).map(|&(_, ref new)| new.clone())
+            };
+
+            match attr.value {
+                AValue::Link(ref link) => {
+                    if let Some(link) = find(link) {
+                        new_node.set_attribute((aid, AValue::Link(link)));
+                    }
+                }
+                AValue::FuncLink(ref link) => {
+                    if let Some(link) = find(link) {
+                        new_node.set_attribute((aid, AValue::FuncLink(link)));
+                    }
+                }
+                AValue::Paint(ref link, fallback) => {
+                    if let Some(link) = find(link) {
+                        new_node.set_attribute((aid, AValue::Paint(link, fallback)));
+                    }
+                }
+                _ => {
+                    new_node.set_attribute(attr.clone());
+                }
+            }
+        }
+
+        if new_node.is_tag_name(EId::Image) {
+            rebase_image_href(&mut new_node, ext_dir, doc_dir);
+        }
+
+        // IDs must be unique in the current document.
+        if old_node.has_id() {
+            let id = gen_unique_id(doc, &old_node.id());
+            new_node.set_id(id);
+        }
+    }
+
+    map.first().map(|&(_, ref new)| new.clone())
+}
+
+fn rebase_image_href(node: &mut Node, ext_dir: &path::Path, doc_dir: &path::Path) {
+    let href = match node.attributes().get_value(AId::Href) {
+        Some(&AValue::String(ref href)) if !href.starts_with("data:") => href.clone(),
+        _ => return,
+    };
+
+    let path = ext_dir.join(&href);
+    let path = match path.strip_prefix(doc_dir) {
+        Ok(rel_path) => rel_path.to_path_buf(),
+        Err(_) => path.clone(),
+    };
+
+    node.set_attribute((AId::Href, AValue::String(path.to_string_lossy().to_string())));
+}
+
+fn copy_structure(
+    doc: &mut Document,
+    node: &Node,
+    map: &mut Vec<(Node, Node)>,
+) -> Option<Node> {
+    let mut new_node = if node.is_text() {
+        doc.create_node(svgdom::NodeType::Text, node.text().to_string())
+    } else if let Some(eid) = node.tag_id() {
+        doc.create_element(eid)
+    } else if node.is_element() {
+        // Non-SVG elements, like `meshgradient` or `hatch`, are detected by name.
+        let name = node.tag_name().to_string();
+        doc.create_element(name.as_str())
+    } else {
+        return None;
+    };
+
+    map.push((node.clone(), new_node.clone()));
+
+    for child in node.children() {
+        if let Some(new_child) = copy_structure(doc, &child, map) {
+            new_node.append(new_child);
+        }
+    }
+
+    Some(new_node)
+}
+
+fn gen_unique_id(doc: &Document, id: &str) -> String {
+    let mut new_id = id.to_string();
+    let mut idx = 1;
+    while doc.root().descendants().any(|n| *n.id() == new_id) {
+        new_id = format!("{}-{}", id, idx);
+        idx += 1;
+    }
+
+    new_id
+}
+
+fn canonicalize(path: &path::Path) -> path::PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
 fn remove_invalid_use(doc: &mut Document) {
     fn _rm(doc: &mut Document) -> usize {
         let root = doc.root();
@@ -547,9 +908,10 @@ fn remove_invalid_use(doc: &mut Document) {
                     if let Some(&AValue::Link(_)) = attrs.get_value(AId::Href) {
                         // Nothing.
                     } else {
-                        // NOTE: actually, an attribute with 'String' type is valid
+                        // NOTE: an attribute with 'String' type is valid
                         // if it contain a path to an external file, like '../img.svg#rect1',
-                        // but we don't support external SVG, so we treat them as invalid.
+                        // but such links are already resolved by `resolve_external_use`,
+                        // so everything that is left is invalid.
                         return true;
                     }
                 }
@@ -565,21 +927,33 @@ fn remove_invalid_use(doc: &mut Document) {
     while _rm(doc) > 0 {}
 }
 
-/// We don't care about `a` elements, but we can't just remove them.
-/// So, if an `a` element is inside a `text` - change the tag name to `tspan`.
-/// Otherwise, to `g`.
+/// If an `a` element is inside a `text` - change the tag name to `tspan`.
+/// The link is lost in this case.
+///
+/// Otherwise, change the tag name to `g` and store the link in the `usvg-href` attribute,
+/// since `svgdom` will try to resolve `xlink:href` on a `g`.
 fn ungroup_a(doc: &Document) {
     for (id, mut node) in doc.root().descendants().svg() {
         if id != EId::A {
             continue;
         }
 
+        let href = match node.attributes().get_value(AId::Href) {
+            Some(&AValue::String(ref href)) => Some(href.clone()),
+            Some(&AValue::Link(ref link)) => Some(format!("#{}", link.id())),
+            _ => None,
+        };
+
         node.remove_attribute(AId::Href);
 
         if node.ancestors().skip(1).any(|n| n.is_tag_name(EId::Text)) {
             node.set_tag_name(EId::Tspan);
         } else {
             node.set_tag_name(EId::G);
+
+            if let Some(href) = href {
+                node.set_attribute(("usvg-href", href));
+            }
         }
     }
 }
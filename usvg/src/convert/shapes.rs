@@ -9,10 +9,7 @@ use svgdom;
 use tree;
 use utils;
 use super::prelude::*;
-use super::{
-    path,
-    units,
-};
+use super::units;
 
 
 pub fn convert(
@@ -105,19 +102,19 @@ pub fn convert_rect(
     let path = if rx.fuzzy_eq(&0.0) {
         utils::rect_to_path(Rect::new(x, y, width, height))
     } else {
-        let p = svgdom::PathBuilder::with_capacity(9)
-            .move_to(x + rx, y)
-            .line_to(x + width - rx, y)
-            .arc_to(rx, ry, 0.0, false, true, x + width, y + ry)
-            .line_to(x + width, y + height - ry)
-            .arc_to(rx, ry, 0.0, false, true, x + width - rx, y + height)
-            .line_to(x + rx, y + height)
-            .arc_to(rx, ry, 0.0, false, true, x, y + height - ry)
-            .line_to(x, y + ry)
-            .arc_to(rx, ry, 0.0, false, true, x + rx, y)
-            .finalize();
-
-        path::convert_path(p)
+        vec![
+            tree::PathSegment::MoveTo { x: x + rx, y },
+            tree::PathSegment::LineTo { x: x + width - rx, y },
+            quarter_arc(x + width - rx, y + ry, x + width - rx, y, x + width, y + ry),
+            tree::PathSegment::LineTo { x: x + width, y: y + height - ry },
+            quarter_arc(x + width - rx, y + height - ry,
+                        x + width, y + height - ry, x + width - rx, y + height),
+            tree::PathSegment::LineTo { x: x + rx, y: y + height },
+            quarter_arc(x + rx, y + height - ry, x + rx, y + height, x, y + height - ry),
+            tree::PathSegment::LineTo { x, y: y + ry },
+            quarter_arc(x + rx, y + ry, x, y + ry, x + rx, y),
+            tree::PathSegment::ClosePath,
+        ]
     };
 
     Some(path)
@@ -231,12 +228,34 @@ pub fn convert_ellipse(
 }
 
 fn ellipse_to_path(cx: f64, cy: f64, rx: f64, ry: f64) -> Vec<tree::PathSegment> {
-    path::convert_path(svgdom::PathBuilder::with_capacity(6)
-        .move_to(cx + rx, cy)
-        .arc_to(rx, ry, 0.0, false, true, cx,      cy + ry)
-        .arc_to(rx, ry, 0.0, false, true, cx - rx, cy)
-        .arc_to(rx, ry, 0.0, false, true, cx,      cy - ry)
-        .arc_to(rx, ry, 0.0, false, true, cx + rx, cy)
-        .close_path()
-        .finalize())
+    vec![
+        tree::PathSegment::MoveTo { x: cx + rx, y: cy },
+        quarter_arc(cx, cy, cx + rx, cy, cx, cy + ry),
+        quarter_arc(cx, cy, cx, cy + ry, cx - rx, cy),
+        quarter_arc(cx, cy, cx - rx, cy, cx, cy - ry),
+        quarter_arc(cx, cy, cx, cy - ry, cx + rx, cy),
+        tree::PathSegment::ClosePath,
+    ]
+}
+
+/// Converts a quarter of an axis-aligned ellipse into a single curve.
+///
+/// Each arc of the equivalent path becomes exactly one segment,
+/// so markers are placed only at the equivalent path vertices.
+fn quarter_arc(
+    cx: f64, cy: f64,
+    x1: f64, y1: f64,
+    x2: f64, y2: f64,
+) -> tree::PathSegment {
+    // 4/3 * (sqrt(2) - 1)
+    const KAPPA: f64 = 0.552_284_749_830_793_4;
+
+    tree::PathSegment::CurveTo {
+        x1: x1 + (x2 - cx) * KAPPA,
+        y1: y1 + (y2 - cy) * KAPPA,
+        x2: x2 + (x1 - cx) * KAPPA,
+        y2: y2 + (y1 - cy) * KAPPA,
+        x: x2,
+        y: y2,
+    }
 }
@@ -9,6 +9,8 @@ use svgdom;
 use tree;
 use super::prelude::*;
 use super::{
+    hatch,
+    mesh,
     paint_server,
     switch,
 };
@@ -34,13 +36,21 @@ pub fn resolve_fill(
             paint: tree::Paint::Color(tree::Color::black()),
             opacity: tree::Opacity::default(),
             rule: clip_rule,
+            from_current_color: false,
         });
     }
 
 
     let mut sub_opacity = tree::Opacity::default();
+    let mut from_current_color = false;
     let paint = if let Some(n) = node.find_node_with_attribute(AId::Fill) {
-        convert_paint(&n, AId::Fill, has_bbox, state, &mut sub_opacity, tree)?
+        let paint = match resolve_context_paint(&n, "usvg-fill-context", state) {
+            Some(paint) => paint?,
+            None => convert_paint(&n, AId::Fill, has_bbox, state, &mut sub_opacity, tree)?,
+        };
+
+        from_current_color = is_current_color(&n, &paint, "usvg-fill-current-color");
+        paint
     } else {
         tree::Paint::Color(tree::Color::black())
     };
@@ -67,6 +77,7 @@ pub fn resolve_fill(
         paint,
         opacity: fill_opacity.into(),
         rule: fill_rule,
+        from_current_color,
     })
 }
 
@@ -83,8 +94,15 @@ pub fn resolve_stroke(
 
 
     let mut sub_opacity = tree::Opacity::default();
+    let mut from_current_color = false;
     let paint = if let Some(n) = node.find_node_with_attribute(AId::Stroke) {
-        convert_paint(&n, AId::Stroke, has_bbox, state, &mut sub_opacity, tree)?
+        let paint = match resolve_context_paint(&n, "usvg-stroke-context", state) {
+            Some(paint) => paint?,
+            None => convert_paint(&n, AId::Stroke, has_bbox, state, &mut sub_opacity, tree)?,
+        };
+
+        from_current_color = is_current_color(&n, &paint, "usvg-stroke-current-color");
+        paint
     } else {
         return None;
     };
@@ -122,6 +140,8 @@ pub fn resolve_stroke(
 
     let dasharray = conv_dasharray(node, state);
 
+    let non_scaling = resolve_non_scaling_stroke(node);
+
     let stroke = tree::Stroke {
         paint,
         dasharray,
@@ -131,11 +151,73 @@ pub fn resolve_stroke(
         width,
         linecap,
         linejoin,
+        from_current_color,
+        non_scaling,
     };
 
     Some(stroke)
 }
 
+/// Resolves `vector-effect`.
+///
+/// It's not inherited and isn't supported by `svgdom`.
+/// But a text span is a part of the `text` element,
+/// so a text span uses the closest value set on it, a parent `tspan` or `text`.
+fn resolve_non_scaling_stroke(node: &svgdom::Node) -> bool {
+    for n in node.ancestors() {
+        if let Some(AValue::String(value)) = n.attributes().get_value("vector-effect") {
+            return value.trim() == "non-scaling-stroke";
+        }
+
+        if !n.is_tag_name(EId::Tspan) && !n.is_tag_name(EId::TextPath) {
+            break;
+        }
+    }
+
+    false
+}
+
+/// Checks that a paint was resolved from `currentColor` during preprocessing.
+///
+/// Paint server links are not affected, only the fallback color can be.
+fn is_current_color(node: &svgdom::Node, paint: &tree::Paint, marker: &str) -> bool {
+    match *paint {
+        tree::Paint::Color(_) => node.has_attribute(marker),
+        tree::Paint::Link(_) => false,
+    }
+}
+
+/// Resolves `context-fill` and `context-stroke`.
+///
+/// They are replaced with `none` and a `usvg-*-context` attribute during the CSS resolving,
+/// because `svgdom` doesn't support them.
+///
+/// Returns `None` when the paint is not a context one.
+/// Returns `Some(None)` when there is no context element or it doesn't have such paint.
+fn resolve_context_paint(
+    node: &svgdom::Node,
+    marker: &str,
+    state: &State,
+) -> Option<Option<tree::Paint>> {
+    let value = match node.attributes().get_value(marker) {
+        Some(AValue::String(value)) => value.clone(),
+        _ => return None,
+    };
+
+    let context = match state.context_element {
+        Some(ref context) => context,
+        None => return Some(None),
+    };
+
+    let paint = match value.as_str() {
+        "context-fill" => context.fill.as_ref().map(|fill| fill.paint.clone()),
+        "context-stroke" => context.stroke.as_ref().map(|stroke| stroke.paint.clone()),
+        _ => None,
+    };
+
+    Some(paint)
+}
+
 fn convert_paint(
     node: &svgdom::Node,
     aid: AId,
@@ -147,10 +229,14 @@ fn convert_paint(
     let av = node.attributes().get_value(aid).cloned()?;
     match av {
         AValue::Color(c) => {
+            *opacity = node.convert_color_alpha(aid);
             Some(tree::Paint::Color(c))
         }
         AValue::Paint(ref link, fallback) => {
-            if link.is_paint_server() {
+            let is_paint_server =    link.is_paint_server()
+                                   || mesh::is_mesh_gradient(link)
+                                   || hatch::is_hatch(link);
+            if is_paint_server {
                 match paint_server::convert(link, state, tree) {
                     Some(paint_server::ServerOrColor::Server { id, units }) => {
                         // We can use a paint server node with ObjectBoundingBox units
@@ -158,7 +244,7 @@ fn convert_paint(
                         //
                         // See SVG spec 7.11 for details.
                         if !has_bbox && units == tree::Units::ObjectBoundingBox {
-                            from_fallback(fallback)
+                            from_fallback(node, aid, fallback, opacity)
                         } else {
                             Some(tree::Paint::Link(id))
                         }
@@ -168,7 +254,7 @@ fn convert_paint(
                         Some(tree::Paint::Color(color))
                     }
                     None => {
-                        from_fallback(fallback)
+                        from_fallback(node, aid, fallback, opacity)
                     }
                 }
             } else {
@@ -186,7 +272,12 @@ fn convert_paint(
     }
 }
 
-fn from_fallback(fallback: Option<svgdom::PaintFallback>) -> Option<tree::Paint> {
+fn from_fallback(
+    node: &svgdom::Node,
+    aid: AId,
+    fallback: Option<svgdom::PaintFallback>,
+    opacity: &mut tree::Opacity,
+) -> Option<tree::Paint> {
     match fallback? {
         svgdom::PaintFallback::None => {
             None
@@ -196,6 +287,7 @@ fn from_fallback(fallback: Option<svgdom::PaintFallback>) -> Option<tree::Paint>
             None
         }
         svgdom::PaintFallback::Color(c) => {
+            *opacity = node.convert_color_alpha(aid);
             Some(tree::Paint::Color(c))
         }
     }
@@ -245,3 +337,79 @@ pub fn is_visible_element(node: &svgdom::Node, opt: &Options) -> bool {
     && node.is_valid_transform(AId::Transform)
     && switch::is_condition_passed(&node, opt)
 }
+
+/// A `paint-order` item.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaintOrderKind {
+    Fill,
+    Stroke,
+    Markers,
+}
+
+/// A resolved `paint-order`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PaintOrder(pub [PaintOrderKind; 3]);
+
+impl PaintOrder {
+    pub fn fill_and_stroke(&self) -> tree::PaintOrder {
+        let fill = self.position(PaintOrderKind::Fill);
+        let stroke = self.position(PaintOrderKind::Stroke);
+        if stroke < fill {
+            tree::PaintOrder::StrokeAndFill
+        } else {
+            tree::PaintOrder::FillAndStroke
+        }
+    }
+
+    pub fn position(&self, kind: PaintOrderKind) -> usize {
+        self.0.iter().position(|k| *k == kind).unwrap()
+    }
+}
+
+// `paint-order` is not supported by `svgdom`, so it's stored as a string.
+pub fn resolve_paint_order(node: &svgdom::Node) -> PaintOrder {
+    let default = [PaintOrderKind::Fill, PaintOrderKind::Stroke, PaintOrderKind::Markers];
+
+    let value = node.ancestors().filter_map(|n| {
+        match n.attributes().get_value("paint-order") {
+            Some(AValue::String(s)) => Some(s.clone()),
+            _ => None,
+        }
+    }).next();
+
+    let value = match value {
+        Some(value) => value,
+        None => return PaintOrder(default),
+    };
+
+    let mut order = Vec::with_capacity(3);
+    for name in value.split_whitespace() {
+        let kind = match name {
+            "normal" if order.is_empty() => break,
+            "fill" => PaintOrderKind::Fill,
+            "stroke" => PaintOrderKind::Stroke,
+            "markers" => PaintOrderKind::Markers,
+            _ => {
+                warn!("Invalid 'paint-order' value: '{}'. Fallback to normal.", value);
+                return PaintOrder(default);
+            }
+        };
+
+        // Duplicates are invalid.
+        if order.contains(&kind) {
+            warn!("Invalid 'paint-order' value: '{}'. Fallback to normal.", value);
+            return PaintOrder(default);
+        }
+
+        order.push(kind);
+    }
+
+    // Omitted values are painted in the default order.
+    for kind in &default {
+        if !order.contains(kind) {
+            order.push(*kind);
+        }
+    }
+
+    PaintOrder([order[0], order[1], order[2]])
+}
@@ -21,6 +21,7 @@ pub trait SvgNodeExt {
     fn convert_user_length(&self, aid: AId, state: &State, def: Length) -> f64;
     fn try_convert_user_length(&self, aid: AId, state: &State) -> Option<f64>;
     fn convert_opacity(&self, aid: AId) -> tree::Opacity;
+    fn convert_color_alpha(&self, aid: AId) -> tree::Opacity;
     fn href_iter(&self) -> HrefIter;
     fn move_attribute_to(&mut self, aid: AId, to: &mut Self);
     fn copy_attribute_to(&self, aid: AId, to: &mut Self);
@@ -120,6 +121,18 @@ impl SvgNodeExt for svgdom::Node {
         f64_bound(0.0, opacity, 1.0).into()
     }
 
+    // Set by the CSS preprocessor for colors with an alpha channel.
+    fn convert_color_alpha(&self, aid: AId) -> tree::Opacity {
+        let name = format!("usvg-{}-alpha", aid);
+        let alpha = match self.attributes().get_value(name.as_str()) {
+            Some(AValue::Number(n)) => *n,
+            Some(AValue::String(s)) => s.parse().unwrap_or(1.0),
+            _ => 1.0,
+        };
+
+        f64_bound(0.0, alpha, 1.0).into()
+    }
+
     fn href_iter(&self) -> HrefIter {
         HrefIter {
             origin: self.clone(),
@@ -21,7 +21,7 @@ static FEATURES: &[&str] = &[
     "http://www.w3.org/TR/SVG11/feature#ConditionalProcessing",
     "http://www.w3.org/TR/SVG11/feature#Image",
     "http://www.w3.org/TR/SVG11/feature#Style",
-    // "http://www.w3.org/TR/SVG11/feature#ViewportAttribute", // `clip` and `overflow`, not yet
+    "http://www.w3.org/TR/SVG11/feature#ViewportAttribute",
     "http://www.w3.org/TR/SVG11/feature#Shape",
     "http://www.w3.org/TR/SVG11/feature#Text", // partial
     "http://www.w3.org/TR/SVG11/feature#BasicText",
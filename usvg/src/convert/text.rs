@@ -42,6 +42,7 @@ pub fn convert(
         id: node.id().clone(),
         transform: text_ts,
         rotate,
+        paint_order: style::resolve_paint_order(node).fill_and_stroke(),
         chunks,
     }));
 }
@@ -2,6 +2,12 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{
+    Hash,
+    Hasher,
+};
+
 // external
 use svgdom;
 
@@ -10,6 +16,7 @@ use tree;
 use utils;
 use tree::prelude::*;
 use super::prelude::*;
+use super::style;
 
 
 pub fn convert(
@@ -20,6 +27,14 @@ pub fn convert(
 ) {
     debug_assert!(node.has_attribute("usvg-use"));
 
+    // `use` is the context element for the referenced content.
+    let mut use_state = state.clone();
+    use_state.context_element = Some(super::ContextElement {
+        fill: style::resolve_fill(node, true, state, tree),
+        stroke: style::resolve_stroke(node, true, state, tree),
+    });
+    let state = &use_state;
+
     // We require original transformation to setup 'clipPath'.
     let mut orig_ts = node.attributes().get_transform(AId::Transform);
     let mut new_ts = tree::Transform::default();
@@ -71,16 +86,33 @@ pub fn convert_svg(
         new_ts.translate(x, y);
     }
 
+    let size = {
+        let w = node.convert_user_length(AId::Width, state, Length::new(100.0, Unit::Percent));
+        let h = node.convert_user_length(AId::Height, state, Length::new(100.0, Unit::Percent));
+        Size::new(w, h)
+    };
+
+    // A zero-sized viewport disables rendering.
+    if !size.is_valid() {
+        return;
+    }
+
     if let Some(ts) = viewbox_transform(node, node, state) {
         new_ts.append(&ts);
     }
 
+    // A nested `svg` establishes a new viewport,
+    // so percentage units of its children should be resolved relative to it.
+    let mut new_state = state.clone();
+    new_state.size = size;
+    new_state.view_box = node.get_viewbox().unwrap_or_else(|| size.to_rect(0.0, 0.0));
+
     if let Some(clip_rect) = get_clip_rect(node, node, state) {
         let mut g = clip_element(node, clip_rect, orig_ts, parent, tree);
-        convert_children(node, new_ts, state, &mut g, tree);
+        convert_children(node, new_ts, &new_state, &mut g, tree);
     } else {
         orig_ts.append(&new_ts);
-        convert_children(node, orig_ts, state, parent, tree);
+        convert_children(node, orig_ts, &new_state, parent, tree);
     }
 }
 
@@ -111,6 +143,22 @@ fn clip_element(
     //   <elem/>
     // </g>
 
+    let id = gen_clip_path(node, clip_rect, tree);
+
+    parent.append_kind(tree::NodeKind::Group(tree::Group {
+        id: node.id().clone(),
+        transform,
+        clip_path: Some(id),
+        ..tree::Group::default()
+    }))
+}
+
+/// Creates a `clipPath` with a single rect and returns its ID.
+pub fn gen_clip_path(
+    node: &svgdom::Node,
+    clip_rect: Rect,
+    tree: &mut tree::Tree,
+) -> String {
     let id = gen_clip_path_id(node, tree);
 
     let mut clip_path = tree.append_to_defs(tree::NodeKind::ClipPath(tree::ClipPath {
@@ -125,12 +173,7 @@ fn clip_element(
         ..tree::Path::default()
     }));
 
-    parent.append_kind(tree::NodeKind::Group(tree::Group {
-        id: node.id().clone(),
-        transform,
-        clip_path: Some(id),
-        ..tree::Group::default()
-    }))
+    id
 }
 
 fn convert_children(
@@ -140,6 +183,34 @@ fn convert_children(
     parent: &mut tree::Node,
     tree: &mut tree::Tree,
 ) {
+    if state.opt.keep_use && node.has_attribute("usvg-use") {
+        if let Some(link) = convert_definition(node, state, tree) {
+            match super::convert_group(node, state, false, parent, tree) {
+                super::GroupKind::Keep(mut g) => {
+                    if let tree::NodeKind::Group(ref mut g) = *g.borrow_mut() {
+                        g.transform = transform;
+                    }
+
+                    g.append_kind(tree::NodeKind::Use(tree::Use {
+                        id: String::new(),
+                        transform: tree::Transform::default(),
+                        link,
+                    }));
+                }
+                super::GroupKind::Skip => {
+                    parent.append_kind(tree::NodeKind::Use(tree::Use {
+                        id: node.id().clone(),
+                        transform,
+                        link,
+                    }));
+                }
+                super::GroupKind::Ignore => {}
+            }
+
+            return;
+        }
+    }
+
     let required = !transform.is_default();
 
     match super::convert_group(node, state, required, parent, tree) {
@@ -157,18 +228,111 @@ fn convert_children(
     }
 }
 
+/// Converts the `use` content into a `Group` inside the `Defs`.
+///
+/// The content is converted only once for each combination of the referenced
+/// element, the inherited style and the viewport.
+///
+/// Returns the `Group` ID or `None` when the content cannot be shared.
+fn convert_definition(
+    node: &svgdom::Node,
+    state: &State,
+    tree: &mut tree::Tree,
+) -> Option<String> {
+    // `clipPath` children cannot be references.
+    if state.is_in_clip_path() {
+        return None;
+    }
+
+    let link = match node.attributes().get_value(AId::Href) {
+        Some(&AValue::Link(ref link)) => link.clone(),
+        _ => return None,
+    };
+
+    let mut hasher = DefaultHasher::new();
+    context_key(node, state)?.hash(&mut hasher);
+    let id = format!("{}-{:x}", link.id(), hasher.finish());
+
+    if tree.defs_by_id(&id).is_some() {
+        return Some(id);
+    }
+
+    // Make sure that we don't use an ID from the original document.
+    if node.root().descendants().any(|n| *n.id() == id) {
+        return None;
+    }
+
+    let mut g = tree.append_to_defs(tree::NodeKind::Group(tree::Group {
+        id: id.clone(),
+        ..tree::Group::default()
+    }));
+
+    super::convert_children(node, state, &mut g, tree);
+
+    Some(id)
+}
+
+/// Collects everything the `use` content depends on, except the content itself.
+///
+/// Returns `None` when the `use` depends on the element that instantiated it,
+/// like a marker.
+fn context_key(node: &svgdom::Node, state: &State) -> Option<String> {
+    let mut key = format!("{:?} {:?}", state.size, state.view_box);
+
+    // `width` and `height` are copied to the referenced `svg`.
+    for aid in &[AId::Width, AId::Height] {
+        if let Some(attr) = node.attributes().get(*aid) {
+            key.push_str(&format!(" {}", attr));
+        }
+    }
+
+    // The closest ancestor defines the value.
+    let mut aids: Vec<AId> = Vec::new();
+    for n in node.ancestors() {
+        for (aid, attr) in n.attributes().iter().svg() {
+            if !aid.is_inheritable() || aids.contains(&aid) {
+                continue;
+            }
+
+            aids.push(aid);
+            key.push_str(&format!(" {}", attr));
+
+            // Colors can be split by the CSS processing.
+            for suffix in &["alpha", "current-color"] {
+                let name = format!("usvg-{}-{}", aid, suffix);
+                if let Some(attr) = n.attributes().get(name.as_str()) {
+                    key.push_str(&format!(" {}", attr));
+                }
+            }
+
+            let name = format!("usvg-{}-context", aid);
+            if n.has_attribute(name.as_str()) {
+                return None;
+            }
+        }
+    }
+
+    // Inheritable attributes that are not supported by `svgdom` are stored as strings.
+    for name in &["paint-order"] {
+        for n in node.ancestors() {
+            if let Some(attr) = n.attributes().get(*name) {
+                key.push_str(&format!(" {}", attr));
+                break;
+            }
+        }
+    }
+
+    Some(key)
+}
+
 fn get_clip_rect(
     use_node: &svgdom::Node,
     symbol_node: &svgdom::Node,
     state: &State,
 ) -> Option<Rect> {
     // No need to clip elements with overflow:visible.
-    {
-        let attrs = symbol_node.attributes();
-        let overflow = attrs.get_str_or(AId::Overflow, "hidden");
-        if overflow != "hidden" && overflow != "scroll" {
-            return None;
-        }
+    if !has_overflow_clip(symbol_node) {
+        return None;
     }
 
     let (x, y, w, h) = {
@@ -185,11 +349,103 @@ fn get_clip_rect(
 
     // TODO: add a test case
     // Clip rect is not needed when it has the same size as a whole image.
-    if w.fuzzy_eq(&state.size.width) && h.fuzzy_eq(&state.size.height) {
+    if    !symbol_node.has_attribute(AId::Clip)
+       && x.is_fuzzy_zero() && y.is_fuzzy_zero()
+       && w.fuzzy_eq(&state.size.width) && h.fuzzy_eq(&state.size.height)
+    {
+        return None;
+    }
+
+    Some(apply_clip_property(symbol_node, Rect::new(x, y, w, h), state))
+}
+
+/// Checks that element's content should be clipped by its viewport.
+///
+/// `overflow:auto` is the same as `visible`.
+pub fn has_overflow_clip(node: &svgdom::Node) -> bool {
+    let attrs = node.attributes();
+    let overflow = attrs.get_str_or(AId::Overflow, "hidden");
+    overflow == "hidden" || overflow == "scroll"
+}
+
+/// Applies the `clip` property to the viewport clip rect.
+///
+/// `clip` is defined as `rect(<top>, <right>, <bottom>, <left>)`,
+/// where each offset is relative to the top-left corner of the `rect`
+/// and `auto` means the corresponding `rect` edge.
+pub fn apply_clip_property(
+    node: &svgdom::Node,
+    rect: Rect,
+    state: &State,
+) -> Rect {
+    let offsets = {
+        let attrs = node.attributes();
+        let value = match attrs.get_str(AId::Clip) {
+            Some(value) => value,
+            None => return rect,
+        };
+
+        match parse_clip_rect(value) {
+            Some(offsets) => offsets,
+            None => {
+                if value.trim() != "auto" {
+                    warn!("Invalid 'clip' value: '{}'. Skipped.", value);
+                }
+
+                return rect;
+            }
+        }
+    };
+
+    let conv = |len: Option<Length>, aid: AId, def: f64| {
+        match len {
+            Some(len) => {
+                super::units::convert_length(len, node, aid, tree::Units::UserSpaceOnUse, state)
+            }
+            None => def,
+        }
+    };
+
+    let top    = rect.y + conv(offsets[0], AId::Y, 0.0);
+    let right  = rect.x + conv(offsets[1], AId::X, rect.width);
+    let bottom = rect.y + conv(offsets[2], AId::Y, rect.height);
+    let left   = rect.x + conv(offsets[3], AId::X, 0.0);
+
+    Rect::new(left, top, (right - left).max(0.0), (bottom - top).max(0.0))
+}
+
+/// Parses `rect(<top>, <right>, <bottom>, <left>)`.
+///
+/// Commas are optional. `auto` offsets are returned as `None`.
+fn parse_clip_rect(text: &str) -> Option<[Option<Length>; 4]> {
+    let text = text.trim();
+    if !text.starts_with("rect(") || !text.ends_with(')') {
+        return None;
+    }
+
+    let mut offsets = [None; 4];
+    let mut idx = 0;
+    let values = text[5..text.len() - 1]
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty());
+
+    for value in values {
+        if idx == offsets.len() {
+            return None;
+        }
+
+        if value != "auto" {
+            offsets[idx] = Some(value.parse::<Length>().ok()?);
+        }
+
+        idx += 1;
+    }
+
+    if idx != offsets.len() {
         return None;
     }
 
-    Some(Rect::new(x, y, w, h))
+    Some(offsets)
 }
 
 /// Creates a free id for `clipPath`.
@@ -0,0 +1,261 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! CSS Color Level 4 syntax support.
+//!
+//! `svgdom` supports only SVG 1.1 colors, so colors with an alpha channel
+//! and functional notations are converted into `#rrggbb` and a separate alpha.
+//! ICC colors are converted into `#rrggbb` too.
+
+use super::icc;
+
+
+/// Converts a paint value with a CSS Color Level 4 or an ICC color.
+///
+/// Handles both plain colors and `url(...)` fallbacks.
+///
+/// Returns `None` when the value doesn't need to be changed or is invalid.
+pub fn normalize_paint(text: &str, profiles: &[icc::Profile]) -> Option<(String, f64)> {
+    let text = text.trim();
+    if text.starts_with("url(") {
+        let end = text.find(')')? + 1;
+        let (link, fallback) = text.split_at(end);
+        let (color, alpha) = normalize_icc(fallback, profiles)?;
+        return Some((format!("{} {}", link, color), alpha));
+    }
+
+    normalize_icc(text, profiles)
+}
+
+/// Converts `<color> icc-color(...)`.
+///
+/// The sRGB color is used when the ICC one cannot be resolved.
+fn normalize_icc(text: &str, profiles: &[icc::Profile]) -> Option<(String, f64)> {
+    let idx = match text.find("icc-color(") {
+        Some(idx) => idx,
+        None => return normalize(text),
+    };
+
+    let (fallback, icc_color) = text.split_at(idx);
+    if let Some(rgb) = icc::resolve(icc_color, profiles) {
+        return Some((to_hex(rgb), 1.0));
+    }
+
+    let fallback = fallback.trim();
+    if fallback.is_empty() {
+        return None;
+    }
+
+    Some(normalize(fallback).unwrap_or_else(|| (fallback.to_string(), 1.0)))
+}
+
+/// Converts a CSS Color Level 4 color into a `#rrggbb` color and an alpha.
+///
+/// Returns `None` when the color is supported by the SVG parser as is
+/// or is invalid.
+pub fn normalize(text: &str) -> Option<(String, f64)> {
+    let text = text.trim().to_lowercase();
+
+    let (rgb, alpha) = if text == "transparent" {
+        ([0, 0, 0], 0.0)
+    } else if text.starts_with('#') {
+        parse_hex_with_alpha(&text[1..])?
+    } else if let Some(args) = func_args(&text, &["rgb", "rgba"]) {
+        parse_rgb(&args)?
+    } else if let Some(args) = func_args(&text, &["hsl", "hsla"]) {
+        parse_hsl(&args)?
+    } else {
+        return None;
+    };
+
+    Some((to_hex(rgb), alpha))
+}
+
+fn to_hex(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+/// Parses `#rgba` and `#rrggbbaa`.
+///
+/// Other forms are supported by the SVG parser.
+fn parse_hex_with_alpha(text: &str) -> Option<([u8; 3], f64)> {
+    if !text.bytes().all(|c| (c as char).is_digit(16)) {
+        return None;
+    }
+
+    let digit = |i: usize| u8::from_str_radix(&text[i..i + 1], 16).ok();
+    let byte = |i: usize| u8::from_str_radix(&text[i..i + 2], 16).ok();
+
+    match text.len() {
+        4 => {
+            let rgb = [digit(0)? * 17, digit(1)? * 17, digit(2)? * 17];
+            Some((rgb, (digit(3)? * 17) as f64 / 255.0))
+        }
+        8 => {
+            let rgb = [byte(0)?, byte(2)?, byte(4)?];
+            Some((rgb, byte(6)? as f64 / 255.0))
+        }
+        _ => None,
+    }
+}
+
+/// Splits function arguments.
+///
+/// Both comma and space separated forms are supported.
+/// The alpha, if any, is always the fourth item.
+/// In the space separated form it must be separated by a slash.
+fn func_args(text: &str, names: &[&str]) -> Option<Vec<String>> {
+    let start = text.find('(')?;
+    if !names.contains(&text[..start].trim()) || !text.ends_with(')') {
+        return None;
+    }
+
+    let inner = &text[start + 1..text.len() - 1];
+
+    if inner.contains(',') {
+        // Slash and comma separated alpha cannot be mixed.
+        if inner.contains('/') {
+            return None;
+        }
+
+        let args: Vec<String> = inner.split(',').map(|s| s.trim().to_string()).collect();
+        if args.len() != 3 && args.len() != 4 {
+            return None;
+        }
+
+        return Some(args);
+    }
+
+    let (main, alpha) = match inner.find('/') {
+        Some(idx) => (&inner[..idx], Some(inner[idx + 1..].trim())),
+        None => (inner, None),
+    };
+
+    let mut args: Vec<String> = main.split_whitespace().map(|s| s.to_string()).collect();
+    if args.len() != 3 {
+        return None;
+    }
+
+    if let Some(alpha) = alpha {
+        if alpha.is_empty() || alpha.contains(char::is_whitespace) {
+            return None;
+        }
+
+        args.push(alpha.to_string());
+    }
+
+    Some(args)
+}
+
+fn parse_rgb(args: &[String]) -> Option<([u8; 3], f64)> {
+    let mut rgb = [0; 3];
+    for (i, arg) in args.iter().take(3).enumerate() {
+        let n = if arg.ends_with('%') {
+            parse_number(&arg[..arg.len() - 1])? * 2.55
+        } else {
+            parse_number(arg)?
+        };
+
+        rgb[i] = bound(0.0, n, 255.0).round() as u8;
+    }
+
+    Some((rgb, parse_alpha(args.get(3))?))
+}
+
+fn parse_hsl(args: &[String]) -> Option<([u8; 3], f64)> {
+    let hue = parse_hue(&args[0])?;
+    let saturation = bound(0.0, parse_percent(&args[1])?, 1.0);
+    let lightness = bound(0.0, parse_percent(&args[2])?, 1.0);
+
+    let hue = (hue % 360.0 + 360.0) % 360.0 / 360.0;
+    let t2 = if lightness <= 0.5 {
+        lightness * (saturation + 1.0)
+    } else {
+        lightness + saturation - lightness * saturation
+    };
+    let t1 = lightness * 2.0 - t2;
+
+    let to_byte = |n: f64| (bound(0.0, n, 1.0) * 255.0).round() as u8;
+    let rgb = [
+        to_byte(hue_to_rgb(t1, t2, hue + 1.0 / 3.0)),
+        to_byte(hue_to_rgb(t1, t2, hue)),
+        to_byte(hue_to_rgb(t1, t2, hue - 1.0 / 3.0)),
+    ];
+
+    Some((rgb, parse_alpha(args.get(3))?))
+}
+
+fn hue_to_rgb(t1: f64, t2: f64, mut hue: f64) -> f64 {
+    if hue < 0.0 {
+        hue += 1.0;
+    }
+
+    if hue > 1.0 {
+        hue -= 1.0;
+    }
+
+    if hue * 6.0 < 1.0 {
+        t1 + (t2 - t1) * hue * 6.0
+    } else if hue * 2.0 < 1.0 {
+        t2
+    } else if hue * 3.0 < 2.0 {
+        t1 + (t2 - t1) * (2.0 / 3.0 - hue) * 6.0
+    } else {
+        t1
+    }
+}
+
+/// Parses a hue in degrees.
+fn parse_hue(text: &str) -> Option<f64> {
+    let units = [("deg", 1.0), ("grad", 0.9), ("rad", 180.0 / ::std::f64::consts::PI), ("turn", 360.0)];
+    for &(unit, k) in &units {
+        if text.ends_with(unit) {
+            return parse_number(&text[..text.len() - unit.len()]).map(|n| n * k);
+        }
+    }
+
+    parse_number(text)
+}
+
+/// Parses a percentage into a 0..1 range.
+///
+/// Plain numbers are allowed too, like in the Level 4 syntax.
+fn parse_percent(text: &str) -> Option<f64> {
+    let text = if text.ends_with('%') { &text[..text.len() - 1] } else { text };
+    parse_number(text).map(|n| n / 100.0)
+}
+
+fn parse_alpha(text: Option<&String>) -> Option<f64> {
+    let text = match text {
+        Some(text) => text,
+        None => return Some(1.0),
+    };
+
+    let alpha = if text.ends_with('%') {
+        parse_number(&text[..text.len() - 1])? / 100.0
+    } else {
+        parse_number(text)?
+    };
+
+    Some(bound(0.0, alpha, 1.0))
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    let n: f64 = text.trim().parse().ok()?;
+    if n.is_finite() { Some(n) } else { None }
+}
+
+fn bound(min: f64, val: f64, max: f64) -> f64 {
+    debug_assert!(min.is_finite());
+    debug_assert!(val.is_finite());
+    debug_assert!(max.is_finite());
+
+    if val > max {
+        max
+    } else if val < min {
+        min
+    } else {
+        val
+    }
+}
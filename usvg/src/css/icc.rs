@@ -0,0 +1,726 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! ICC colors support.
+//!
+//! ICC colors are converted to sRGB using the referenced profile:
+//!
+//! - Profiles with an `A2B0`, `A2B1` or `A2B2` tag are evaluated using this LUT.
+//!   `lut8`, `lut16` and `lutAtoB` types are supported.
+//! - RGB profiles without a LUT are evaluated using the matrix/TRC model.
+//! - Gray profiles without a LUT are evaluated using the `kTRC` tag.
+//! - Lab profiles without a LUT expect Lab values.
+//!
+//! The PCS color is then converted from D50 to sRGB using the relative
+//! colorimetric intent.
+//!
+//! Unknown profiles and profiles that cannot be loaded or evaluated
+//! will use the sRGB fallback color.
+
+use std::fs;
+use std::path::Path;
+
+// external
+use base64;
+
+// self
+use utils;
+use super::xml;
+
+
+/// The maximum number of LUT input channels.
+const MAX_CHANNELS: usize = 8;
+
+/// The PCS illuminant.
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+/// Converts a D50 XYZ color into a linear sRGB one, including the Bradford adaptation.
+const XYZ_TO_SRGB: [[f64; 3]; 3] = [
+    [ 3.1338561, -1.6168667, -0.4906146],
+    [-0.9787684,  1.9161415,  0.0334540],
+    [ 0.0719453, -0.2289914,  1.4052427],
+];
+
+pub struct Profile {
+    pub name: String,
+    channels: usize,
+    is_lab: bool,
+    transform: Transform,
+}
+
+enum Transform {
+    Lut(Lut),
+    MatrixTrc([Curve; 3], [[f64; 3]; 3]),
+    GrayTrc(Curve),
+    Lab,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pcs {
+    Xyz,
+    Lab,
+}
+
+/// A PCS values encoding.
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Lut8,
+    Lut16,
+    V4,
+}
+
+struct Lut {
+    pcs: Pcs,
+    encoding: Encoding,
+    a_curves: Vec<Curve>,
+    clut: Option<Clut>,
+    m_curves: Vec<Curve>,
+    matrix: Option<[f64; 12]>,
+    b_curves: Vec<Curve>,
+}
+
+enum Curve {
+    Identity,
+    Gamma(f64),
+    Table(Vec<f64>),
+    Parametric(u16, [f64; 7]),
+}
+
+struct Clut {
+    grid: Vec<usize>,
+    outputs: usize,
+    data: Vec<f64>,
+}
+
+/// Collects all `color-profile` elements with a supported profile.
+///
+/// `dirs` are the current file directory and the directory local profiles must be inside.
+/// Only embedded profiles are loaded when `dirs` is not set.
+pub fn collect_profiles(doc: &xml::Document, dirs: Option<(&Path, &Path)>) -> Vec<Profile> {
+    let mut profiles = Vec::new();
+    for elem in &doc.elements {
+        if elem.local_name() != "color-profile" {
+            continue;
+        }
+
+        let name = match elem.attribute("name") {
+            Some(name) => xml::unescape(name),
+            None => continue,
+        };
+
+        let href = match elem.attribute("xlink:href").or_else(|| elem.attribute("href")) {
+            Some(href) => xml::unescape(href),
+            None => {
+                warn!("Color profile '{}' without a link is not supported.", name);
+                continue;
+            }
+        };
+
+        match load_profile(&href, dirs) {
+            Some((channels, is_lab, transform)) => {
+                profiles.push(Profile { name, channels, is_lab, transform });
+            }
+            None => warn!("Color profile '{}' cannot be loaded. Fallback colors will be used.", name),
+        }
+    }
+
+    profiles
+}
+
+fn load_profile(href: &str, dirs: Option<(&Path, &Path)>) -> Option<(usize, bool, Transform)> {
+    let data = if href.starts_with("data:") {
+        let idx = href.find(',')?;
+        if !href[..idx].ends_with(";base64") {
+            return None;
+        }
+
+        let data: String = href[idx + 1..].chars().filter(|c| !c.is_whitespace()).collect();
+        base64::decode(&data).ok()?
+    } else {
+        let (dir, root_dir) = dirs?;
+        let path = utils::resolve_local_path(href, dir, root_dir)?;
+        fs::read(path).ok()?
+    };
+
+    parse_profile(&data)
+}
+
+/// Parses an ICC profile.
+///
+/// Returns the number of input channels, a Lab input flag and a transform.
+fn parse_profile(data: &[u8]) -> Option<(usize, bool, Transform)> {
+    if data.len() < 132 || &data[36..40] != b"acsp" {
+        return None;
+    }
+
+    let space = &data[16..20];
+    let channels = color_space_channels(space)?;
+    let is_lab = space == b"Lab ";
+
+    let pcs = match &data[20..24] {
+        b"XYZ " => Pcs::Xyz,
+        b"Lab " => Pcs::Lab,
+        _ => return None,
+    };
+
+    // Perceptual, relative colorimetric and saturation intents.
+    for sig in &[b"A2B0", b"A2B1", b"A2B2"] {
+        if let Some(tag) = find_tag(data, sig) {
+            let lut = parse_lut(tag, pcs)?;
+            return Some((channels, is_lab, Transform::Lut(lut)));
+        }
+    }
+
+    let transform = match space {
+        b"RGB " => {
+            let curve = |sig: &[u8; 4]| find_tag(data, sig).and_then(|tag| parse_curve(tag)).map(|v| v.0);
+            let xyz = |sig: &[u8; 4]| find_tag(data, sig).and_then(parse_xyz);
+
+            let curves = [curve(b"rTRC")?, curve(b"gTRC")?, curve(b"bTRC")?];
+            let (r, g, b) = (xyz(b"rXYZ")?, xyz(b"gXYZ")?, xyz(b"bXYZ")?);
+            let matrix = [
+                [r[0], g[0], b[0]],
+                [r[1], g[1], b[1]],
+                [r[2], g[2], b[2]],
+            ];
+
+            Transform::MatrixTrc(curves, matrix)
+        }
+        b"GRAY" => {
+            let (curve, _) = parse_curve(find_tag(data, b"kTRC")?)?;
+            Transform::GrayTrc(curve)
+        }
+        b"Lab " => Transform::Lab,
+        _ => return None,
+    };
+
+    Some((channels, is_lab, transform))
+}
+
+fn color_space_channels(space: &[u8]) -> Option<usize> {
+    match space {
+        b"GRAY" => Some(1),
+        b"RGB " | b"Lab " | b"XYZ " | b"CMY " | b"HSV " | b"HLS " |
+        b"YCbr" | b"Luv " | b"Yxy " => Some(3),
+        b"CMYK" => Some(4),
+        _ => {
+            // `2CLR` .. `FCLR`.
+            if &space[1..] != b"CLR" {
+                return None;
+            }
+
+            let n = (space[0] as char).to_digit(16)? as usize;
+            if n >= 2 && n <= MAX_CHANNELS {
+                Some(n)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn find_tag<'a>(data: &'a [u8], sig: &[u8; 4]) -> Option<&'a [u8]> {
+    let count = read_u32(data, 128)? as usize;
+    for i in 0..count {
+        let entry = 132 + i * 12;
+        if data.get(entry..entry + 4)? == sig {
+            let offset = read_u32(data, entry + 4)? as usize;
+            let size = read_u32(data, entry + 8)? as usize;
+            return data.get(offset..offset.checked_add(size)?);
+        }
+    }
+
+    None
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Option<u8> {
+    data.get(offset).cloned()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let b = data.get(offset..offset + 2)?;
+    Some((b[0] as u16) << 8 | b[1] as u16)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let b = data.get(offset..offset + 4)?;
+    Some((b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32)
+}
+
+fn read_s15_16(data: &[u8], offset: usize) -> Option<f64> {
+    Some(read_u32(data, offset)? as i32 as f64 / 65536.0)
+}
+
+fn parse_xyz(data: &[u8]) -> Option<[f64; 3]> {
+    if data.get(0..4)? != b"XYZ " {
+        return None;
+    }
+
+    Some([read_s15_16(data, 8)?, read_s15_16(data, 12)?, read_s15_16(data, 16)?])
+}
+
+/// Parses a `curv` or `para` curve.
+///
+/// Returns a curve and its size in bytes.
+fn parse_curve(data: &[u8]) -> Option<(Curve, usize)> {
+    match data.get(0..4)? {
+        b"curv" => {
+            let count = read_u32(data, 8)? as usize;
+            let size = count.checked_mul(2)?.checked_add(12)?;
+            let curve = match count {
+                0 => Curve::Identity,
+                1 => Curve::Gamma(read_u16(data, 12)? as f64 / 256.0),
+                _ => {
+                    let mut table = Vec::with_capacity(count);
+                    for i in 0..count {
+                        table.push(read_u16(data, 12 + i * 2)? as f64 / 65535.0);
+                    }
+
+                    Curve::Table(table)
+                }
+            };
+
+            Some((curve, size))
+        }
+        b"para" => {
+            let kind = read_u16(data, 8)?;
+            let count = match kind {
+                0 => 1,
+                1 => 3,
+                2 => 4,
+                3 => 5,
+                4 => 7,
+                _ => return None,
+            };
+
+            let mut params = [0.0; 7];
+            for i in 0..count {
+                params[i] = read_s15_16(data, 12 + i * 4)?;
+            }
+
+            Some((Curve::Parametric(kind, params), 12 + count * 4))
+        }
+        _ => None,
+    }
+}
+
+/// Parses `count` 4-byte aligned curves.
+fn parse_curves(data: &[u8], mut offset: usize, count: usize) -> Option<Vec<Curve>> {
+    let mut curves = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (curve, size) = parse_curve(data.get(offset..)?)?;
+        curves.push(curve);
+        offset = offset.checked_add((size + 3) & !3)?;
+    }
+
+    Some(curves)
+}
+
+fn parse_lut(data: &[u8], pcs: Pcs) -> Option<Lut> {
+    match data.get(0..4)? {
+        b"mft1" => parse_lut8_16(data, pcs, false),
+        b"mft2" => parse_lut8_16(data, pcs, true),
+        b"mAB " => parse_lut_a_to_b(data, pcs),
+        _ => None,
+    }
+}
+
+/// Parses `lut8` and `lut16` types.
+///
+/// The matrix is ignored, since it's used only with the XYZ input.
+fn parse_lut8_16(data: &[u8], pcs: Pcs, is_16: bool) -> Option<Lut> {
+    let inputs = read_u8(data, 8)? as usize;
+    let outputs = read_u8(data, 9)? as usize;
+    let grid = read_u8(data, 10)? as usize;
+    if inputs == 0 || inputs > MAX_CHANNELS || outputs != 3 || grid < 2 {
+        return None;
+    }
+
+    let (in_entries, out_entries, mut offset) = if is_16 {
+        (read_u16(data, 48)? as usize, read_u16(data, 50)? as usize, 52)
+    } else {
+        (256, 256, 48)
+    };
+
+    if in_entries < 2 || out_entries < 2 {
+        return None;
+    }
+
+    let read = |offset: usize| -> Option<f64> {
+        if is_16 {
+            Some(read_u16(data, offset)? as f64 / 65535.0)
+        } else {
+            Some(read_u8(data, offset)? as f64 / 255.0)
+        }
+    };
+    let item_size = if is_16 { 2 } else { 1 };
+
+    let read_tables = |count: usize, entries: usize, offset: &mut usize| -> Option<Vec<Curve>> {
+        let mut tables = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut table = Vec::with_capacity(entries);
+            for i in 0..entries {
+                table.push(read(*offset + i * item_size)?);
+            }
+
+            *offset += entries * item_size;
+            tables.push(Curve::Table(table));
+        }
+
+        Some(tables)
+    };
+
+    let a_curves = read_tables(inputs, in_entries, &mut offset)?;
+
+    let grid = vec![grid; inputs];
+    let points = grid.iter().try_fold(1usize, |acc, g| acc.checked_mul(*g))?;
+    let len = points.checked_mul(outputs)?;
+    if data.len() < offset.checked_add(len.checked_mul(item_size)?)? {
+        return None;
+    }
+
+    let mut clut_data = Vec::with_capacity(len);
+    for i in 0..len {
+        clut_data.push(read(offset + i * item_size)?);
+    }
+    offset += len * item_size;
+
+    let b_curves = read_tables(outputs, out_entries, &mut offset)?;
+
+    Some(Lut {
+        pcs,
+        encoding: if is_16 { Encoding::Lut16 } else { Encoding::Lut8 },
+        a_curves,
+        clut: Some(Clut { grid, outputs, data: clut_data }),
+        m_curves: Vec::new(),
+        matrix: None,
+        b_curves,
+    })
+}
+
+/// Parses the `lutAtoBType` type.
+fn parse_lut_a_to_b(data: &[u8], pcs: Pcs) -> Option<Lut> {
+    let inputs = read_u8(data, 8)? as usize;
+    let outputs = read_u8(data, 9)? as usize;
+    if inputs == 0 || inputs > MAX_CHANNELS || outputs != 3 {
+        return None;
+    }
+
+    let b_offset = read_u32(data, 12)? as usize;
+    let matrix_offset = read_u32(data, 16)? as usize;
+    let m_offset = read_u32(data, 20)? as usize;
+    let clut_offset = read_u32(data, 24)? as usize;
+    let a_offset = read_u32(data, 28)? as usize;
+
+    // B curves are required.
+    if b_offset == 0 {
+        return None;
+    }
+
+    let b_curves = parse_curves(data, b_offset, outputs)?;
+
+    let m_curves = if m_offset != 0 {
+        parse_curves(data, m_offset, outputs)?
+    } else {
+        Vec::new()
+    };
+
+    let matrix = if matrix_offset != 0 {
+        let mut matrix = [0.0; 12];
+        for (i, v) in matrix.iter_mut().enumerate() {
+            *v = read_s15_16(data, matrix_offset + i * 4)?;
+        }
+
+        Some(matrix)
+    } else {
+        None
+    };
+
+    let a_curves = if a_offset != 0 {
+        parse_curves(data, a_offset, inputs)?
+    } else {
+        Vec::new()
+    };
+
+    let clut = if clut_offset != 0 {
+        let mut grid = Vec::with_capacity(inputs);
+        for i in 0..inputs {
+            let g = read_u8(data, clut_offset + i)? as usize;
+            if g < 2 {
+                return None;
+            }
+
+            grid.push(g);
+        }
+
+        let precision = read_u8(data, clut_offset + 16)? as usize;
+        if precision != 1 && precision != 2 {
+            return None;
+        }
+
+        let points = grid.iter().try_fold(1usize, |acc, g| acc.checked_mul(*g))?;
+        let len = points.checked_mul(outputs)?;
+        let start = clut_offset + 20;
+        if data.len() < start.checked_add(len.checked_mul(precision)?)? {
+            return None;
+        }
+
+        let mut clut_data = Vec::with_capacity(len);
+        for i in 0..len {
+            let v = if precision == 1 {
+                read_u8(data, start + i)? as f64 / 255.0
+            } else {
+                read_u16(data, start + i * 2)? as f64 / 65535.0
+            };
+
+            clut_data.push(v);
+        }
+
+        Some(Clut { grid, outputs, data: clut_data })
+    } else {
+        // Without a CLUT, the number of channels cannot be changed.
+        if inputs != outputs {
+            return None;
+        }
+
+        None
+    };
+
+    Some(Lut {
+        pcs,
+        encoding: Encoding::V4,
+        a_curves,
+        clut,
+        m_curves,
+        matrix,
+        b_curves,
+    })
+}
+
+impl Curve {
+    fn eval(&self, x: f64) -> f64 {
+        let x = clamp(x);
+        let y = match *self {
+            Curve::Identity => x,
+            Curve::Gamma(g) => x.powf(g),
+            Curve::Table(ref table) => {
+                let pos = x * (table.len() - 1) as f64;
+                let idx = (pos.floor() as usize).min(table.len() - 2);
+                let t = pos - idx as f64;
+                table[idx] + (table[idx + 1] - table[idx]) * t
+            }
+            Curve::Parametric(kind, p) => {
+                let (g, a, b, c, d, e, f) = (p[0], p[1], p[2], p[3], p[4], p[5], p[6]);
+                match kind {
+                    0 => x.powf(g),
+                    1 => if x >= -b / a { (a * x + b).powf(g) } else { 0.0 },
+                    2 => if x >= -b / a { (a * x + b).powf(g) + c } else { c },
+                    3 => if x >= d { (a * x + b).powf(g) } else { c * x },
+                    _ => if x >= d { (a * x + b).powf(g) + e } else { c * x + f },
+                }
+            }
+        };
+
+        if y.is_finite() { clamp(y) } else { 0.0 }
+    }
+}
+
+impl Clut {
+    /// Evaluates a CLUT using the multilinear interpolation.
+    fn eval(&self, input: &[f64]) -> Vec<f64> {
+        let n = self.grid.len();
+
+        // The first input channel varies least rapidly.
+        let mut strides = vec![self.outputs; n];
+        for i in (0..n.saturating_sub(1)).rev() {
+            strides[i] = strides[i + 1] * self.grid[i + 1];
+        }
+
+        let mut base = vec![0; n];
+        let mut fract = vec![0.0; n];
+        for i in 0..n {
+            let pos = clamp(input.get(i).cloned().unwrap_or(0.0)) * (self.grid[i] - 1) as f64;
+            base[i] = (pos.floor() as usize).min(self.grid[i] - 2);
+            fract[i] = pos - base[i] as f64;
+        }
+
+        let mut output = vec![0.0; self.outputs];
+        for corner in 0..(1usize << n) {
+            let mut weight = 1.0;
+            let mut idx = 0;
+            for i in 0..n {
+                if corner & (1 << i) != 0 {
+                    weight *= fract[i];
+                    idx += (base[i] + 1) * strides[i];
+                } else {
+                    weight *= 1.0 - fract[i];
+                    idx += base[i] * strides[i];
+                }
+            }
+
+            if weight == 0.0 {
+                continue;
+            }
+
+            for (j, v) in output.iter_mut().enumerate() {
+                *v += self.data.get(idx + j).cloned().unwrap_or(0.0) * weight;
+            }
+        }
+
+        output
+    }
+}
+
+impl Lut {
+    /// Evaluates a LUT and returns a D50 XYZ color.
+    fn eval(&self, input: &[f64]) -> [f64; 3] {
+        let mut values = input.to_vec();
+        apply_curves(&self.a_curves, &mut values);
+
+        if let Some(ref clut) = self.clut {
+            values = clut.eval(&values);
+        }
+
+        apply_curves(&self.m_curves, &mut values);
+
+        if let Some(ref m) = self.matrix {
+            let (x, y, z) = (values[0], values[1], values[2]);
+            values = vec![
+                clamp(m[0] * x + m[1] * y + m[2] * z + m[9]),
+                clamp(m[3] * x + m[4] * y + m[5] * z + m[10]),
+                clamp(m[6] * x + m[7] * y + m[8] * z + m[11]),
+            ];
+        }
+
+        apply_curves(&self.b_curves, &mut values);
+
+        let v = [values[0], values[1], values[2]];
+        match self.pcs {
+            Pcs::Xyz => {
+                // u1Fixed15Number
+                let scale = 65535.0 / 32768.0;
+                [v[0] * scale, v[1] * scale, v[2] * scale]
+            }
+            Pcs::Lab => {
+                // The legacy 16-bit Lab encoding.
+                let scale = if self.encoding == Encoding::Lut16 { 65535.0 / 65280.0 } else { 1.0 };
+                lab_to_xyz([v[0] * scale * 100.0, v[1] * scale * 255.0 - 128.0, v[2] * scale * 255.0 - 128.0])
+            }
+        }
+    }
+
+    /// Encodes a Lab color as a LUT input.
+    fn encode_lab(&self, lab: &[f64]) -> Vec<f64> {
+        let scale = if self.encoding == Encoding::Lut16 { 65280.0 / 65535.0 } else { 1.0 };
+        vec![
+            lab[0] / 100.0 * scale,
+            (lab[1] + 128.0) / 255.0 * scale,
+            (lab[2] + 128.0) / 255.0 * scale,
+        ]
+    }
+}
+
+fn apply_curves(curves: &[Curve], values: &mut [f64]) {
+    for (v, curve) in values.iter_mut().zip(curves) {
+        *v = curve.eval(*v);
+    }
+}
+
+impl Profile {
+    /// Converts a profile color into a D50 XYZ color.
+    fn to_xyz(&self, values: &[f64]) -> [f64; 3] {
+        match self.transform {
+            Transform::Lut(ref lut) => {
+                if self.is_lab {
+                    lut.eval(&lut.encode_lab(values))
+                } else {
+                    lut.eval(values)
+                }
+            }
+            Transform::MatrixTrc(ref curves, ref m) => {
+                let r = curves[0].eval(values[0]);
+                let g = curves[1].eval(values[1]);
+                let b = curves[2].eval(values[2]);
+                [
+                    m[0][0] * r + m[0][1] * g + m[0][2] * b,
+                    m[1][0] * r + m[1][1] * g + m[1][2] * b,
+                    m[2][0] * r + m[2][1] * g + m[2][2] * b,
+                ]
+            }
+            Transform::GrayTrc(ref curve) => {
+                let y = curve.eval(values[0]);
+                [D50[0] * y, D50[1] * y, D50[2] * y]
+            }
+            Transform::Lab => lab_to_xyz([values[0], values[1], values[2]]),
+        }
+    }
+}
+
+fn lab_to_xyz(lab: [f64; 3]) -> [f64; 3] {
+    let fy = (lab[0] + 16.0) / 116.0;
+    let fx = fy + lab[1] / 500.0;
+    let fz = fy - lab[2] / 200.0;
+
+    let f_inv = |t: f64| {
+        if t > 6.0 / 29.0 {
+            t * t * t
+        } else {
+            3.0 * (6.0 / 29.0) * (6.0 / 29.0) * (t - 4.0 / 29.0)
+        }
+    };
+
+    [D50[0] * f_inv(fx), D50[1] * f_inv(fy), D50[2] * f_inv(fz)]
+}
+
+fn xyz_to_srgb(xyz: [f64; 3]) -> [u8; 3] {
+    let m = &XYZ_TO_SRGB;
+    let linear = [
+        m[0][0] * xyz[0] + m[0][1] * xyz[1] + m[0][2] * xyz[2],
+        m[1][0] * xyz[0] + m[1][1] * xyz[1] + m[1][2] * xyz[2],
+        m[2][0] * xyz[0] + m[2][1] * xyz[1] + m[2][2] * xyz[2],
+    ];
+
+    let to_byte = |c: f64| {
+        let c = clamp(c);
+        let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+        (clamp(c) * 255.0).round() as u8
+    };
+
+    [to_byte(linear[0]), to_byte(linear[1]), to_byte(linear[2])]
+}
+
+fn clamp(n: f64) -> f64 {
+    if n < 0.0 { 0.0 } else if n > 1.0 { 1.0 } else { n }
+}
+
+/// Converts `icc-color(name, ...)` to sRGB.
+pub fn resolve(text: &str, profiles: &[Profile]) -> Option<[u8; 3]> {
+    let text = text.trim();
+    if !text.starts_with("icc-color(") || !text.ends_with(')') {
+        return None;
+    }
+
+    let mut args = text[10..text.len() - 1].split(',').map(|s| s.trim());
+    let name = args.next()?;
+    let mut values = Vec::new();
+    for arg in args {
+        let n: f64 = arg.parse().ok()?;
+        values.push(n);
+    }
+
+    let profile = profiles.iter().find(|p| p.name == name)?;
+    if values.len() != profile.channels {
+        warn!("Invalid number of components for the '{}' color profile.", name);
+        return None;
+    }
+
+    // Lab values have their own ranges.
+    if !profile.is_lab {
+        for v in &mut values {
+            *v = clamp(*v);
+        }
+    }
+
+    Some(xyz_to_srgb(profile.to_xyz(&values)))
+}
@@ -0,0 +1,479 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! CSS support.
+//!
+//! `svgdom` supports only a small subset of CSS, so style sheets are resolved
+//! before the parsing, directly on the SVG text. All matched declarations
+//! are converted into presentation attributes and `style` elements are removed.
+//!
+//! CSS Color Level 4 and ICC colors are converted into SVG 1.1 ones at the same time.
+//! The alpha is stored in a separate `usvg-*-alpha` attribute, like `usvg-fill-alpha`.
+//!
+//! `context-fill` and `context-stroke` are replaced with `none`
+//! and stored in a separate `usvg-*-context` attribute, like `usvg-fill-context`.
+
+use std::path::Path;
+
+use self::selector::Specificity;
+use self::stylesheet::{
+    Declaration,
+    StyleSheet,
+};
+
+mod color;
+mod icc;
+mod selector;
+mod stylesheet;
+mod xml;
+
+
+/// Resolves style sheets and CSS colors.
+///
+/// `user_sheet` is applied after the document style sheets with the same,
+/// author-level, precedence. So it will override document rules
+/// with the same specificity.
+///
+/// `dirs` are the current file directory, which is used to resolve relative
+/// color profile paths, and the directory all color profiles must be inside.
+/// Local color profiles are not loaded when `dirs` is not set.
+///
+/// Returns `None` when there is nothing to change or the document
+/// cannot be processed. The original text should be used in this case.
+pub fn resolve_css(
+    text: &str,
+    user_sheet: Option<&str>,
+    dirs: Option<(&Path, &Path)>,
+) -> Option<String> {
+    // Skip documents without anything to resolve as soon as possible.
+    if user_sheet.is_none() && !may_need_resolving(text) {
+        return None;
+    }
+
+    let doc = match xml::parse(text) {
+        Some(doc) => doc,
+        None => {
+            // The document is passed to the real parser as is,
+            // which supports only a small subset of CSS.
+            warn!("Failed to scan the document. Style sheets and CSS colors are not resolved.");
+            return None;
+        }
+    };
+    let profiles = icc::collect_profiles(&doc, dirs);
+
+    let mut sheet = StyleSheet::new();
+    let mut style_elements = Vec::new();
+    for (idx, elem) in doc.elements.iter().enumerate() {
+        if elem.local_name() != "style" {
+            continue;
+        }
+
+        match elem.attribute("type") {
+            None | Some("") | Some("text/css") => {}
+            Some(kind) => {
+                warn!("'{}' style sheets are not supported. Skipped.", kind);
+                continue;
+            }
+        }
+
+        sheet.parse_more(&xml::text_content(&doc, elem));
+        style_elements.push(idx);
+    }
+
+    if let Some(user_sheet) = user_sheet {
+        sheet.parse_more(user_sheet);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (idx, elem) in doc.elements.iter().enumerate() {
+        // Children of the removed elements.
+        if elem.range.start < pos {
+            continue;
+        }
+
+        if style_elements.contains(&idx) {
+            out.push_str(&text[pos..elem.range.start]);
+            pos = elem.range.end;
+            continue;
+        }
+
+        let mut props = cascade(&doc, &sheet, idx);
+        resolve_colors(elem, &profiles, &mut props);
+
+        // Classes are already resolved.
+        let has_style =    elem.attribute("style").is_some()
+                        || elem.attribute("class").is_some();
+        if props.is_empty() && !has_style {
+            continue;
+        }
+
+        out.push_str(&text[pos..elem.start_tag.start]);
+        write_start_tag(elem, &props, text, &mut out);
+        pos = elem.start_tag.end;
+    }
+
+    // Nothing was changed.
+    if pos == 0 {
+        return None;
+    }
+
+    out.push_str(&text[pos..]);
+
+    Some(out)
+}
+
+/// Checks that a document may have style sheets, inline styles or colors to resolve.
+///
+/// It's a quick and conservative check, so false positives are fine.
+fn may_need_resolving(text: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "style", "class", "rgb", "hsl", "transparent", "icc-color", "context-",
+    ];
+
+    let bytes = text.as_bytes();
+    let contains = |keyword: &str| {
+        bytes.windows(keyword.len()).any(|w| w.eq_ignore_ascii_case(keyword.as_bytes()))
+    };
+
+    KEYWORDS.iter().any(|k| contains(k)) || has_hex_color_with_alpha(text)
+}
+
+/// Checks for `#rgba` and `#rrggbbaa` colors.
+fn has_hex_color_with_alpha(text: &str) -> bool {
+    text.split('#').skip(1).any(|s| {
+        let len = s.bytes().take_while(|c| (*c as char).is_digit(16)).count();
+        len == 4 || len == 8
+    })
+}
+
+/// Collects element's properties.
+///
+/// The order is: presentation attributes (they are not touched),
+/// style sheets, the `style` attribute and then the same for `!important`.
+fn cascade(doc: &xml::Document, sheet: &StyleSheet, idx: usize) -> Vec<(String, String)> {
+    let elem = &doc.elements[idx];
+    let inline = match elem.attribute("style") {
+        Some(style) => stylesheet::parse_declarations(&xml::unescape(style)),
+        None => Vec::new(),
+    };
+
+    // Important, is inline, specificity and order.
+    let mut list: Vec<((bool, bool, Specificity, usize), &Declaration)> = Vec::new();
+
+    for rule in &sheet.rules {
+        if rule.selector.matches(doc, idx) {
+            for decl in &rule.declarations {
+                let key = (decl.important, false, rule.specificity, list.len());
+                list.push((key, decl));
+            }
+        }
+    }
+
+    for decl in &inline {
+        let key = (decl.important, true, [0; 3], list.len());
+        list.push((key, decl));
+    }
+
+    list.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut props: Vec<(String, String)> = Vec::new();
+    for (_, decl) in list {
+        match decl.name.as_str() {
+            // CSS transform has a different syntax.
+            "transform" => {}
+            // Vendor-specific properties, like `-inkscape-font-specification`,
+            // and custom properties are not valid attribute names.
+            name if !xml::is_name(name) => {}
+            "marker" => {
+                for name in &["marker-start", "marker-mid", "marker-end"] {
+                    set_prop(&mut props, name, &decl.value);
+                }
+            }
+            _ => set_prop(&mut props, &decl.name, &decl.value),
+        }
+    }
+
+    props
+}
+
+/// Converts CSS Color Level 4 and ICC colors, `context-fill` and `context-stroke`.
+fn resolve_colors(elem: &xml::Element, profiles: &[icc::Profile], props: &mut Vec<(String, String)>) {
+    for name in &["fill", "stroke", "stop-color", "flood-color"] {
+        let value = match props.iter().find(|p| p.0 == *name) {
+            Some(prop) => prop.1.clone(),
+            None => {
+                match elem.attribute(name) {
+                    Some(value) => xml::unescape(value),
+                    None => continue,
+                }
+            }
+        };
+
+        let value = value.trim();
+        if value == "context-fill" || value == "context-stroke" {
+            set_prop(props, name, "none");
+            set_prop(props, &format!("usvg-{}-context", name), value);
+            continue;
+        }
+
+        if let Some((color, alpha)) = color::normalize_paint(value, profiles) {
+            set_prop(props, name, &color);
+
+            if alpha < 1.0 {
+                set_prop(props, &format!("usvg-{}-alpha", name), &alpha.to_string());
+            }
+        }
+    }
+}
+
+fn set_prop(props: &mut Vec<(String, String)>, name: &str, value: &str) {
+    if let Some(prop) = props.iter_mut().find(|p| p.0 == name) {
+        prop.1 = value.to_string();
+        return;
+    }
+
+    props.push((name.to_string(), value.to_string()));
+}
+
+fn write_start_tag(
+    elem: &xml::Element,
+    props: &[(String, String)],
+    text: &str,
+    out: &mut String,
+) {
+    out.push('<');
+    out.push_str(elem.name);
+
+    for attr in &elem.attributes {
+        if attr.name == "style" || attr.name == "class" {
+            continue;
+        }
+
+        // Overwritten by CSS.
+        if props.iter().any(|p| p.0 == attr.name) {
+            continue;
+        }
+
+        out.push(' ');
+        out.push_str(&text[attr.range.clone()]);
+    }
+
+    for &(ref name, ref value) in props {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        out.push_str(&xml::escape(value));
+        out.push('"');
+    }
+
+    if elem.self_closing {
+        out.push_str("/>");
+    } else {
+        out.push('>');
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::TempDir;
+
+    macro_rules! test {
+        ($name:ident, $input:expr, $output:expr) => {
+            #[test]
+            fn $name() {
+                assert_eq!(resolve_css($input, None, None).unwrap(), $output);
+            }
+        };
+    }
+
+    test!(type_selector,
+"<svg><style>rect { fill: red }</style><rect/><g/></svg>",
+"<svg><rect fill=\"red\"/><g/></svg>");
+
+    test!(class_and_id_specificity,
+"<svg><style>#r1 { fill: green } .a { fill: red }</style><rect id='r1' class='a'/></svg>",
+"<svg><rect id='r1' fill=\"green\"/></svg>");
+
+    test!(presentation_attribute_is_overridden,
+"<svg><style>.a { fill: red }</style><rect fill='blue' class='a b'/></svg>",
+"<svg><rect fill=\"red\"/></svg>");
+
+    test!(inline_style_wins,
+"<svg><style>.a { fill: red; stroke: blue }</style><rect class='a' style='fill:green'/></svg>",
+"<svg><rect fill=\"green\" stroke=\"blue\"/></svg>");
+
+    test!(important,
+"<svg><style>.a { fill: red !important }</style><rect class='a' style='fill:green'/></svg>",
+"<svg><rect fill=\"red\"/></svg>");
+
+    test!(combinators,
+"<svg><style>g > rect { fill: red } g rect + rect { stroke: blue } circle ~ path { opacity: 0.5 }</style>\
+<g><rect/><rect/></g><circle/><g/><path/></svg>",
+"<svg><g><rect fill=\"red\"/><rect fill=\"red\" stroke=\"blue\"/></g><circle/><g/><path opacity=\"0.5\"/></svg>");
+
+    test!(pseudo_classes,
+"<svg><style>rect:first-child { fill: red } rect:nth-child(2n) { fill: blue }</style>\
+<g><rect/><rect/><rect/></g></svg>",
+"<svg><g><rect fill=\"red\"/><rect fill=\"blue\"/><rect/></g></svg>");
+
+    test!(attribute_selectors,
+"<svg><style>[x] { fill: red } [id^=ab] { stroke: blue }</style><rect x='1' id='abc'/></svg>",
+"<svg><rect x='1' id='abc' fill=\"red\" stroke=\"blue\"/></svg>");
+
+    #[test]
+    fn user_sheet() {
+        let input = "<svg><style>.a { fill: red }</style><rect class='a'/><circle/></svg>";
+        let output = "<svg><rect fill=\"green\"/><circle fill=\"green\"/></svg>";
+        let user_sheet = ".a { fill: green } circle { fill: green }";
+        assert_eq!(resolve_css(input, Some(user_sheet), None).unwrap(), output);
+    }
+
+    test!(colors_with_alpha,
+"<svg><rect fill='rgba(255, 0, 0, 0.5)' stroke='#00ff0080'/><stop stop-color='transparent'/></svg>",
+"<svg><rect fill=\"#ff0000\" usvg-fill-alpha=\"0.5\" stroke=\"#00ff00\" usvg-stroke-alpha=\"0.5019607843137255\"/>\
+<stop stop-color=\"#000000\" usvg-stop-color-alpha=\"0\"/></svg>");
+
+    test!(color_functions,
+"<svg><style>rect { fill: hsl(120deg 100% 25% / 50%) }</style><rect/>\
+<path stroke='url(#lg1) rgb(0 0 255)'/><feFlood flood-color='hsla(0, 100%, 50%, 1)'/></svg>",
+"<svg><rect fill=\"#008000\" usvg-fill-alpha=\"0.5\"/>\
+<path stroke=\"url(#lg1) #0000ff\"/><feFlood flood-color=\"#ff0000\"/></svg>");
+
+    /// Creates a CMYK profile with a `lut16` `A2B0` tag, where `L = 100 * (1 - K)`.
+    fn cmyk_profile() -> Vec<u8> {
+        let mut tag = Vec::new();
+        tag.extend_from_slice(b"mft2\0\0\0\0");
+        tag.extend_from_slice(&[4, 3, 2, 0]);
+        tag.extend_from_slice(&[0; 36]); // matrix
+        tag.extend_from_slice(&[0, 2, 0, 2]); // table sizes
+        for _ in 0..4 {
+            tag.extend_from_slice(&[0, 0, 0xFF, 0xFF]);
+        }
+        for i in 0..16 {
+            // K is the last and the most rapidly changing input.
+            let l: &[u8] = if i % 2 == 0 { &[0xFF, 0] } else { &[0, 0] };
+            tag.extend_from_slice(l);
+            tag.extend_from_slice(&[0x80, 0, 0x80, 0]);
+        }
+        for _ in 0..3 {
+            tag.extend_from_slice(&[0, 0, 0xFF, 0xFF]);
+        }
+
+        let mut data = vec![0u8; 128];
+        data[16..20].copy_from_slice(b"CMYK");
+        data[20..24].copy_from_slice(b"Lab ");
+        data[36..40].copy_from_slice(b"acsp");
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(b"A2B0");
+        data.extend_from_slice(&[0, 0, 0, 144]);
+        data.extend_from_slice(&[0, 0, 0, tag.len() as u8]);
+        data.extend_from_slice(&tag);
+        data
+    }
+
+    #[test]
+    fn icc_colors() {
+        let dir = TempDir::new("icc-colors");
+        dir.write("cmyk.icc", &cmyk_profile());
+        let root_dir = dir.0.canonicalize().unwrap();
+
+        let input = "<svg><color-profile name='cmyk' xlink:href='cmyk.icc'/>\
+<rect fill='#CD853F icc-color(cmyk, 0.2, 0.5, 1, 0.5)' stroke='#CD853F icc-color(unknown, 0.1)'/>\
+<circle fill='#CD853F icc-color(cmyk, 0.5)'/></svg>";
+        let output = "<svg><color-profile name='cmyk' xlink:href='cmyk.icc'/>\
+<rect fill=\"#777777\" stroke=\"#CD853F\"/><circle fill=\"#CD853F\"/></svg>";
+        let dirs = Some((root_dir.as_path(), root_dir.as_path()));
+        assert_eq!(resolve_css(input, None, dirs).unwrap(), output);
+    }
+
+    #[test]
+    fn icc_profile_without_tags() {
+        // A header is not enough to convert a color.
+        let mut header = vec![0u8; 132];
+        header[16..20].copy_from_slice(b"CMYK");
+        header[20..24].copy_from_slice(b"Lab ");
+        header[36..40].copy_from_slice(b"acsp");
+        let dir = TempDir::new("icc-profile-without-tags");
+        dir.write("header.icc", &header);
+        let root_dir = dir.0.canonicalize().unwrap();
+
+        let input = "<svg><color-profile name='cmyk' xlink:href='header.icc'/>\
+<rect fill='#CD853F icc-color(cmyk, 0, 0.5, 1, 0.2)'/></svg>";
+        let output = "<svg><color-profile name='cmyk' xlink:href='header.icc'/>\
+<rect fill=\"#CD853F\"/></svg>";
+        let dirs = Some((root_dir.as_path(), root_dir.as_path()));
+        assert_eq!(resolve_css(input, None, dirs).unwrap(), output);
+    }
+
+    #[test]
+    fn icc_profile_access() {
+        let dir = TempDir::new("icc-profile-access");
+        dir.write("cmyk.icc", &cmyk_profile());
+        dir.write("doc/cmyk.icc", &cmyk_profile());
+        let root_dir = dir.0.join("doc").canonicalize().unwrap();
+
+        let svg = |href: &str| {
+            format!("<svg><color-profile name='cmyk' xlink:href='{}'/>\
+<rect fill='#CD853F icc-color(cmyk, 0.2, 0.5, 1, 0.5)'/></svg>", href)
+        };
+        let output = |href: &str, color: &str| {
+            format!("<svg><color-profile name='cmyk' xlink:href='{}'/>\
+<rect fill=\"{}\"/></svg>", href, color)
+        };
+        let dirs = Some((root_dir.as_path(), root_dir.as_path()));
+
+        // Local files are not loaded without directories.
+        assert_eq!(resolve_css(&svg("cmyk.icc"), None, None).unwrap(),
+                   output("cmyk.icc", "#CD853F"));
+
+        assert_eq!(resolve_css(&svg("cmyk.icc"), None, dirs).unwrap(),
+                   output("cmyk.icc", "#777777"));
+
+        // Files outside the root directory are not loaded.
+        assert_eq!(resolve_css(&svg("../cmyk.icc"), None, dirs).unwrap(),
+                   output("../cmyk.icc", "#CD853F"));
+
+        let abs_path = dir.0.join("cmyk.icc").canonicalize().unwrap();
+        let abs_path = abs_path.to_str().unwrap();
+        assert_eq!(resolve_css(&svg(abs_path), None, dirs).unwrap(),
+                   output(abs_path, "#CD853F"));
+    }
+
+    test!(context_paint,
+"<svg><marker><path fill='context-stroke' style='stroke: context-fill'/></marker></svg>",
+"<svg><marker><path stroke=\"none\" fill=\"none\" \
+usvg-fill-context=\"context-stroke\" usvg-stroke-context=\"context-fill\"/></marker></svg>");
+
+    #[test]
+    fn nothing_to_change() {
+        assert_eq!(resolve_css("<svg><rect fill='#f00' stroke='red'/></svg>", None, None), None);
+    }
+
+    #[test]
+    fn invalid_color_syntax() {
+        let input = "<svg><rect fill='rgb(255 0 0 0.5)' stroke='rgb(255, 0, 0 / 0.5)'/></svg>";
+        assert_eq!(resolve_css(input, None, None), None);
+    }
+
+    #[test]
+    fn malformed_document() {
+        let input = "<svg><style>rect { fill: red }</style><rect/></g></svg>";
+        assert_eq!(resolve_css(input, None, None), None);
+    }
+
+    test!(vendor_specific_properties,
+"<svg><rect style='fill:red;-inkscape-font-specification:Sans;--x:1'/></svg>",
+"<svg><rect fill=\"red\"/></svg>");
+
+    test!(custom_entities,
+"<svg><rect style='fill:&color;;stroke:&lt;'/></svg>",
+"<svg><rect fill=\"&color;\" stroke=\"&lt;\"/></svg>");
+
+    test!(cdata_and_at_rules,
+"<svg><style><![CDATA[@import url(a.css); @media print { rect { fill: red } } rect > g { fill: blue }]]></style><rect/></svg>",
+"<svg><rect/></svg>");
+}
@@ -0,0 +1,485 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::xml::{
+    self,
+    Document,
+};
+
+
+/// A selector specificity.
+///
+/// IDs, classes/attributes/pseudo-classes and types count.
+pub type Specificity = [u32; 3];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Combinator {
+    None,
+    Descendant,
+    Child,
+    AdjacentSibling,
+    GeneralSibling,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum AttributeOperator {
+    Exists,
+    Matches,
+    Contains,
+    DashMatches,
+    Prefix,
+    Suffix,
+    Substring,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PseudoClass {
+    FirstChild,
+    LastChild,
+    OnlyChild,
+    /// `an+b`
+    NthChild(i32, i32),
+    /// `an+b`
+    NthLastChild(i32, i32),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum SubSelector {
+    Id(String),
+    Class(String),
+    Attribute(String, AttributeOperator, String),
+    PseudoClass(PseudoClass),
+}
+
+#[derive(Clone, Debug)]
+struct Component {
+    /// A combinator with the previous component.
+    combinator: Combinator,
+    /// `None` is a universal selector.
+    tag: Option<String>,
+    subs: Vec<SubSelector>,
+}
+
+/// A complex selector, like `g > rect.red`.
+#[derive(Clone, Debug)]
+pub struct Selector {
+    components: Vec<Component>,
+}
+
+impl Selector {
+    /// Parses a single selector.
+    ///
+    /// Returns `None` when the selector is invalid or not supported.
+    pub fn parse(text: &str) -> Option<Selector> {
+        let mut s = Stream { text, pos: 0 };
+        let mut components = Vec::new();
+        let mut combinator = Combinator::None;
+
+        loop {
+            let had_space = s.skip_spaces();
+            if s.at_end() {
+                break;
+            }
+
+            match s.curr() {
+                Some('>') => { s.advance(1); combinator = Combinator::Child; continue; }
+                Some('+') => { s.advance(1); combinator = Combinator::AdjacentSibling; continue; }
+                Some('~') => { s.advance(1); combinator = Combinator::GeneralSibling; continue; }
+                _ => {}
+            }
+
+            if !components.is_empty() && combinator == Combinator::None {
+                if had_space {
+                    combinator = Combinator::Descendant;
+                } else {
+                    return None;
+                }
+            }
+
+            let mut comp = parse_compound(&mut s)?;
+            comp.combinator = combinator;
+            components.push(comp);
+            combinator = Combinator::None;
+        }
+
+        // A dangling combinator.
+        if components.is_empty() || combinator != Combinator::None {
+            return None;
+        }
+
+        Some(Selector { components })
+    }
+
+    pub fn specificity(&self) -> Specificity {
+        let mut spec = [0; 3];
+        for comp in &self.components {
+            if comp.tag.is_some() {
+                spec[2] += 1;
+            }
+
+            for sub in &comp.subs {
+                match *sub {
+                    SubSelector::Id(_) => spec[0] += 1,
+                    _ => spec[1] += 1,
+                }
+            }
+        }
+
+        spec
+    }
+
+    /// Checks that the element matches the selector.
+    pub fn matches(&self, doc: &Document, idx: usize) -> bool {
+        self.match_at(doc, idx, self.components.len() - 1)
+    }
+
+    fn match_at(&self, doc: &Document, idx: usize, comp_idx: usize) -> bool {
+        let comp = &self.components[comp_idx];
+        if !match_compound(comp, doc, idx) {
+            return false;
+        }
+
+        if comp_idx == 0 {
+            return true;
+        }
+
+        let prev_idx = comp_idx - 1;
+        match comp.combinator {
+            Combinator::Descendant => {
+                let mut parent = doc.elements[idx].parent;
+                while let Some(p) = parent {
+                    if self.match_at(doc, p, prev_idx) {
+                        return true;
+                    }
+
+                    parent = doc.elements[p].parent;
+                }
+
+                false
+            }
+            Combinator::Child => {
+                match doc.elements[idx].parent {
+                    Some(p) => self.match_at(doc, p, prev_idx),
+                    None => false,
+                }
+            }
+            Combinator::AdjacentSibling => {
+                let siblings = doc.siblings(idx);
+                let pos = sibling_pos(siblings, idx);
+                pos > 0 && self.match_at(doc, siblings[pos - 1], prev_idx)
+            }
+            Combinator::GeneralSibling => {
+                let siblings = doc.siblings(idx);
+                let pos = sibling_pos(siblings, idx);
+                siblings[..pos].iter().any(|i| self.match_at(doc, *i, prev_idx))
+            }
+            Combinator::None => false,
+        }
+    }
+}
+
+fn sibling_pos(siblings: &[usize], idx: usize) -> usize {
+    siblings.iter().position(|i| *i == idx).unwrap_or(0)
+}
+
+fn match_compound(comp: &Component, doc: &Document, idx: usize) -> bool {
+    let elem = &doc.elements[idx];
+
+    if let Some(ref tag) = comp.tag {
+        if elem.local_name() != tag {
+            return false;
+        }
+    }
+
+    for sub in &comp.subs {
+        let is_match = match *sub {
+            SubSelector::Id(ref id) => {
+                elem.attribute("id").map(xml::unescape).as_ref() == Some(id)
+            }
+            SubSelector::Class(ref class) => {
+                match elem.attribute("class") {
+                    Some(value) => xml::unescape(value).split_whitespace().any(|c| c == class),
+                    None => false,
+                }
+            }
+            SubSelector::Attribute(ref name, op, ref value) => {
+                match elem.attribute(name) {
+                    Some(attr_value) => match_attribute(&xml::unescape(attr_value), op, value),
+                    None => false,
+                }
+            }
+            SubSelector::PseudoClass(pc) => {
+                let siblings = doc.siblings(idx);
+                let pos = sibling_pos(siblings, idx) as i32;
+                let len = siblings.len() as i32;
+                match pc {
+                    PseudoClass::FirstChild => pos == 0,
+                    PseudoClass::LastChild => pos == len - 1,
+                    PseudoClass::OnlyChild => len == 1,
+                    PseudoClass::NthChild(a, b) => match_nth(a, b, pos + 1),
+                    PseudoClass::NthLastChild(a, b) => match_nth(a, b, len - pos),
+                }
+            }
+        };
+
+        if !is_match {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn match_attribute(attr_value: &str, op: AttributeOperator, value: &str) -> bool {
+    match op {
+        AttributeOperator::Exists => true,
+        AttributeOperator::Matches => attr_value == value,
+        AttributeOperator::Contains => attr_value.split_whitespace().any(|v| v == value),
+        AttributeOperator::DashMatches => {
+            attr_value == value || attr_value.starts_with(&format!("{}-", value))
+        }
+        AttributeOperator::Prefix => !value.is_empty() && attr_value.starts_with(value),
+        AttributeOperator::Suffix => !value.is_empty() && attr_value.ends_with(value),
+        AttributeOperator::Substring => !value.is_empty() && attr_value.contains(value),
+    }
+}
+
+/// Checks that `index` (1-based) can be represented as `a*n + b` with `n >= 0`.
+fn match_nth(a: i32, b: i32, index: i32) -> bool {
+    if a == 0 {
+        index == b
+    } else {
+        let diff = index - b;
+        diff % a == 0 && diff / a >= 0
+    }
+}
+
+fn parse_compound(s: &mut Stream) -> Option<Component> {
+    let mut comp = Component {
+        combinator: Combinator::None,
+        tag: None,
+        subs: Vec::new(),
+    };
+
+    let mut is_universal = false;
+    match s.curr()? {
+        '*' => {
+            s.advance(1);
+            is_universal = true;
+            // `*|rect`
+            if s.curr() == Some('|') {
+                s.advance(1);
+                comp.tag = parse_tag(s);
+            }
+        }
+        c if is_ident_start(c) => {
+            comp.tag = parse_tag(s);
+        }
+        _ => {}
+    }
+
+    loop {
+        match s.curr() {
+            Some('#') => {
+                s.advance(1);
+                comp.subs.push(SubSelector::Id(s.consume_ident()?));
+            }
+            Some('.') => {
+                s.advance(1);
+                comp.subs.push(SubSelector::Class(s.consume_ident()?));
+            }
+            Some('[') => {
+                s.advance(1);
+                comp.subs.push(parse_attribute(s)?);
+            }
+            Some(':') => {
+                s.advance(1);
+                comp.subs.push(SubSelector::PseudoClass(parse_pseudo_class(s)?));
+            }
+            _ => break,
+        }
+    }
+
+    if comp.tag.is_none() && comp.subs.is_empty() && !is_universal {
+        return None;
+    }
+
+    Some(comp)
+}
+
+fn parse_tag(s: &mut Stream) -> Option<String> {
+    let name = s.consume_ident()?;
+
+    // `svg|rect`
+    if s.curr() == Some('|') {
+        s.advance(1);
+        return s.consume_ident();
+    }
+
+    Some(name)
+}
+
+fn parse_attribute(s: &mut Stream) -> Option<SubSelector> {
+    s.skip_spaces();
+    let name = s.consume_ident()?;
+    s.skip_spaces();
+
+    let op = match s.curr()? {
+        ']' => {
+            s.advance(1);
+            return Some(SubSelector::Attribute(name, AttributeOperator::Exists, String::new()));
+        }
+        '=' => AttributeOperator::Matches,
+        '~' => AttributeOperator::Contains,
+        '|' => AttributeOperator::DashMatches,
+        '^' => AttributeOperator::Prefix,
+        '$' => AttributeOperator::Suffix,
+        '*' => AttributeOperator::Substring,
+        _ => return None,
+    };
+
+    s.advance(1);
+    if op != AttributeOperator::Matches {
+        if s.curr()? != '=' {
+            return None;
+        }
+
+        s.advance(1);
+    }
+
+    s.skip_spaces();
+    let value = match s.curr()? {
+        c @ '"' | c @ '\'' => {
+            s.advance(1);
+            let start = s.pos;
+            let len = s.text[start..].find(c)?;
+            s.advance(len + 1);
+            s.text[start..start + len].to_string()
+        }
+        _ => s.consume_ident()?,
+    };
+
+    s.skip_spaces();
+    if s.curr()? != ']' {
+        return None;
+    }
+    s.advance(1);
+
+    Some(SubSelector::Attribute(name, op, value))
+}
+
+fn parse_pseudo_class(s: &mut Stream) -> Option<PseudoClass> {
+    let name = s.consume_ident()?.to_lowercase();
+    match name.as_str() {
+        "first-child" => Some(PseudoClass::FirstChild),
+        "last-child" => Some(PseudoClass::LastChild),
+        "only-child" => Some(PseudoClass::OnlyChild),
+        "nth-child" | "nth-last-child" => {
+            if s.curr()? != '(' {
+                return None;
+            }
+
+            let start = s.pos + 1;
+            let len = s.text[start..].find(')')?;
+            let (a, b) = parse_nth(&s.text[start..start + len])?;
+            s.advance(len + 2);
+
+            if name == "nth-child" {
+                Some(PseudoClass::NthChild(a, b))
+            } else {
+                Some(PseudoClass::NthLastChild(a, b))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Parses `an+b`, `odd` and `even`.
+fn parse_nth(text: &str) -> Option<(i32, i32)> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let text = text.to_lowercase();
+    match text.as_str() {
+        "odd" => return Some((2, 1)),
+        "even" => return Some((2, 0)),
+        _ => {}
+    }
+
+    match text.find('n') {
+        Some(idx) => {
+            let a = match &text[..idx] {
+                "" | "+" => 1,
+                "-" => -1,
+                a => a.parse().ok()?,
+            };
+
+            let b = &text[idx + 1..];
+            let b = if b.is_empty() {
+                0
+            } else {
+                b.trim_left_matches('+').parse().ok()?
+            };
+
+            Some((a, b))
+        }
+        None => Some((0, text.parse().ok()?)),
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '-' || !c.is_ascii()
+}
+
+fn is_ident_char(c: char) -> bool {
+    is_ident_start(c) || c.is_digit(10)
+}
+
+struct Stream<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Stream<'a> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.text.len()
+    }
+
+    fn curr(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.pos += n;
+    }
+
+    /// Returns `true` if any spaces were skipped.
+    fn skip_spaces(&mut self) -> bool {
+        let start = self.pos;
+        while let Some(c) = self.curr() {
+            if !c.is_whitespace() {
+                break;
+            }
+
+            self.pos += c.len_utf8();
+        }
+
+        self.pos != start
+    }
+
+    fn consume_ident(&mut self) -> Option<String> {
+        let start = self.pos;
+        while let Some(c) = self.curr() {
+            if !is_ident_char(c) {
+                break;
+            }
+
+            self.pos += c.len_utf8();
+        }
+
+        if start == self.pos {
+            None
+        } else {
+            Some(self.text[start..self.pos].to_string())
+        }
+    }
+}
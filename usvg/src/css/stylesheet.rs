@@ -0,0 +1,226 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::selector::{
+    Selector,
+    Specificity,
+};
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Declaration {
+    pub name: String,
+    pub value: String,
+    pub important: bool,
+}
+
+pub struct Rule {
+    pub selector: Selector,
+    pub specificity: Specificity,
+    pub declarations: Vec<Declaration>,
+}
+
+#[derive(Default)]
+pub struct StyleSheet {
+    pub rules: Vec<Rule>,
+}
+
+impl StyleSheet {
+    pub fn new() -> Self {
+        StyleSheet::default()
+    }
+
+    /// Parses a style sheet and appends its rules.
+    ///
+    /// Invalid and unsupported rules are skipped. At-rules are ignored.
+    pub fn parse_more(&mut self, text: &str) {
+        let text = remove_comments(text);
+        let mut s = text.as_str();
+
+        loop {
+            s = s.trim_left();
+            // Skip HTML comment delimiters that can be used to hide CSS from legacy agents.
+            if s.starts_with("<!--") {
+                s = &s[4..];
+                continue;
+            }
+            if s.starts_with("-->") {
+                s = &s[3..];
+                continue;
+            }
+
+            if s.is_empty() {
+                break;
+            }
+
+            if s.starts_with('@') {
+                s = skip_at_rule(s);
+                continue;
+            }
+
+            let block_start = match find_unquoted(s, '{') {
+                Some(idx) => idx,
+                None => break,
+            };
+
+            let prelude = &s[..block_start];
+            let block_len = match block_len(&s[block_start..]) {
+                Some(len) => len,
+                None => break,
+            };
+            let block = &s[block_start + 1..block_start + block_len - 1];
+            s = &s[block_start + block_len..];
+
+            let declarations = parse_declarations(block);
+            for selector_text in prelude.split(',') {
+                match Selector::parse(selector_text) {
+                    Some(selector) => {
+                        self.rules.push(Rule {
+                            specificity: selector.specificity(),
+                            selector,
+                            declarations: declarations.clone(),
+                        });
+                    }
+                    None => {
+                        warn!("Unsupported CSS selector: '{}'. Skipped.", selector_text.trim());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parses a declarations block, like the `style` attribute content.
+pub fn parse_declarations(text: &str) -> Vec<Declaration> {
+    let text = remove_comments(text);
+    let mut list = Vec::new();
+    for decl in split_unquoted(&text, ';') {
+        let idx = match find_unquoted(decl, ':') {
+            Some(idx) => idx,
+            None => continue,
+        };
+
+        let name = decl[..idx].trim().to_lowercase();
+        let mut value = decl[idx + 1..].trim();
+        if name.is_empty() || value.is_empty() {
+            continue;
+        }
+
+        let mut important = false;
+        if let Some(idx) = value.rfind('!') {
+            if value[idx + 1..].trim().eq_ignore_ascii_case("important") {
+                important = true;
+                value = value[..idx].trim();
+            }
+        }
+
+        list.push(Declaration {
+            name,
+            value: value.to_string(),
+            important,
+        });
+    }
+
+    list
+}
+
+fn remove_comments(text: &str) -> String {
+    let mut s = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("/*") {
+        s.push_str(&rest[..start]);
+        s.push(' ');
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+
+    s.push_str(rest);
+    s
+}
+
+/// Skips an at-rule, like `@import url(a.css);` or `@media print { ... }`.
+fn skip_at_rule(s: &str) -> &str {
+    let semicolon = find_unquoted(s, ';');
+    let block = find_unquoted(s, '{');
+    match (semicolon, block) {
+        (Some(a), Some(b)) if a < b => &s[a + 1..],
+        (_, Some(b)) => {
+            match block_len(&s[b..]) {
+                Some(len) => &s[b + len..],
+                None => "",
+            }
+        }
+        (Some(a), None) => &s[a + 1..],
+        (None, None) => "",
+    }
+}
+
+/// Returns a length of the `{...}` block, including braces.
+fn block_len(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => {
+                match c {
+                    '"' | '\'' => quote = Some(c),
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(i + 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn find_unquoted(s: &str, needle: char) -> Option<usize> {
+    let mut quote = None;
+    let mut parens = 0;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => {
+                match c {
+                    '"' | '\'' => quote = Some(c),
+                    '(' => parens += 1,
+                    ')' => parens -= 1,
+                    _ if c == needle && parens == 0 => return Some(i),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut list = Vec::new();
+    let mut rest = s;
+    while let Some(idx) = find_unquoted(rest, sep) {
+        list.push(&rest[..idx]);
+        rest = &rest[idx + 1..];
+    }
+
+    list.push(rest);
+    list
+}
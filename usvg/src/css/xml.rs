@@ -0,0 +1,379 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A minimal XML scanner.
+//!
+//! Builds an elements tree with byte ranges of the original text,
+//! so start tags can be rewritten without touching anything else.
+
+use std::ops::Range;
+
+
+pub struct Attribute<'a> {
+    pub name: &'a str,
+    /// A raw value. Entities are not resolved.
+    pub value: &'a str,
+    /// A whole attribute range, including the name and quotes.
+    pub range: Range<usize>,
+}
+
+pub struct Element<'a> {
+    pub name: &'a str,
+    pub attributes: Vec<Attribute<'a>>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// A start tag range, including `<` and `>`.
+    pub start_tag: Range<usize>,
+    pub self_closing: bool,
+    /// A content range. Empty for self-closing elements.
+    pub content: Range<usize>,
+    /// A whole element range, including the end tag.
+    pub range: Range<usize>,
+}
+
+impl<'a> Element<'a> {
+    /// Returns an element name without a namespace prefix.
+    pub fn local_name(&self) -> &'a str {
+        match self.name.find(':') {
+            Some(idx) => &self.name[idx + 1..],
+            None => self.name,
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.attributes.iter().find(|a| a.name == name).map(|a| a.value)
+    }
+}
+
+pub struct Document<'a> {
+    pub text: &'a str,
+    pub elements: Vec<Element<'a>>,
+    /// Top-level elements.
+    pub roots: Vec<usize>,
+}
+
+impl<'a> Document<'a> {
+    /// Returns element siblings, including the element itself.
+    pub fn siblings(&self, idx: usize) -> &[usize] {
+        match self.elements[idx].parent {
+            Some(parent) => &self.elements[parent].children,
+            None => &self.roots,
+        }
+    }
+}
+
+/// Parses an XML text.
+///
+/// Returns `None` on any error. The input should be passed to the real parser then.
+pub fn parse(text: &str) -> Option<Document> {
+    let mut doc = Document {
+        text,
+        elements: Vec::new(),
+        roots: Vec::new(),
+    };
+
+    let mut stack: Vec<usize> = Vec::new();
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let s = &text[pos..];
+        if !s.starts_with('<') {
+            pos += s.find('<').unwrap_or(s.len());
+        } else if s.starts_with("<!--") {
+            pos += s.find("-->")? + 3;
+        } else if s.starts_with("<![CDATA[") {
+            pos += s.find("]]>")? + 3;
+        } else if s.starts_with("<!") {
+            pos += skip_doctype(s)?;
+        } else if s.starts_with("<?") {
+            pos += s.find("?>")? + 2;
+        } else if s.starts_with("</") {
+            let len = s.find('>')? + 1;
+            let name = s[2..len - 1].trim();
+            let idx = stack.pop()?;
+            if doc.elements[idx].name != name {
+                return None;
+            }
+
+            doc.elements[idx].content.end = pos;
+            doc.elements[idx].range.end = pos + len;
+            pos += len;
+        } else {
+            let mut elem = parse_start_tag(text, pos)?;
+            elem.parent = stack.last().cloned();
+
+            let idx = doc.elements.len();
+            match elem.parent {
+                Some(parent) => doc.elements[parent].children.push(idx),
+                None => doc.roots.push(idx),
+            }
+
+            pos = elem.start_tag.end;
+            let self_closing = elem.self_closing;
+            doc.elements.push(elem);
+
+            if !self_closing {
+                stack.push(idx);
+            }
+        }
+    }
+
+    if !stack.is_empty() {
+        return None;
+    }
+
+    Some(doc)
+}
+
+fn skip_doctype(s: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => {
+                match c {
+                    '"' | '\'' => quote = Some(c),
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    '>' if depth == 0 => return Some(i + 1),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn parse_start_tag(text: &str, start: usize) -> Option<Element> {
+    let bytes = text.as_bytes();
+    let mut pos = start + 1;
+
+    let name_start = pos;
+    while pos < bytes.len() && !is_space(bytes[pos]) && bytes[pos] != b'/' && bytes[pos] != b'>' {
+        pos += 1;
+    }
+    let name = &text[name_start..pos];
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut attributes = Vec::new();
+    loop {
+        while pos < bytes.len() && is_space(bytes[pos]) {
+            pos += 1;
+        }
+
+        match *bytes.get(pos)? {
+            b'>' => {
+                pos += 1;
+                return Some(new_element(name, attributes, start..pos, false));
+            }
+            b'/' => {
+                if bytes.get(pos + 1) != Some(&b'>') {
+                    return None;
+                }
+
+                pos += 2;
+                return Some(new_element(name, attributes, start..pos, true));
+            }
+            _ => {}
+        }
+
+        let attr_start = pos;
+        while pos < bytes.len() && !is_space(bytes[pos]) && bytes[pos] != b'=' {
+            pos += 1;
+        }
+        let attr_name = &text[attr_start..pos];
+
+        while pos < bytes.len() && is_space(bytes[pos]) {
+            pos += 1;
+        }
+
+        if bytes.get(pos) != Some(&b'=') {
+            return None;
+        }
+        pos += 1;
+
+        while pos < bytes.len() && is_space(bytes[pos]) {
+            pos += 1;
+        }
+
+        let quote = *bytes.get(pos)?;
+        if quote != b'"' && quote != b'\'' {
+            return None;
+        }
+        pos += 1;
+
+        let value_start = pos;
+        while pos < bytes.len() && bytes[pos] != quote {
+            pos += 1;
+        }
+
+        if pos == bytes.len() {
+            return None;
+        }
+
+        let value = &text[value_start..pos];
+        pos += 1;
+
+        attributes.push(Attribute {
+            name: attr_name,
+            value,
+            range: attr_start..pos,
+        });
+    }
+}
+
+fn new_element<'a>(
+    name: &'a str,
+    attributes: Vec<Attribute<'a>>,
+    start_tag: Range<usize>,
+    self_closing: bool,
+) -> Element<'a> {
+    Element {
+        name,
+        attributes,
+        parent: None,
+        children: Vec::new(),
+        content: start_tag.end..start_tag.end,
+        range: start_tag.start..start_tag.end,
+        start_tag,
+        self_closing,
+    }
+}
+
+fn is_space(c: u8) -> bool {
+    c == b' ' || c == b'\t' || c == b'\n' || c == b'\r'
+}
+
+/// Returns an element text content.
+///
+/// Comments are skipped, CDATA is preserved, and entities are resolved.
+pub fn text_content(doc: &Document, elem: &Element) -> String {
+    let mut content = String::new();
+    let mut s = &doc.text[elem.content.clone()];
+    while !s.is_empty() {
+        if s.starts_with("<![CDATA[") {
+            let end = s.find("]]>").unwrap_or(s.len());
+            content.push_str(&s[9..end]);
+            s = &s[(end + 3).min(s.len())..];
+        } else if s.starts_with("<!--") {
+            let end = s.find("-->").map(|i| i + 3).unwrap_or(s.len());
+            s = &s[end..];
+        } else if s.starts_with('<') {
+            // Skip nested elements.
+            let end = s.find('>').map(|i| i + 1).unwrap_or(s.len());
+            s = &s[end..];
+        } else {
+            let end = s.find('<').unwrap_or(s.len());
+            content.push_str(&unescape(&s[..end]));
+            s = &s[end..];
+        }
+    }
+
+    content
+}
+
+/// Resolves predefined and numeric entities.
+///
+/// Other entity references are resolved by the SVG parser,
+/// so they are kept as `\0name\0` and restored by `escape`.
+pub fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut s = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('&') {
+        s.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+
+        let entity = &rest[1..end];
+        let c = match entity {
+            // Not allowed in XML.
+            "#0" | "#x0" => None,
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                if entity.starts_with("#x") {
+                    u32::from_str_radix(&entity[2..], 16).ok().and_then(::std::char::from_u32)
+                } else if entity.starts_with('#') {
+                    entity[1..].parse().ok().and_then(::std::char::from_u32)
+                } else {
+                    None
+                }
+            }
+        };
+
+        match c {
+            Some(c) => {
+                s.push(c);
+                rest = &rest[end + 1..];
+            }
+            None if is_name(entity) => {
+                s.push('\0');
+                s.push_str(entity);
+                s.push('\0');
+                rest = &rest[end + 1..];
+            }
+            None => {
+                s.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    s.push_str(rest);
+    s
+}
+
+/// Checks that a string is a valid XML name.
+///
+/// Namespace prefixes are not allowed.
+pub fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Escapes a string for an attribute value with double quotes.
+///
+/// Entity references kept by `unescape` are restored.
+pub fn escape(text: &str) -> String {
+    let mut s = String::with_capacity(text.len());
+    let mut is_entity = false;
+    for c in text.chars() {
+        match c {
+            '\0' => {
+                s.push(if is_entity { ';' } else { '&' });
+                is_entity = !is_entity;
+            }
+            '"' => s.push_str("&quot;"),
+            '&' => s.push_str("&amp;"),
+            '<' => s.push_str("&lt;"),
+            _ => s.push(c),
+        }
+    }
+
+    s
+}
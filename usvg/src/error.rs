@@ -26,8 +26,8 @@ pub enum Error {
     ///
     /// Occurs when width and/or height are <= 0.
     ///
-    /// Also occurs if width, height and viewBox are not set.
-    /// This is against the SVG spec, but an automatic size detection is not supported yet.
+    /// Also occurs if width, height and viewBox are not set
+    /// and `Options::detect_size` is disabled.
     InvalidSize,
 
     /// Failed to parse an SVG data.
@@ -39,7 +39,7 @@ and an XML library with some small amount of code.
 - No units (mm, em, etc.)
 - No comments
 - No DTD
-- No CSS (partial support)
+- No CSS
 - No `script` (simply ignoring it)
 
 Full spec can be found [here](https://github.com/RazrFalcon/usvg/blob/master/docs/usvg_spec.adoc).
@@ -47,13 +47,13 @@ Full spec can be found [here](https://github.com/RazrFalcon/usvg/blob/master/doc
 ## Limitations
 
 - Currently, it's not lossless. Some SVG features isn't supported yet and will be ignored.
-- CSS support is minimal.
+- CSS support is limited to CSS 2.1 selectors and a few pseudo-classes.
+  At-rules, like `@media`, are ignored.
+- ICC colors support only `lut8`, `lut16`, `lutAtoB` and matrix/TRC profiles.
 - Scripting and animation isn't supported and not planned.
-- `a` elements will be removed.
 - Unsupported elements:
   - some filter-based elements
   - font-based elements
-  - `marker`
 
 [SVG]: https://en.wikipedia.org/wiki/Scalable_Vector_Graphics
 */
@@ -125,6 +125,7 @@ macro_rules! debug_panic {
 
 pub mod utils;
 mod convert;
+mod css;
 mod error;
 mod geom;
 mod options;
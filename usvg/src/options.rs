@@ -4,15 +4,28 @@
 
 use std::path::PathBuf;
 
+use geom::Size;
+use tree::Color;
+
 
 /// Processing options.
 #[derive(Clone, Debug)]
 pub struct Options {
     /// SVG image path.
     ///
-    /// Used to resolve relative image paths.
+    /// Used to resolve relative image paths and links to external files.
     pub path: Option<PathBuf>,
 
+    /// Load external files.
+    ///
+    /// If set to `true`, `use` elements with a link to an external SVG file
+    /// and `color-profile` elements with a link to a local ICC profile will be resolved.
+    /// Only files inside the `path` directory, including subdirectories, can be loaded.
+    /// Nothing is loaded when `path` is not set.
+    ///
+    /// Disabled by default, since an untrusted SVG should not be able to read local files.
+    pub load_external_files: bool,
+
     /// Target DPI.
     ///
     /// Impact units conversion.
@@ -35,18 +48,79 @@ pub struct Options {
     /// If set to `true`, all non-empty groups with `id` attribute will not
     /// be removed.
     pub keep_named_groups: bool,
+
+    /// Keep markers.
+    ///
+    /// If set to `true`, markers will be converted into `Marker` definitions
+    /// and referenced by `Path::markers` instead of being converted into groups.
+    ///
+    /// Markers that use `context-fill` or `context-stroke` are always converted into groups.
+    pub keep_markers: bool,
+
+    /// Keep `use` references.
+    ///
+    /// If set to `true`, the content referenced by `use` elements will be converted
+    /// into a `Group` inside the `Defs` only once and referenced by `Use` nodes.
+    /// Otherwise, the content is copied for each `use`.
+    ///
+    /// The content is shared only by `use` elements with the same inherited style
+    /// and viewport. Nodes inside the shared content are not returned by `Tree::node_by_id`.
+    pub keep_use: bool,
+
+    /// Detect the image size automatically.
+    ///
+    /// If set to `true`, an SVG without a valid size, like the one
+    /// without `width`, `height` and `viewBox`, will use the bounding box
+    /// of its content as a size and viewBox instead of producing
+    /// an `Error::InvalidSize`.
+    ///
+    /// Note that text is not taken into account, since `usvg`
+    /// doesn't know the font metrics. And percentage lengths inside the content
+    /// are resolved against `default_size`, since the actual size is unknown
+    /// during the conversion.
+    ///
+    /// An SVG with a `viewBox` always has a valid size.
+    pub detect_size: bool,
+
+    /// A fallback image size.
+    ///
+    /// Used when `detect_size` is set and the content bounding box is invalid.
+    pub default_size: Size,
+
+    /// A user style sheet.
+    ///
+    /// Will be applied to every document after its own style sheets,
+    /// with the same precedence. So rules with the same specificity
+    /// will override the document ones.
+    pub style_sheet: Option<String>,
+
+    /// A root `color` value.
+    ///
+    /// Overrides the `color` attribute of the root `svg` element,
+    /// so all `currentColor` values that are not redefined by
+    /// a nested `color` will be resolved to it.
+    ///
+    /// Useful for recoloring monochrome icons.
+    pub color: Option<Color>,
 }
 
 impl Default for Options {
     fn default() -> Options {
         Options {
             path: None,
+            load_external_files: false,
             dpi: 96.0,
             // Default font is user-agent dependent so we can use whatever we like.
             font_family: "Times New Roman".to_owned(),
             font_size: 12.0,
             languages: vec!["en".to_string()],
             keep_named_groups: false,
+            keep_markers: false,
+            keep_use: false,
+            detect_size: false,
+            default_size: Size::new(100.0, 150.0),
+            style_sheet: None,
+            color: None,
         }
     }
 }
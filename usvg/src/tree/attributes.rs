@@ -101,6 +101,31 @@ impl ToString for FillRule {
 }
 
 
+/// A paint order.
+///
+/// `paint-order` attribute in the SVG.
+///
+/// Markers are not included, because they are stored as separate nodes,
+/// which are already placed in the required order.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaintOrder {
+    FillAndStroke,
+    StrokeAndFill,
+}
+
+enum_default!(PaintOrder, FillAndStroke);
+
+impl ToString for PaintOrder {
+    fn to_string(&self) -> String {
+        match self {
+            PaintOrder::FillAndStroke => "normal",
+            PaintOrder::StrokeAndFill => "stroke",
+        }.to_string()
+    }
+}
+
+
 /// An element units.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -119,6 +144,52 @@ impl ToString for Units {
 }
 
 
+/// Marker units.
+///
+/// `markerUnits` attribute in the SVG.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MarkerUnits {
+    StrokeWidth,
+    UserSpaceOnUse,
+}
+
+enum_default!(MarkerUnits, StrokeWidth);
+
+impl ToString for MarkerUnits {
+    fn to_string(&self) -> String {
+        match self {
+            MarkerUnits::StrokeWidth    => "strokeWidth",
+            MarkerUnits::UserSpaceOnUse => "userSpaceOnUse",
+        }.to_string()
+    }
+}
+
+
+/// A marker orientation.
+///
+/// `orient` attribute in the SVG.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MarkerOrientation {
+    /// Along the path direction.
+    Auto,
+    /// Like `Auto`, but the start marker is rotated by 180 degrees.
+    AutoStartReverse,
+    /// A fixed angle in degrees.
+    Angle(f64),
+}
+
+
+/// A marker position on a path.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MarkerKind {
+    Start,
+    Middle,
+    End,
+}
+
+
 /// A spread method.
 ///
 /// `spreadMethod` attribute in the SVG.
@@ -374,6 +445,8 @@ pub struct Fill {
     pub paint: Paint,
     pub opacity: Opacity,
     pub rule: FillRule,
+    /// Indicates that `paint` was resolved from `currentColor`.
+    pub from_current_color: bool,
 }
 
 impl Default for Fill {
@@ -382,6 +455,7 @@ impl Default for Fill {
             paint: Paint::Color(Color::black()),
             opacity: Opacity::default(),
             rule: FillRule::default(),
+            from_current_color: false,
         }
     }
 }
@@ -399,6 +473,12 @@ pub struct Stroke {
     pub width: StrokeWidth,
     pub linecap: LineCap,
     pub linejoin: LineJoin,
+    /// Indicates that `paint` was resolved from `currentColor`.
+    pub from_current_color: bool,
+    /// Indicates that the stroke width is in the screen units.
+    ///
+    /// `vector-effect="non-scaling-stroke"` in SVG.
+    pub non_scaling: bool,
 }
 
 impl Default for Stroke {
@@ -414,6 +494,8 @@ impl Default for Stroke {
             width: StrokeWidth::default(),
             linecap: LineCap::default(),
             linejoin: LineJoin::default(),
+            from_current_color: false,
+            non_scaling: false,
         }
     }
 }
@@ -512,6 +594,56 @@ impl ToString for FilterInput {
 }
 
 
+/// A blending mode.
+///
+/// `mix-blend-mode` attribute in the SVG.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+enum_default!(BlendMode, Normal);
+
+impl ToString for BlendMode {
+    fn to_string(&self) -> String {
+        match self {
+            BlendMode::Normal       => "normal",
+            BlendMode::Multiply     => "multiply",
+            BlendMode::Screen       => "screen",
+            BlendMode::Overlay      => "overlay",
+            BlendMode::Darken       => "darken",
+            BlendMode::Lighten      => "lighten",
+            BlendMode::ColorDodge   => "color-dodge",
+            BlendMode::ColorBurn    => "color-burn",
+            BlendMode::HardLight    => "hard-light",
+            BlendMode::SoftLight    => "soft-light",
+            BlendMode::Difference   => "difference",
+            BlendMode::Exclusion    => "exclusion",
+            BlendMode::Hue          => "hue",
+            BlendMode::Saturation   => "saturation",
+            BlendMode::Color        => "color",
+            BlendMode::Luminosity   => "luminosity",
+        }.to_string()
+    }
+}
+
+
 /// A color interpolation mode.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -530,6 +662,28 @@ impl ToString for ColorInterpolation {
 }
 
 
+/// A mask type.
+///
+/// `mask-type` attribute in SVG 2.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MaskType {
+    Luminance,
+    Alpha,
+}
+
+enum_default!(MaskType, Luminance);
+
+impl ToString for MaskType {
+    fn to_string(&self) -> String {
+        match self {
+            MaskType::Luminance => "luminance",
+            MaskType::Alpha     => "alpha",
+        }.to_string()
+    }
+}
+
+
 /// A raster image container.
 #[derive(Clone, Debug)]
 pub enum ImageData {
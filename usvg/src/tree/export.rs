@@ -78,8 +78,26 @@ fn conv_defs(
                 grad_elem.set_attribute((AId::Fx, rg.fx));
                 grad_elem.set_attribute((AId::Fy, rg.fy));
 
+                if !rg.fr.is_zero() {
+                    grad_elem.set_attribute(("fr", rg.fr.value()));
+                }
+
                 conv_base_grad(&rg.base, new_doc, &mut grad_elem);
             }
+            NodeKind::MeshGradient(ref mg) => {
+                let mut grad_elem = new_doc.create_element("meshgradient");
+                defs.append(grad_elem.clone());
+
+                grad_elem.set_id(mg.id.clone());
+
+                let start = mg.rows[0][0].points[0];
+                grad_elem.set_attribute((AId::X, start.x));
+                grad_elem.set_attribute((AId::Y, start.y));
+
+                conv_units(AId::GradientUnits, mg.units, Units::ObjectBoundingBox, &mut grad_elem);
+                conv_transform(AId::GradientTransform, &mg.transform, &mut grad_elem);
+                conv_mesh_rows(&mg.rows, new_doc, &mut grad_elem);
+            }
             NodeKind::ClipPath(ref clip) => {
                 let mut clip_elem = new_doc.create_element(EId::ClipPath);
                 defs.append(clip_elem.clone());
@@ -105,6 +123,10 @@ fn conv_defs(
                 conv_units(AId::MaskContentUnits, mask.content_units, Units::UserSpaceOnUse, &mut mask_elem);
                 conv_rect(mask.rect, &mut mask_elem);
 
+                if mask.kind == MaskType::Alpha {
+                    mask_elem.set_attribute(("mask-type", mask.kind.to_string()));
+                }
+
                 if let Some(ref id) = mask.mask {
                     link_later.push((id.clone(), AId::Mask, mask_elem.clone()));
                 }
@@ -129,6 +151,56 @@ fn conv_defs(
                 conv_transform(AId::PatternTransform, &pattern.transform, &mut pattern_elem);
                 later_nodes.push((n.clone(), pattern_elem.clone()));
             }
+            NodeKind::Group(ref g) => {
+                // Shared `use` content.
+                let mut g_elem = new_doc.create_element(EId::G);
+                defs.append(g_elem.clone());
+
+                g_elem.set_id(g.id.clone());
+
+                later_nodes.push((n.clone(), g_elem.clone()));
+            }
+            NodeKind::Marker(ref marker) => {
+                let mut marker_elem = new_doc.create_element(EId::Marker);
+                defs.append(marker_elem.clone());
+
+                marker_elem.set_id(marker.id.clone());
+
+                marker_elem.set_attribute((AId::RefX, marker.rect.x));
+                marker_elem.set_attribute((AId::RefY, marker.rect.y));
+                marker_elem.set_attribute((AId::MarkerWidth, marker.rect.width));
+                marker_elem.set_attribute((AId::MarkerHeight, marker.rect.height));
+
+                if !marker.units.is_default() {
+                    marker_elem.set_attribute((AId::MarkerUnits, marker.units.to_string()));
+                }
+
+                match marker.orientation {
+                    MarkerOrientation::Auto => {
+                        marker_elem.set_attribute((AId::Orient, "auto"));
+                    }
+                    MarkerOrientation::AutoStartReverse => {
+                        marker_elem.set_attribute(("orient", "auto-start-reverse"));
+                    }
+                    MarkerOrientation::Angle(angle) => {
+                        if !angle.is_fuzzy_zero() {
+                            marker_elem.set_attribute((AId::Orient, angle));
+                        }
+                    }
+                }
+
+                if let Some(vbox) = marker.view_box {
+                    conv_viewbox(&vbox, &mut marker_elem);
+                }
+
+                // A clip path is generated from the `overflow` property,
+                // so it will be restored on the next parsing.
+                if marker.clip_path.is_none() {
+                    marker_elem.set_attribute((AId::Overflow, "visible"));
+                }
+
+                later_nodes.push((n.clone(), marker_elem.clone()));
+            }
             NodeKind::Filter(ref filter) => {
                 let mut filter_elem = new_doc.create_element(EId::Filter);
                 defs.append(filter_elem.clone());
@@ -278,6 +350,7 @@ fn conv_elements(
                 conv_transform(AId::Transform, &p.transform, &mut path_elem);
                 path_elem.set_enum_attribute(AId::Visibility, p.visibility);
                 path_elem.set_id(p.id.clone());
+                conv_paint_order(p.paint_order, &mut path_elem);
 
                 use svgdom::Path as SvgDomPath;
                 use svgdom::PathSegment as SvgDomPathSegment;
@@ -304,6 +377,7 @@ fn conv_elements(
 
                 conv_fill(tree, &p.fill, defs, parent, &mut path_elem);
                 conv_stroke(tree, &p.stroke, defs, &mut path_elem);
+                conv_markers(tree, &p.markers, &p.stroke, defs, &mut path_elem);
             }
             NodeKind::Text(ref text) => {
                 let mut text_elem = new_doc.create_element(EId::Text);
@@ -311,6 +385,7 @@ fn conv_elements(
 
                 conv_transform(AId::Transform, &text.transform, &mut text_elem);
                 text_elem.set_id(text.id.clone());
+                conv_paint_order(text.paint_order, &mut text_elem);
 
 
                 if let Some(ref rotate) = text.rotate {
@@ -386,12 +461,21 @@ fn conv_elements(
 
                 img_elem.set_attribute((AId::Href, conv_image_data(&img.data, img.format)));
             }
+            NodeKind::Use(ref u) => {
+                let mut use_elem = new_doc.create_element(EId::Use);
+                parent.append(use_elem.clone());
+
+                conv_transform(AId::Transform, &u.transform, &mut use_elem);
+                use_elem.set_id(u.id.clone());
+                conv_link(tree, defs, AId::Href, &u.link, &mut use_elem);
+            }
             NodeKind::Group(ref g) => {
                 let mut g_elem = if parent.is_tag_name(EId::ClipPath) {
                     conv_elements(tree, &n, defs, new_doc, parent);
                     parent.last_child().unwrap()
                 } else {
-                    let mut g_elem = new_doc.create_element(EId::G);
+                    let eid = if g.link.is_some() { EId::A } else { EId::G };
+                    let mut g_elem = new_doc.create_element(eid);
                     parent.append(g_elem.clone());
                     g_elem
                 };
@@ -399,6 +483,14 @@ fn conv_elements(
                 conv_transform(AId::Transform, &g.transform, &mut g_elem);
                 g_elem.set_id(g.id.clone());
 
+                if let Some(ref link) = g.link {
+                    g_elem.set_attribute((AId::Href, link.href.clone()));
+
+                    if let Some(ref target) = link.target {
+                        g_elem.set_attribute((AId::Target, target.clone()));
+                    }
+                }
+
                 conv_opt_link(tree, defs, AId::ClipPath, &g.clip_path, &mut g_elem);
                 conv_opt_link(tree, defs, AId::Mask, &g.mask, &mut g_elem);
                 conv_opt_link(tree, defs, AId::Filter, &g.filter, &mut g_elem);
@@ -407,6 +499,14 @@ fn conv_elements(
                     g_elem.set_attribute((AId::Opacity, g.opacity.value()));
                 }
 
+                if g.blend_mode != BlendMode::Normal {
+                    g_elem.set_attribute(("mix-blend-mode", g.blend_mode.to_string()));
+                }
+
+                if g.isolate {
+                    g_elem.set_attribute(("isolation", "isolate"));
+                }
+
                 if !g_elem.has_id() && g_elem.attributes().len() == 0 {
                     warn!("Group must have at least one attribute otherwise it's pointless.");
                 }
@@ -505,6 +605,42 @@ fn conv_fill(
     }
 }
 
+fn conv_markers(
+    tree: &Tree,
+    markers: &[MarkerInstance],
+    stroke: &Option<Stroke>,
+    defs: &svgdom::Node,
+    node: &mut svgdom::Node,
+) {
+    // Markers can be set on a path without a stroke, but `markerUnits=strokeWidth`
+    // still depends on its width.
+    if stroke.is_none() {
+        if let Some(instance) = markers.first() {
+            if !instance.stroke_width.fuzzy_eq(&1.0) {
+                node.set_attribute((AId::StrokeWidth, instance.stroke_width));
+            }
+        }
+    }
+
+    let kinds = [
+        (MarkerKind::Start, AId::MarkerStart),
+        (MarkerKind::Middle, AId::MarkerMid),
+        (MarkerKind::End, AId::MarkerEnd),
+    ];
+
+    for &(kind, aid) in &kinds {
+        if let Some(instance) = markers.iter().find(|m| m.kind == kind) {
+            conv_link(tree, defs, aid, &instance.id, node);
+        }
+    }
+}
+
+fn conv_paint_order(order: PaintOrder, node: &mut svgdom::Node) {
+    if !order.is_default() {
+        node.set_attribute(("paint-order", order.to_string()));
+    }
+}
+
 fn conv_stroke(
     tree: &Tree,
     stroke: &Option<Stroke>,
@@ -539,6 +675,10 @@ fn conv_stroke(
         if let Some(ref array) = stroke.dasharray {
             node.set_attribute((AId::StrokeDasharray, NumberList(array.clone())));
         }
+
+        if stroke.non_scaling {
+            node.set_attribute(("vector-effect", "non-scaling-stroke"));
+        }
     }
 }
 
@@ -553,6 +693,10 @@ fn conv_base_grad(
 
     node.set_enum_attribute(AId::SpreadMethod, g.spread_method);
 
+    if g.color_interpolation == ColorInterpolation::LinearRGB {
+        node.set_attribute((AId::ColorInterpolation, g.color_interpolation.to_string()));
+    }
+
     conv_transform(AId::GradientTransform, &g.transform, node);
 
     for s in &g.stops {
@@ -568,6 +712,46 @@ fn conv_base_grad(
     }
 }
 
+fn conv_mesh_rows(
+    rows: &[Vec<MeshPatch>],
+    doc: &mut svgdom::Document,
+    node: &mut svgdom::Node,
+) {
+    for (y, row) in rows.iter().enumerate() {
+        let mut row_elem = doc.create_element("meshrow");
+        node.append(row_elem.clone());
+
+        for (x, patch) in row.iter().enumerate() {
+            let mut patch_elem = doc.create_element("meshpatch");
+            row_elem.append(patch_elem.clone());
+
+            // Edges shared with the previous patches must be omitted.
+            for edge in 0..4 {
+                if (edge == 0 && y != 0) || (edge == 3 && x != 0) {
+                    continue;
+                }
+
+                let p = &patch.points;
+                let i = edge * 3;
+                let path = format!("C {} {} {} {} {} {}",
+                                   p[i + 1].x, p[i + 1].y,
+                                   p[i + 2].x, p[i + 2].y,
+                                   p[(i + 3) % 12].x, p[(i + 3) % 12].y);
+
+                let mut stop = doc.create_element(EId::Stop);
+                patch_elem.append(stop.clone());
+
+                stop.set_attribute((AId::Path, path));
+                stop.set_attribute((AId::StopColor, patch.colors[edge]));
+
+                if !patch.opacities[edge].is_default() {
+                    stop.set_attribute((AId::StopOpacity, patch.opacities[edge].value()));
+                }
+            }
+        }
+    }
+}
+
 fn conv_transform(
     aid: AId,
     ts: &svgdom::Transform,
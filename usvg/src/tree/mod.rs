@@ -17,6 +17,8 @@ use libflate;
 pub use self::nodes::*;
 pub use self::attributes::*;
 use {
+    css,
+    utils,
     Error,
     Options,
 };
@@ -61,23 +63,18 @@ impl Tree {
 
     /// Parses `Tree` from the SVG string.
     pub fn from_str(text: &str, opt: &Options) -> Result<Self, Error> {
-        let dom_opt = svgdom::ParseOptions {
-            skip_invalid_attributes: true,
-            skip_invalid_css: true,
-            skip_unresolved_classes: true,
-        };
-
-        let doc = svgdom::Document::from_str_with_opt(text, &dom_opt)
-            .map_err(|e| Error::ParsingFailed(e))?;
-
+        let doc = parse_svgdom(text, None, opt)?;
         Self::from_dom(doc, &opt)
     }
 
     /// Parses `Tree` from the `svgdom::Document`.
     ///
+    /// Unlike `from_str`, style sheets, including `Options::style_sheet`,
+    /// and CSS colors are not resolved, since they are resolved on the SVG text.
+    ///
     /// An empty `Tree` will be returned on any error.
     pub fn from_dom(mut doc: svgdom::Document, opt: &Options) -> Result<Self, Error> {
-        super::convert::prepare_doc(&mut doc);
+        super::convert::prepare_doc(&mut doc, opt);
         super::convert::convert_doc(&doc, opt)
     }
 
@@ -174,6 +171,170 @@ impl Tree {
     pub fn to_svgdom(&self) -> svgdom::Document {
         export::convert(self)
     }
+
+    /// Replaces all colors in the tree.
+    ///
+    /// Affects fill and stroke colors, including text spans and text decorations,
+    /// gradient stops and `feFlood` colors.
+    ///
+    /// Clip paths are skipped, since only their shape is used.
+    pub fn map_colors<F>(&mut self, mut f: F)
+        where F: FnMut(Color) -> Color
+    {
+        visit_colors(&self.root, &mut |c: &mut Color| *c = f(*c));
+    }
+
+    /// Returns a list of all distinct colors used in the tree.
+    ///
+    /// Colors are listed in the order of appearance.
+    /// The same elements as in `map_colors` are checked.
+    pub fn colors(&self) -> Vec<Color> {
+        let mut list = Vec::new();
+        for node in colored_nodes(&self.root) {
+            node_colors(&*node.borrow(), &mut |c: Color| {
+                if !list.contains(&c) {
+                    list.push(c);
+                }
+            });
+        }
+
+        list
+    }
+}
+
+/// Returns all nodes outside of clip paths.
+fn colored_nodes(root: &Node) -> Vec<Node> {
+    let is_clip_path = |node: Node| {
+        if let NodeKind::ClipPath(_) = *node.borrow() { true } else { false }
+    };
+
+    root.descendants().filter(|node| !node.ancestors().any(&is_clip_path)).collect()
+}
+
+fn node_colors(kind: &NodeKind, f: &mut FnMut(Color)) {
+    fn fill_color(fill: &Option<Fill>, f: &mut FnMut(Color)) {
+        if let Some(Fill { paint: Paint::Color(c), .. }) = *fill {
+            f(c);
+        }
+    }
+
+    fn stroke_color(stroke: &Option<Stroke>, f: &mut FnMut(Color)) {
+        if let Some(Stroke { paint: Paint::Color(c), .. }) = *stroke {
+            f(c);
+        }
+    }
+
+    match *kind {
+        NodeKind::Path(ref path) => {
+            fill_color(&path.fill, f);
+            stroke_color(&path.stroke, f);
+        }
+        NodeKind::Text(ref text) => {
+            for span in text.chunks.iter().flat_map(|chunk| chunk.spans.iter()) {
+                fill_color(&span.fill, f);
+                stroke_color(&span.stroke, f);
+
+                let decoration = &span.decoration;
+                for style in &[&decoration.underline, &decoration.overline, &decoration.line_through] {
+                    if let Some(ref style) = **style {
+                        fill_color(&style.fill, f);
+                        stroke_color(&style.stroke, f);
+                    }
+                }
+            }
+        }
+        NodeKind::LinearGradient(ref lg) => {
+            for stop in &lg.base.stops {
+                f(stop.color);
+            }
+        }
+        NodeKind::RadialGradient(ref rg) => {
+            for stop in &rg.base.stops {
+                f(stop.color);
+            }
+        }
+        NodeKind::MeshGradient(ref mg) => {
+            for patch in mg.rows.iter().flat_map(|row| row.iter()) {
+                for color in &patch.colors {
+                    f(*color);
+                }
+            }
+        }
+        NodeKind::Filter(ref filter) => {
+            for primitive in &filter.children {
+                if let FilterKind::FeFlood(ref fe) = primitive.kind {
+                    f(fe.color);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn visit_colors(root: &Node, f: &mut FnMut(&mut Color)) {
+    fn visit_fill(fill: &mut Option<Fill>, f: &mut FnMut(&mut Color)) {
+        if let Some(Fill { paint: Paint::Color(ref mut c), .. }) = *fill {
+            f(c);
+        }
+    }
+
+    fn visit_stroke(stroke: &mut Option<Stroke>, f: &mut FnMut(&mut Color)) {
+        if let Some(Stroke { paint: Paint::Color(ref mut c), .. }) = *stroke {
+            f(c);
+        }
+    }
+
+    fn visit_decoration(style: &mut Option<TextDecorationStyle>, f: &mut FnMut(&mut Color)) {
+        if let Some(ref mut style) = *style {
+            visit_fill(&mut style.fill, f);
+            visit_stroke(&mut style.stroke, f);
+        }
+    }
+
+    for node in colored_nodes(root) {
+        match *node.borrow_mut() {
+            NodeKind::Path(ref mut path) => {
+                visit_fill(&mut path.fill, f);
+                visit_stroke(&mut path.stroke, f);
+            }
+            NodeKind::Text(ref mut text) => {
+                for chunk in &mut text.chunks {
+                    for span in &mut chunk.spans {
+                        visit_fill(&mut span.fill, f);
+                        visit_stroke(&mut span.stroke, f);
+                        visit_decoration(&mut span.decoration.underline, f);
+                        visit_decoration(&mut span.decoration.overline, f);
+                        visit_decoration(&mut span.decoration.line_through, f);
+                    }
+                }
+            }
+            NodeKind::LinearGradient(ref mut lg) => {
+                for stop in &mut lg.base.stops {
+                    f(&mut stop.color);
+                }
+            }
+            NodeKind::RadialGradient(ref mut rg) => {
+                for stop in &mut rg.base.stops {
+                    f(&mut stop.color);
+                }
+            }
+            NodeKind::MeshGradient(ref mut mg) => {
+                for patch in mg.rows.iter_mut().flat_map(|row| row.iter_mut()) {
+                    for color in &mut patch.colors {
+                        f(color);
+                    }
+                }
+            }
+            NodeKind::Filter(ref mut filter) => {
+                for primitive in &mut filter.children {
+                    if let FilterKind::FeFlood(ref mut fe) = primitive.kind {
+                        f(&mut fe.color);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
 }
 
 /// Additional `Node` methods.
@@ -220,6 +381,43 @@ impl NodeExt for Node {
 }
 
 
+/// Parses `svgdom::Document` from the SVG string.
+///
+/// Style sheets, including `Options::style_sheet`, and CSS colors
+/// are resolved before the parsing.
+///
+/// `path` is the parsed file path. `Options::path` is used when not set.
+pub(crate) fn parse_svgdom(
+    text: &str,
+    path: Option<&path::Path>,
+    opt: &Options,
+) -> Result<svgdom::Document, Error> {
+    let user_sheet = opt.style_sheet.as_ref().map(|s| s.as_str());
+
+    // Local files can be loaded only from the main document directory.
+    let root_dir = match opt.path {
+        Some(ref path) if opt.load_external_files => utils::file_dir(path),
+        _ => None,
+    };
+    let dir = path.or(opt.path.as_ref().map(|p| p.as_path())).and_then(utils::file_dir);
+    let dirs = match (dir.as_ref(), root_dir.as_ref()) {
+        (Some(dir), Some(root_dir)) => Some((dir.as_path(), root_dir.as_path())),
+        _ => None,
+    };
+
+    let resolved_text = css::resolve_css(text, user_sheet, dirs);
+    let text = resolved_text.as_ref().map(|s| s.as_str()).unwrap_or(text);
+
+    let dom_opt = svgdom::ParseOptions {
+        skip_invalid_attributes: true,
+        skip_invalid_css: true,
+        skip_unresolved_classes: true,
+    };
+
+    svgdom::Document::from_str_with_opt(text, &dom_opt)
+        .map_err(|e| Error::ParsingFailed(e))
+}
+
 /// Loads SVG, SVGZ file content.
 pub fn load_svg_file(path: &path::Path) -> Result<String, Error> {
     use std::fs;
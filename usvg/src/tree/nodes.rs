@@ -19,14 +19,17 @@ pub enum NodeKind {
     Defs,
     LinearGradient(LinearGradient),
     RadialGradient(RadialGradient),
+    MeshGradient(MeshGradient),
     ClipPath(ClipPath),
     Mask(Mask),
     Pattern(Pattern),
+    Marker(Marker),
     Filter(Filter),
     Path(Path),
     Text(Text),
     Image(Image),
     Group(Group),
+    Use(Use),
 }
 
 impl NodeKind {
@@ -40,14 +43,17 @@ impl NodeKind {
             NodeKind::Defs => "",
             NodeKind::LinearGradient(ref e) => e.id.as_str(),
             NodeKind::RadialGradient(ref e) => e.id.as_str(),
+            NodeKind::MeshGradient(ref e) => e.id.as_str(),
             NodeKind::ClipPath(ref e) => e.id.as_str(),
             NodeKind::Mask(ref e) => e.id.as_str(),
             NodeKind::Pattern(ref e) => e.id.as_str(),
+            NodeKind::Marker(ref e) => e.id.as_str(),
             NodeKind::Filter(ref e) => e.id.as_str(),
             NodeKind::Path(ref e) => e.id.as_str(),
             NodeKind::Text(ref e) => e.id.as_str(),
             NodeKind::Image(ref e) => e.id.as_str(),
             NodeKind::Group(ref e) => e.id.as_str(),
+            NodeKind::Use(ref e) => e.id.as_str(),
         }
     }
 
@@ -61,14 +67,17 @@ impl NodeKind {
             NodeKind::Defs => Transform::default(),
             NodeKind::LinearGradient(ref e) => e.transform,
             NodeKind::RadialGradient(ref e) => e.transform,
+            NodeKind::MeshGradient(ref e) => e.transform,
             NodeKind::ClipPath(ref e) => e.transform,
             NodeKind::Mask(_) => Transform::default(),
             NodeKind::Pattern(ref e) => e.transform,
+            NodeKind::Marker(_) => Transform::default(),
             NodeKind::Filter(_) => Transform::default(),
             NodeKind::Path(ref e) => e.transform,
             NodeKind::Text(ref e) => e.transform,
             NodeKind::Image(ref e) => e.transform,
             NodeKind::Group(ref e) => e.transform,
+            NodeKind::Use(ref e) => e.transform,
         }
     }
 }
@@ -115,10 +124,21 @@ pub struct Path {
     /// Stroke style.
     pub stroke: Option<Stroke>,
 
+    /// Fill and stroke paint order.
+    pub paint_order: PaintOrder,
+
     /// Segments list.
     ///
     /// All segments are in absolute coordinates.
     pub segments: Vec<PathSegment>,
+
+    /// Marker instances.
+    ///
+    /// Rendered after the fill and the stroke.
+    ///
+    /// Set only when `Options::keep_markers` is enabled.
+    /// Otherwise, markers are converted into groups.
+    pub markers: Vec<MarkerInstance>,
 }
 
 impl Default for Path {
@@ -129,12 +149,48 @@ impl Default for Path {
             visibility: Visibility::Visible,
             fill: None,
             stroke: None,
+            paint_order: PaintOrder::default(),
             segments: Vec::new(),
+            markers: Vec::new(),
         }
     }
 }
 
 
+/// A marker instance.
+///
+/// References a `Marker` in defs.
+#[derive(Clone, Debug)]
+pub struct MarkerInstance {
+    /// `Marker` ID.
+    pub id: String,
+
+    /// Marker position on the path.
+    pub kind: MarkerKind,
+
+    /// Vertex X coordinate.
+    pub x: f64,
+
+    /// Vertex Y coordinate.
+    pub y: f64,
+
+    /// Resolved marker angle in degrees.
+    pub angle: f64,
+
+    /// Stroke width of the marked path.
+    ///
+    /// Used by `markerUnits=strokeWidth` even when the path has no stroke.
+    pub stroke_width: f64,
+
+    /// Marker content transform.
+    ///
+    /// Includes the vertex position, the angle, the stroke width scale
+    /// and the viewbox transform.
+    /// Relative to the path coordinate system.
+    pub transform: Transform,
+}
+
+
 /// A text element.
 ///
 /// `text` element in SVG.
@@ -155,6 +211,9 @@ pub struct Text {
     /// If set, contains a list of rotation angles for each *code point* in the `text`.
     pub rotate: Option<Vec<f64>>,
 
+    /// Fill and stroke paint order.
+    pub paint_order: PaintOrder,
+
     /// A list of text chunks.
     pub chunks: Vec<TextChunk>,
 }
@@ -302,6 +361,28 @@ pub struct Group {
 
     /// Element filter.
     pub filter: Option<String>,
+
+    /// Group blend mode.
+    ///
+    /// The group should be combined with a parent group
+    /// using the specified mode.
+    ///
+    /// `mix-blend-mode` in SVG.
+    pub blend_mode: BlendMode,
+
+    /// Isolates the group from the parent one.
+    ///
+    /// Since every group is rendered on a separate canvas,
+    /// this flag doesn't require any additional processing
+    /// and only indicates that the group must be preserved.
+    ///
+    /// `isolation` in SVG.
+    pub isolate: bool,
+
+    /// A hyperlink.
+    ///
+    /// Set when the group was an `a` element.
+    pub link: Option<Hyperlink>,
 }
 
 impl Default for Group {
@@ -313,11 +394,57 @@ impl Default for Group {
             clip_path: None,
             mask: None,
             filter: None,
+            blend_mode: BlendMode::default(),
+            isolate: false,
+            link: None,
         }
     }
 }
 
 
+/// A hyperlink.
+///
+/// `a` element attributes in SVG.
+#[derive(Clone, Debug)]
+pub struct Hyperlink {
+    /// Link target URL.
+    ///
+    /// Not resolved and can be relative.
+    ///
+    /// `xlink:href` in SVG.
+    pub href: String,
+
+    /// Link browsing context, like `_blank`.
+    ///
+    /// `target` in SVG.
+    pub target: Option<String>,
+}
+
+
+/// A reference to a shared content.
+///
+/// `use` element in SVG.
+///
+/// Created only when `Options::keep_use` is enabled.
+/// Otherwise, the referenced content is copied into the tree.
+#[derive(Clone, Debug)]
+pub struct Use {
+    /// Element's ID.
+    ///
+    /// Taken from the SVG itself.
+    /// Can be empty.
+    pub id: String,
+
+    /// Element transform.
+    pub transform: Transform,
+
+    /// A `Group` in defs with the referenced content.
+    ///
+    /// Its children should be rendered as if they were the children of this node.
+    pub link: String,
+}
+
+
 /// A generic gradient.
 #[derive(Clone, Debug)]
 pub struct BaseGradient {
@@ -336,6 +463,11 @@ pub struct BaseGradient {
     /// `spreadMethod` in SVG.
     pub spread_method: SpreadMethod,
 
+    /// Color space in which stops are interpolated.
+    ///
+    /// `color-interpolation` in SVG.
+    pub color_interpolation: ColorInterpolation,
+
     /// A list of `stop` elements.
     pub stops: Vec<Stop>,
 }
@@ -388,6 +520,10 @@ pub struct RadialGradient {
     pub r: PositiveNumber,
     pub fx: f64,
     pub fy: f64,
+    /// Focal radius.
+    ///
+    /// `fr` in SVG 2.
+    pub fr: PositiveNumber,
 
     /// Base gradient data.
     pub base: BaseGradient,
@@ -402,6 +538,60 @@ impl Deref for RadialGradient {
 }
 
 
+/// A mesh gradient.
+///
+/// `meshgradient` element in SVG 2.
+#[derive(Clone, Debug)]
+pub struct MeshGradient {
+    /// Element's ID.
+    ///
+    /// Taken from the SVG itself.
+    /// Can't be empty.
+    pub id: String,
+
+    /// Coordinate system units.
+    ///
+    /// `gradientUnits` in SVG.
+    pub units: Units,
+
+    /// Gradient transform.
+    ///
+    /// `gradientTransform` in SVG.
+    pub transform: Transform,
+
+    /// A list of patch rows.
+    ///
+    /// `meshrow` elements in SVG.
+    /// Always non-empty and all rows have the same number of patches.
+    pub rows: Vec<Vec<MeshPatch>>,
+}
+
+
+/// A mesh gradient patch.
+///
+/// A Coons patch bounded by four cubic Bézier curves.
+/// Colors are bilinearly interpolated between corners.
+#[derive(Clone, Copy, Debug)]
+pub struct MeshPatch {
+    /// Patch control points.
+    ///
+    /// Starts at the top-left corner and goes clockwise. Each edge is represented
+    /// by its start corner and two control points, so corners are
+    /// at indexes 0, 3, 6 and 9.
+    pub points: [Point; 12],
+
+    /// Corner colors.
+    ///
+    /// In the top-left, top-right, bottom-right and bottom-left order.
+    pub colors: [Color; 4],
+
+    /// Corner opacities.
+    ///
+    /// In the same order as `colors`.
+    pub opacities: [Opacity; 4],
+}
+
+
 /// Gradient's stop element.
 ///
 /// `stop` element in SVG.
@@ -484,6 +674,11 @@ pub struct Mask {
     /// `maskContentUnits` in SVG.
     pub content_units: Units,
 
+    /// Mask type.
+    ///
+    /// `mask-type` in SVG 2 or `mask-mode` on the masked element.
+    pub kind: MaskType,
+
     /// Mask rectangle.
     ///
     /// `x`, `y`, `width` and `height` in SVG.
@@ -496,6 +691,46 @@ pub struct Mask {
 }
 
 
+/// A marker element.
+///
+/// `marker` element in SVG.
+///
+/// Created only when `Options::keep_markers` is enabled.
+/// Its children are rendered at each `MarkerInstance`.
+#[derive(Clone, Debug)]
+pub struct Marker {
+    /// Element's ID.
+    ///
+    /// Taken from the SVG itself.
+    /// Can't be empty.
+    pub id: String,
+
+    /// Coordinate system units.
+    ///
+    /// `markerUnits` in SVG.
+    pub units: MarkerUnits,
+
+    /// Marker rectangle.
+    ///
+    /// `refX`, `refY`, `markerWidth` and `markerHeight` in SVG.
+    pub rect: Rect,
+
+    /// Marker viewbox.
+    pub view_box: Option<ViewBox>,
+
+    /// Marker orientation.
+    ///
+    /// `orient` in SVG.
+    pub orientation: MarkerOrientation,
+
+    /// Content clip path.
+    ///
+    /// Set when the content is clipped by the `overflow` property.
+    /// In the marker content coordinates.
+    pub clip_path: Option<String>,
+}
+
+
 /// A pattern element.
 ///
 /// `pattern` element in SVG.
@@ -749,10 +984,1422 @@ pub struct FeTile {
 
 #[cfg(test)]
 mod tests {
+    use std::f64;
+
     use super::*;
+    use tree::{Node, Tree};
+    use utils::TempDir;
+    use Options;
+
+    fn parse(text: &str) -> Tree {
+        Tree::from_str(text, &Options::default()).unwrap()
+    }
+
+    fn parse_file(path: &::std::path::Path, load_external_files: bool) -> Tree {
+        let opt = Options {
+            path: Some(path.to_path_buf()),
+            load_external_files,
+            .. Options::default()
+        };
+
+        Tree::from_file(path, &opt).unwrap()
+    }
+
+    fn paths_count(tree: &Tree) -> usize {
+        tree.root().descendants()
+            .filter(|n| !tree.is_in_defs(n))
+            .filter(|n| if let NodeKind::Path(_) = *n.borrow() { true } else { false })
+            .count()
+    }
+
+    fn svg(content: &str) -> Vec<u8> {
+        format!("<svg xmlns='http://www.w3.org/2000/svg' \
+                      xmlns:xlink='http://www.w3.org/1999/xlink' \
+                      width='10' height='10'>{}</svg>", content).into_bytes()
+    }
 
     #[test]
     fn node_kind_size() {
         assert!(std::mem::size_of::<NodeKind>() <= 256);
     }
+
+    #[test]
+    fn mask_mode_on_shape() {
+        let tree = parse("
+        <svg xmlns='http://www.w3.org/2000/svg' width='10' height='10'>
+            <mask id='m'>
+                <rect width='10' height='10' fill='white'/>
+            </mask>
+            <rect width='10' height='10' mask='url(#m)' mask-mode='alpha'/>
+        </svg>");
+
+        let masks: Vec<(String, MaskType)> = tree.defs().children().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Mask(ref mask) => Some((mask.id.clone(), mask.kind)),
+                _ => None,
+            }
+        }).collect();
+        assert_eq!(masks.len(), 1);
+        assert_eq!(masks[0].1, MaskType::Alpha);
+
+        let g = tree.root().descendants().find(|n| {
+            if let NodeKind::Group(ref g) = *n.borrow() { g.mask.is_some() } else { false }
+        }).unwrap();
+        if let NodeKind::Group(ref g) = *g.borrow() {
+            assert_eq!(g.mask.as_ref(), Some(&masks[0].0));
+        };
+    }
+
+    #[test]
+    fn external_use() {
+        let dir = TempDir::new("external-use");
+        dir.write("ext.svg", &svg("<rect id='r' width='5' height='5'/>"));
+        let path = dir.write("main.svg", &svg("<use xlink:href='ext.svg#r'/>"));
+
+        assert_eq!(paths_count(&parse_file(&path, false)), 0);
+        assert_eq!(paths_count(&parse_file(&path, true)), 1);
+    }
+
+    #[test]
+    fn external_use_without_path() {
+        let dir = TempDir::new("external-use-without-path");
+        dir.write("ext.svg", &svg("<rect id='r' width='5' height='5'/>"));
+
+        let opt = Options {
+            load_external_files: true,
+            .. Options::default()
+        };
+        let text = String::from_utf8(svg("<use xlink:href='ext.svg#r'/>")).unwrap();
+        let tree = Tree::from_str(&text, &opt).unwrap();
+        assert_eq!(paths_count(&tree), 0);
+    }
+
+    #[test]
+    fn external_use_recursive() {
+        let dir = TempDir::new("external-use-recursive");
+        dir.write("ext.svg", &svg("
+            <g id='g'>
+                <rect width='5' height='5'/>
+                <use xlink:href='main.svg#r'/>
+            </g>"));
+        let path = dir.write("main.svg", &svg("
+            <rect id='r' width='5' height='5'/>
+            <use xlink:href='ext.svg#g'/>"));
+
+        // A link back to the main file is skipped.
+        assert_eq!(paths_count(&parse_file(&path, true)), 2);
+    }
+
+    #[test]
+    fn external_use_max_depth() {
+        let dir = TempDir::new("external-use-max-depth");
+        for i in 1..12 {
+            dir.write(&format!("f{}.svg", i), &svg(&format!("
+                <g id='g'>
+                    <rect width='5' height='5'/>
+                    <use xlink:href='f{}.svg#g'/>
+                </g>", i + 1)));
+        }
+        let path = dir.write("f0.svg", &svg("
+            <rect width='5' height='5'/>
+            <use xlink:href='f1.svg#g'/>"));
+
+        // The main file and 7 nested ones.
+        assert_eq!(paths_count(&parse_file(&path, true)), 8);
+    }
+
+    #[test]
+    fn external_use_invalid_files() {
+        let dir = TempDir::new("external-use-invalid-files");
+        dir.write("outside.svg", &svg("<rect id='r' width='5' height='5'/>"));
+        dir.write("doc/invalid.svg", b"<svg");
+        dir.write("doc/text.txt", b"text");
+        let path = dir.write("doc/main.svg", &svg("
+            <use xlink:href='missing.svg#r'/>
+            <use xlink:href='invalid.svg#r'/>
+            <use xlink:href='text.txt#r'/>
+            <use xlink:href='../outside.svg#r'/>"));
+
+        assert_eq!(paths_count(&parse_file(&path, true)), 0);
+    }
+
+    #[test]
+    fn external_use_image() {
+        let dir = TempDir::new("external-use-image");
+        dir.write("sub/image.png", b"\x89PNG\r\n\x1a\n");
+        dir.write("sub/ext.svg", &svg("<image id='i' width='5' height='5' xlink:href='image.png'/>"));
+        let path = dir.write("main.svg", &svg("<use xlink:href='sub/ext.svg#i'/>"));
+
+        let tree = parse_file(&path, true);
+        let image = tree.root().descendants().find(|n| {
+            if let NodeKind::Image(_) = *n.borrow() { true } else { false }
+        }).unwrap();
+        if let NodeKind::Image(ref image) = *image.borrow() {
+            match image.data {
+                ImageData::Path(ref path) => {
+                    assert_eq!(path.as_path(), ::std::path::Path::new("sub/image.png"));
+                }
+                _ => panic!("invalid image data"),
+            }
+        };
+    }
+
+    fn parse_svg(content: &str) -> Tree {
+        Tree::from_data(&svg(content), &Options::default()).unwrap()
+    }
+
+    fn abs_transform(node: &Node) -> Transform {
+        use tree::NodeExt;
+
+        let mut ts = Transform::default();
+        for n in node.ancestors().collect::<Vec<_>>().iter().rev() {
+            ts.append(&n.transform());
+        }
+
+        ts
+    }
+
+    fn first_path(tree: &Tree) -> Node {
+        tree.root().descendants()
+            .filter(|n| !tree.is_in_defs(n))
+            .find(|n| if let NodeKind::Path(_) = *n.borrow() { true } else { false })
+            .unwrap()
+    }
+
+    fn path_bbox(node: &Node) -> Rect {
+        match *node.borrow() {
+            NodeKind::Path(ref path) => ::utils::path_bbox(&path.segments, None, &Transform::default()),
+            _ => panic!("not a path"),
+        }
+    }
+
+    /// Returns the clip rect of the first group with a `clip-path`.
+    fn viewport_clip(tree: &Tree) -> Option<Rect> {
+        let id = tree.root().descendants().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Group(ref g) => g.clip_path.clone(),
+                _ => None,
+            }
+        }).next()?;
+
+        let clip = tree.defs_by_id(&id)?;
+        let path = clip.first_child()?;
+        Some(path_bbox(&path))
+    }
+
+    fn assert_ts(ts: Transform, a: f64, d: f64, e: f64, f: f64) {
+        assert!(approx(ts.a, a) && approx(ts.b, 0.0) && approx(ts.c, 0.0)
+                && approx(ts.d, d) && approx(ts.e, e) && approx(ts.f, f), "{:?}", ts);
+    }
+
+    #[test]
+    fn nested_svg() {
+        let tree = parse_svg("
+            <svg x='10' y='20' width='100' height='50' viewBox='0 0 10 5'>
+                <rect width='10' height='5'/>
+            </svg>");
+
+        assert_rect(viewport_clip(&tree).unwrap(), 10.0, 20.0, 100.0, 50.0);
+        assert_ts(abs_transform(&first_path(&tree)), 10.0, 10.0, 10.0, 20.0);
+    }
+
+    #[test]
+    fn nested_svg_percent_units() {
+        let tree = parse_svg("
+            <svg width='100' height='50'>
+                <rect width='50%' height='50%'/>
+            </svg>");
+
+        assert_rect(path_bbox(&first_path(&tree)), 0.0, 0.0, 50.0, 25.0);
+    }
+
+    #[test]
+    fn nested_svg_zero_size() {
+        let tree = parse_svg("
+            <svg width='0' height='50'>
+                <rect width='10' height='10'/>
+            </svg>");
+
+        assert_eq!(paths_count(&tree), 0);
+    }
+
+    #[test]
+    fn use_svg() {
+        let tree = parse_svg("
+            <defs>
+                <svg id='s' width='20' height='20' viewBox='0 0 10 10'>
+                    <rect width='10' height='10'/>
+                </svg>
+            </defs>
+            <use xlink:href='#s' x='5' y='5' width='40' height='40'/>");
+
+        assert_eq!(paths_count(&tree), 1);
+        assert_rect(viewport_clip(&tree).unwrap(), 0.0, 0.0, 40.0, 40.0);
+        assert_ts(abs_transform(&first_path(&tree)), 4.0, 4.0, 5.0, 5.0);
+    }
+
+    #[test]
+    fn external_use_whole_file() {
+        let dir = TempDir::new("external-use-whole-file");
+        dir.write("ext.svg", &svg("<rect width='5' height='5'/>"));
+        let path = dir.write("main.svg", &svg("<use xlink:href='ext.svg'/>"));
+
+        assert_eq!(paths_count(&parse_file(&path, false)), 0);
+        assert_eq!(paths_count(&parse_file(&path, true)), 1);
+    }
+
+    fn clipped_groups_count(tree: &Tree) -> usize {
+        tree.root().descendants().filter(|n| {
+            if let NodeKind::Group(ref g) = *n.borrow() { g.clip_path.is_some() } else { false }
+        }).count()
+    }
+
+    #[test]
+    fn viewport_overflow_visible() {
+        let tree = parse_svg("
+            <svg x='10' y='20' width='100' height='50' overflow='visible'>
+                <rect width='200' height='200'/>
+            </svg>");
+
+        assert_eq!(paths_count(&tree), 1);
+        assert!(viewport_clip(&tree).is_none());
+    }
+
+    #[test]
+    fn viewport_clip_property() {
+        let tree = parse_svg("
+            <svg x='10' y='20' width='100' height='50' clip='rect(5, 30, 40, 10)'>
+                <rect width='200' height='200'/>
+            </svg>");
+
+        assert_rect(viewport_clip(&tree).unwrap(), 20.0, 25.0, 20.0, 35.0);
+    }
+
+    #[test]
+    fn viewport_clip_property_auto() {
+        let tree = parse_svg("
+            <svg x='10' y='20' width='100' height='50' clip='rect(auto auto 10 auto)'>
+                <rect width='200' height='200'/>
+            </svg>");
+
+        assert_rect(viewport_clip(&tree).unwrap(), 10.0, 20.0, 100.0, 10.0);
+    }
+
+    #[test]
+    fn viewport_clip_property_with_overflow_visible() {
+        let tree = parse_svg("
+            <svg x='10' y='20' width='100' height='50' overflow='visible'
+                 clip='rect(5, 30, 40, 10)'>
+                <rect width='200' height='200'/>
+            </svg>");
+
+        assert!(viewport_clip(&tree).is_none());
+    }
+
+    #[test]
+    fn marker_overflow() {
+        let marker = |attrs: &str| {
+            parse_svg(&format!("
+                <marker id='m' {}><rect width='10' height='10'/></marker>
+                <path d='M 10 10 L 50 10' stroke='black' marker-start='url(#m)'/>", attrs))
+        };
+
+        let tree = marker("");
+        assert_eq!(clipped_groups_count(&tree), 1);
+        assert_rect(viewport_clip(&tree).unwrap(), 0.0, 0.0, 3.0, 3.0);
+
+        assert_eq!(clipped_groups_count(&marker("overflow='visible'")), 0);
+        assert_eq!(clipped_groups_count(&marker("overflow='auto'")), 0);
+        assert_eq!(clipped_groups_count(&marker("overflow='scroll'")), 1);
+    }
+
+    #[test]
+    fn pattern_clip_property() {
+        let tree = parse_svg("
+            <pattern id='p' patternUnits='userSpaceOnUse' width='10' height='10'
+                     clip='rect(1 5 5 auto)'>
+                <rect width='10' height='10'/>
+            </pattern>
+            <rect width='10' height='10' fill='url(#p)'/>");
+
+        let patt = tree.defs_by_id("p").unwrap();
+        let g = patt.first_child().unwrap();
+        let id = match *g.borrow() {
+            NodeKind::Group(ref g) => g.clip_path.clone().unwrap(),
+            _ => panic!("the pattern content is not clipped"),
+        };
+
+        let clip = tree.defs_by_id(&id).unwrap();
+        assert_rect(path_bbox(&clip.first_child().unwrap()), 0.0, 1.0, 5.0, 4.0);
+    }
+
+    fn path_paints(color: Option<Color>) -> Vec<(Paint, bool, Option<(Paint, bool)>)> {
+        let opt = Options {
+            color,
+            .. Options::default()
+        };
+
+        let tree = Tree::from_str("
+        <svg xmlns='http://www.w3.org/2000/svg' width='10' height='10' color='red'>
+            <rect width='10' height='10' fill='currentColor' stroke='currentColor'/>
+            <g color='blue'>
+                <rect width='10' height='10' fill='currentColor'/>
+            </g>
+            <rect width='10' height='10' fill='green' stroke='red'/>
+        </svg>", &opt).unwrap();
+
+        tree.root().descendants().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Path(ref path) => {
+                    let fill = path.fill.as_ref().unwrap();
+                    let stroke = path.stroke.as_ref()
+                        .map(|s| (s.paint.clone(), s.from_current_color));
+                    Some((fill.paint.clone(), fill.from_current_color, stroke))
+                }
+                _ => None,
+            }
+        }).collect()
+    }
+
+    fn assert_color(paint: &Paint, color: Color) {
+        match *paint {
+            Paint::Color(c) => assert_eq!(c, color),
+            _ => panic!("not a color"),
+        }
+    }
+
+    #[test]
+    fn current_color() {
+        let paints = path_paints(None);
+        assert_eq!(paints.len(), 3);
+
+        assert_color(&paints[0].0, Color::new(255, 0, 0));
+        assert!(paints[0].1);
+        let stroke = paints[0].2.as_ref().unwrap();
+        assert_color(&stroke.0, Color::new(255, 0, 0));
+        assert!(stroke.1);
+
+        assert_color(&paints[1].0, Color::new(0, 0, 255));
+        assert!(paints[1].1);
+
+        assert_color(&paints[2].0, Color::new(0, 128, 0));
+        assert!(!paints[2].1);
+        assert!(!paints[2].2.as_ref().unwrap().1);
+    }
+
+    #[test]
+    fn current_color_override() {
+        let paints = path_paints(Some(Color::new(0, 128, 0)));
+        assert_eq!(paints.len(), 3);
+
+        assert_color(&paints[0].0, Color::new(0, 128, 0));
+        assert!(paints[0].1);
+        assert_color(&paints[0].2.as_ref().unwrap().0, Color::new(0, 128, 0));
+
+        // A nested `color` is not affected.
+        assert_color(&paints[1].0, Color::new(0, 0, 255));
+        assert!(paints[1].1);
+
+        assert!(!paints[2].1);
+    }
+
+    /// Exports the tree and parses it back.
+    fn round_trip(tree: &Tree, opt: &Options) -> Tree {
+        Tree::from_str(&tree.to_svgdom().to_string(), opt).unwrap()
+    }
+
+    fn paint_orders(tree: &Tree) -> Vec<PaintOrder> {
+        tree.root().descendants().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Path(ref path) => Some(path.paint_order),
+                NodeKind::Text(ref text) => Some(text.paint_order),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// Returns the root children kinds, like `fill`, `stroke`, `fill+stroke` or `group`.
+    fn render_order(tree: &Tree) -> Vec<&'static str> {
+        tree.root().children().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Path(ref path) => {
+                    match (path.fill.is_some(), path.stroke.is_some()) {
+                        (true, true) => Some("fill+stroke"),
+                        (true, false) => Some("fill"),
+                        (false, true) => Some("stroke"),
+                        (false, false) => Some("none"),
+                    }
+                }
+                NodeKind::Group(_) => Some("group"),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn paint_order() {
+        let opt = Options::default();
+        let tree = parse_svg("
+            <rect width='10' height='10' stroke='red'/>
+            <rect width='10' height='10' stroke='red' paint-order='stroke'/>
+            <rect width='10' height='10' stroke='red' paint-order='markers stroke'/>
+            <rect width='10' height='10' stroke='red' paint-order='fill fill'/>
+            <g paint-order='stroke markers'>
+                <rect width='10' height='10' stroke='red'/>
+                <text paint-order='normal'>Text</text>
+                <text>Text</text>
+            </g>");
+
+        let orders = vec![
+            PaintOrder::FillAndStroke,
+            PaintOrder::StrokeAndFill,
+            PaintOrder::StrokeAndFill,
+            PaintOrder::FillAndStroke,
+            PaintOrder::StrokeAndFill,
+            PaintOrder::FillAndStroke,
+            PaintOrder::StrokeAndFill,
+        ];
+        assert_eq!(paint_orders(&tree), orders);
+        assert_eq!(paint_orders(&round_trip(&tree, &opt)), orders);
+    }
+
+    #[test]
+    fn paint_order_markers() {
+        let paths = |order: &str| {
+            render_order(&parse_svg(&format!("
+                <marker id='m'><rect width='10' height='10'/></marker>
+                <path d='M 10 10 L 50 10' fill='green' stroke='red'
+                      marker-start='url(#m)' paint-order='{}'/>", order)))
+        };
+
+        assert_eq!(paths("normal"), vec!["fill+stroke", "group"]);
+        assert_eq!(paths("markers"), vec!["group", "fill+stroke"]);
+        assert_eq!(paths("fill markers"), vec!["fill", "group", "stroke"]);
+        assert_eq!(paths("stroke markers"), vec!["stroke", "group", "fill"]);
+    }
+
+    fn non_scaling_strokes(tree: &Tree) -> Vec<bool> {
+        let mut list = Vec::new();
+        for node in tree.root().descendants() {
+            match *node.borrow() {
+                NodeKind::Path(ref path) => {
+                    list.push(path.stroke.as_ref().unwrap().non_scaling);
+                }
+                NodeKind::Text(ref text) => {
+                    for chunk in &text.chunks {
+                        for span in &chunk.spans {
+                            list.push(span.stroke.as_ref().unwrap().non_scaling);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        list
+    }
+
+    #[test]
+    fn vector_effect() {
+        let opt = Options::default();
+        let tree = parse_svg("
+            <rect width='10' height='10' stroke='red'/>
+            <rect width='10' height='10' stroke='red' vector-effect='non-scaling-stroke'/>
+            <rect width='10' height='10' stroke='red' vector-effect='none'/>
+            <g vector-effect='non-scaling-stroke' opacity='0.5'>
+                <rect width='10' height='10' stroke='red'/>
+            </g>
+            <text stroke='red' vector-effect='non-scaling-stroke'>
+                Text <tspan vector-effect='none'>Text</tspan>
+            </text>");
+
+        let strokes = vec![false, true, false, false, true, false];
+        assert_eq!(non_scaling_strokes(&tree), strokes);
+        assert_eq!(non_scaling_strokes(&round_trip(&tree, &opt)), strokes);
+    }
+
+    #[test]
+    fn path_bbox_with_scaled_stroke() {
+        let segments = ::utils::rect_to_path(Rect::new(0.0, 0.0, 10.0, 10.0));
+        let ts = Transform::new(2.0, 0.0, 0.0, 2.0, 0.0, 0.0);
+        let mut stroke = Stroke {
+            width: StrokeWidth::new(2.0),
+            .. Stroke::default()
+        };
+
+        let bbox = ::utils::path_bbox_with_scaled_stroke(&segments, Some(&stroke), &ts);
+        assert_rect(bbox, -2.0, -2.0, 24.0, 24.0);
+
+        stroke.non_scaling = true;
+        let bbox = ::utils::path_bbox_with_scaled_stroke(&segments, Some(&stroke), &ts);
+        assert_rect(bbox, -1.0, -1.0, 22.0, 22.0);
+
+        // `path_bbox` doesn't scale the stroke.
+        let bbox = ::utils::path_bbox(&segments, Some(&stroke), &ts);
+        assert_rect(bbox, -1.0, -1.0, 22.0, 22.0);
+    }
+
+    fn mesh_gradients(text: &str) -> Vec<MeshGradient> {
+        let tree = parse(text);
+        let list = tree.defs().children().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::MeshGradient(ref mg) => Some(mg.clone()),
+                _ => None,
+            }
+        }).collect();
+        list
+    }
+
+    fn mesh_svg(mesh: &str) -> String {
+        format!("
+        <svg xmlns='http://www.w3.org/2000/svg' width='20' height='20'>
+            <meshgradient id='mg' x='0' y='0' gradientUnits='userSpaceOnUse'{}
+            </meshgradient>
+            <rect width='20' height='20' fill='url(#mg)'/>
+        </svg>", mesh)
+    }
+
+    const MESH_PATCH: &str = "
+                <meshpatch>
+                    <stop path='l 10,0' stop-color='red'/>
+                    <stop path='l 0,10' stop-color='green'/>
+                    <stop path='c 0,0 -5,5 -10,0' stop-color='blue'/>
+                    <stop path='l 0,-10' stop-color='white'/>
+                </meshpatch>";
+
+    #[test]
+    fn mesh_shared_edges() {
+        let list = mesh_gradients(&mesh_svg(&format!(">
+                <meshrow>{}
+                    <meshpatch>
+                        <stop path='l 10,0' stop-color='red'/>
+                        <stop path='l 0,10' stop-color='green'/>
+                        <stop path='l -10,0' stop-color='blue'/>
+                    </meshpatch>
+                </meshrow>
+                <meshrow>
+                    <meshpatch>
+                        <stop path='l 0,10' stop-color='red'/>
+                        <stop path='l -10,0' stop-color='green'/>
+                        <stop path='l 0,-10'/>
+                    </meshpatch>
+                    <meshpatch>
+                        <stop path='l 0,10'/>
+                        <stop path='l -10,0' stop-color='black'/>
+                    </meshpatch>
+                </meshrow>", MESH_PATCH)));
+
+        assert_eq!(list.len(), 1);
+        let rows = &list[0].rows;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].len(), 2);
+        assert_eq!(rows[1].len(), 2);
+
+        let xy = |p: Point| (p.x, p.y);
+        let green = Color::new(0, 128, 0);
+        let blue = Color::new(0, 0, 255);
+
+        // The left edge is shared with the previous patch.
+        assert_eq!(xy(rows[0][1].points[0]), (10.0, 0.0));
+        assert_eq!(xy(rows[0][1].points[9]), (10.0, 10.0));
+        assert_eq!(xy(rows[0][1].points[6]), (20.0, 10.0));
+        assert_eq!(rows[0][1].colors, [green, green, blue, blue]);
+
+        // The top edge is shared with the patch above, including a curve.
+        assert_eq!(xy(rows[1][0].points[0]), (0.0, 10.0));
+        assert_eq!(xy(rows[1][0].points[1]), (5.0, 15.0));
+        assert_eq!(xy(rows[1][0].points[3]), (10.0, 10.0));
+        assert_eq!(xy(rows[1][0].points[6]), (10.0, 20.0));
+        // Shared corner colors have a priority over stop colors.
+        assert_eq!(rows[1][0].colors[0], Color::white());
+        assert_eq!(rows[1][0].colors[1], blue);
+        assert_eq!(rows[1][0].colors[2], green);
+
+        // Both edges are shared.
+        assert_eq!(xy(rows[1][1].points[0]), (10.0, 10.0));
+        assert_eq!(xy(rows[1][1].points[6]), (20.0, 20.0));
+        assert_eq!(xy(rows[1][1].points[9]), (10.0, 20.0));
+        assert_eq!(rows[1][1].colors[0], blue);
+        assert_eq!(rows[1][1].colors[2], Color::black());
+        assert_eq!(rows[1][1].colors[3], green);
+    }
+
+    #[test]
+    fn mesh_bicubic() {
+        let list = mesh_gradients(&mesh_svg(&format!(" type='bicubic'>
+                <meshrow>{}</meshrow>", MESH_PATCH)));
+
+        assert_eq!(list.len(), 1);
+        let rows = &list[0].rows;
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|row| row.len() == 4));
+
+        // Corner colors are preserved.
+        assert_eq!(rows[0][0].colors[0], Color::new(255, 0, 0));
+        assert_eq!(rows[0][3].colors[1], Color::new(0, 128, 0));
+        assert_eq!(rows[3][3].colors[2], Color::new(0, 0, 255));
+        assert_eq!(rows[3][0].colors[3], Color::white());
+    }
+
+    #[test]
+    fn mesh_malformed() {
+        // Rows with a different number of patches.
+        let text = mesh_svg(&format!(">
+                <meshrow>{0}{0}</meshrow>
+                <meshrow>{0}</meshrow>", MESH_PATCH));
+        assert!(mesh_gradients(&text).is_empty());
+
+        // A patch without enough stops.
+        let text = mesh_svg(">
+                <meshrow>
+                    <meshpatch>
+                        <stop path='l 10,0' stop-color='red'/>
+                        <stop path='l 0,10' stop-color='green'/>
+                    </meshpatch>
+                </meshrow>");
+        assert!(mesh_gradients(&text).is_empty());
+
+        // An unsupported path segment.
+        let text = mesh_svg(&format!(">
+                <meshrow>{}</meshrow>", MESH_PATCH.replace("l 10,0", "q 5,5 10,0")));
+        assert!(mesh_gradients(&text).is_empty());
+
+        // A path without an end point.
+        let text = mesh_svg(&format!(">
+                <meshrow>{}</meshrow>", MESH_PATCH.replace("l 10,0", "l 10")));
+        assert!(mesh_gradients(&text).is_empty());
+
+        // No rows.
+        assert!(mesh_gradients(&mesh_svg(">")).is_empty());
+    }
+
+    fn radial_gradient(attrs: &str) -> Option<RadialGradient> {
+        let tree = parse(&format!("
+        <svg xmlns='http://www.w3.org/2000/svg' xmlns:xlink='http://www.w3.org/1999/xlink'
+             width='200' height='200'>
+            <radialGradient id='rg1' fr='0.1'/>
+            <radialGradient id='rg2' {}>
+                <stop offset='0' stop-color='red'/>
+                <stop offset='1' stop-color='blue'/>
+            </radialGradient>
+            <rect width='200' height='200' fill='url(#rg2)'/>
+        </svg>", attrs));
+
+        let rg = tree.defs().children().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::RadialGradient(ref rg) => Some(rg.clone()),
+                _ => None,
+            }
+        }).next();
+        rg
+    }
+
+    #[test]
+    fn radial_gradient_fr() {
+        let rg = radial_gradient("fr='0.2'").unwrap();
+        assert!(rg.fr.value().fuzzy_eq(&0.2));
+
+        // Resolved like `r`.
+        let rg = radial_gradient("gradientUnits='userSpaceOnUse' r='100' fr='10%'").unwrap();
+        assert!(rg.fr.value().fuzzy_eq(&20.0));
+
+        // Inherited.
+        let rg = radial_gradient("xlink:href='#rg1'").unwrap();
+        assert!(rg.fr.value().fuzzy_eq(&0.1));
+
+        // Invalid values are ignored.
+        let rg = radial_gradient("fr='-0.2'").unwrap();
+        assert!(rg.fr.value().fuzzy_eq(&0.0));
+        let rg = radial_gradient("fr='q'").unwrap();
+        assert!(rg.fr.value().fuzzy_eq(&0.0));
+    }
+
+    #[test]
+    fn radial_gradient_fr_larger_than_r() {
+        assert!(radial_gradient("r='0.2' fr='0.2'").is_none());
+        assert!(radial_gradient("r='0.2' fr='0.3'").is_none());
+    }
+
+    #[test]
+    fn radial_gradient_focal_outside() {
+        // The focal point is moved onto the circle.
+        let rg = radial_gradient("fx='2'").unwrap();
+        assert!(rg.fx.fuzzy_eq(&1.0));
+        assert!(rg.fy.fuzzy_eq(&0.5));
+
+        // The focal circle is kept inside the end circle.
+        let rg = radial_gradient("fx='0.5' fy='-1' fr='0.1'").unwrap();
+        assert!(rg.fx.fuzzy_eq(&0.5));
+        assert!(rg.fy.fuzzy_eq(&0.1));
+
+        // A point inside the circle is preserved.
+        let rg = radial_gradient("fx='0.2' fy='0.6'").unwrap();
+        assert!(rg.fx.fuzzy_eq(&0.2));
+        assert!(rg.fy.fuzzy_eq(&0.6));
+    }
+
+    fn hatch_patterns(hatch: &str, content: &str) -> Vec<(Pattern, Vec<PathSegment>)> {
+        let tree = parse(&format!("
+        <svg xmlns='http://www.w3.org/2000/svg' width='200' height='200'>
+            {}
+            {}
+        </svg>", hatch, content));
+
+        let list = tree.defs().children().filter_map(|n| {
+            let patt = match *n.borrow() {
+                NodeKind::Pattern(ref patt) => patt.clone(),
+                _ => return None,
+            };
+
+            let mut segments = Vec::new();
+            for child in n.children() {
+                if let NodeKind::Path(ref path) = *child.borrow() {
+                    segments.extend_from_slice(&path.segments);
+                }
+            }
+
+            Some((patt, segments))
+        }).collect();
+        list
+    }
+
+    /// Returns vertical lines as (x, y1, y2).
+    fn hatch_lines(segments: &[PathSegment]) -> Vec<(f64, f64, f64)> {
+        let mut lines = Vec::new();
+        for pair in segments.chunks(2) {
+            match (pair[0], pair[1]) {
+                (PathSegment::MoveTo { x: x1, y: y1 }, PathSegment::LineTo { x: x2, y: y2 }) => {
+                    assert!(approx(x1, x2));
+                    lines.push((x1, y1, y2));
+                }
+                _ => panic!("unexpected segments"),
+            }
+        }
+
+        lines
+    }
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    fn assert_rect(r: Rect, x: f64, y: f64, width: f64, height: f64) {
+        let is_eq = approx(r.x, x) && approx(r.y, y)
+                    && approx(r.width, width) && approx(r.height, height);
+        assert!(is_eq, "{:?}", r);
+    }
+
+    // The rect paint area is expanded by the default stroke width times the miter limit.
+    const HATCH_RECT: &str = "<rect x='20' y='30' width='100' height='50' fill='url(#h)'/>";
+
+    #[test]
+    fn hatch_pitch() {
+        let list = hatch_patterns("
+            <hatch id='h' pitch='10' hatchUnits='userSpaceOnUse'>
+                <hatchpath stroke='black' d='M 0 0 L 0 10'/>
+            </hatch>", HATCH_RECT);
+
+        assert_eq!(list.len(), 1);
+        let (ref patt, ref segments) = list[0];
+        assert_eq!(patt.units, Units::UserSpaceOnUse);
+        assert_eq!(patt.content_units, Units::UserSpaceOnUse);
+        assert_rect(patt.rect, 0.0, 28.0, 10.0, 54.0);
+
+        let lines = hatch_lines(segments);
+        // Lines are repeated every pitch and are not rescaled.
+        assert!(lines.iter().all(|&(x, _, _)| approx(x, 0.0) || approx(x, 10.0)));
+        assert!(lines.iter().all(|&(_, y1, y2)| approx(y2 - y1, 10.0)));
+        // The hatch origin is kept, which is 28 units below the tile top.
+        assert!(lines.iter().all(|&(_, y1, _)| approx((y1 + 28.0) % 10.0, 0.0)));
+        // The whole tile height is covered.
+        let top = lines.iter().map(|l| l.1).fold(f64::MAX, f64::min);
+        let bottom = lines.iter().map(|l| l.2).fold(f64::MIN, f64::max);
+        assert!(top <= 0.0 && bottom >= 54.0);
+    }
+
+    #[test]
+    fn hatch_without_path_data() {
+        let list = hatch_patterns("
+            <hatch id='h' pitch='10' hatchUnits='userSpaceOnUse'>
+                <hatchpath stroke='black' offset='5'/>
+            </hatch>", HATCH_RECT);
+
+        let (ref patt, ref segments) = list[0];
+        assert_rect(patt.rect, 0.0, 28.0, 10.0, 54.0);
+        let lines = hatch_lines(segments);
+        assert!(lines.iter().any(|&(x, y1, y2)| {
+            approx(x, 5.0) && approx(y1, 0.0) && approx(y2, 54.0)
+        }));
+    }
+
+    #[test]
+    fn hatch_rotate() {
+        let list = hatch_patterns("
+            <hatch id='h' pitch='10' rotate='45' hatchUnits='userSpaceOnUse'>
+                <hatchpath stroke='black' d='M 0 0 L 0 10'/>
+            </hatch>", HATCH_RECT);
+
+        let (ref patt, ref segments) = list[0];
+        let sin = f64::consts::FRAC_1_SQRT_2;
+        assert!(approx(patt.transform.b, sin));
+
+        // The tile covers the painted area rotated by -45 degrees.
+        // The top is the (122, 28) corner and the bottom is the (18, 82) one.
+        assert_rect(patt.rect, 0.0, -94.0 * sin, 10.0, 158.0 * sin);
+
+        let lines = hatch_lines(segments);
+        assert!(lines.iter().all(|&(_, y1, y2)| approx(y2 - y1, 10.0)));
+        let top = lines.iter().map(|l| l.1).fold(f64::MAX, f64::min);
+        let bottom = lines.iter().map(|l| l.2).fold(f64::MIN, f64::max);
+        assert!(top <= 0.0 && bottom >= patt.rect.height);
+    }
+
+    #[test]
+    fn hatch_default_units() {
+        // `hatchUnits=objectBoundingBox` and `hatchContentUnits=userSpaceOnUse`.
+        let list = hatch_patterns("
+            <hatch id='h' x='0.5' pitch='0.1'>
+                <hatchpath stroke='black' d='M 0 0 L 0 10'/>
+            </hatch>", HATCH_RECT);
+
+        let (ref patt, ref segments) = list[0];
+        assert_eq!(patt.units, Units::UserSpaceOnUse);
+        assert_eq!(patt.content_units, Units::UserSpaceOnUse);
+        assert_rect(patt.rect, 70.0, 28.0, 10.0, 54.0);
+
+        let lines = hatch_lines(segments);
+        assert!(lines.iter().all(|&(x, _, _)| approx(x, 0.0) || approx(x, 10.0)));
+        assert!(lines.iter().all(|&(_, y1, y2)| approx(y2 - y1, 10.0)));
+    }
+
+    #[test]
+    fn hatch_bbox_content_units() {
+        let list = hatch_patterns("
+            <hatch id='h' pitch='0.1' hatchContentUnits='objectBoundingBox'>
+                <hatchpath stroke='black' stroke-width='0.01' d='M 0 0 L 0 0.1'/>
+            </hatch>", HATCH_RECT);
+
+        let (ref patt, ref segments) = list[0];
+        assert_eq!(patt.units, Units::UserSpaceOnUse);
+        assert_eq!(patt.content_units, Units::ObjectBoundingBox);
+        assert_rect(patt.rect, 20.0, 28.0, 10.0, 54.0);
+
+        // Content is in the bounding box units, so the pitch is 0.1
+        // and the tile height is 1.08.
+        let lines = hatch_lines(segments);
+        assert!(lines.iter().all(|&(x, _, _)| approx(x, 0.0) || approx(x, 0.1)));
+        assert!(lines.iter().all(|&(_, y1, y2)| approx(y2 - y1, 0.1)));
+        let bottom = lines.iter().map(|l| l.2).fold(f64::MIN, f64::max);
+        assert!(bottom >= 1.08);
+    }
+
+    #[test]
+    fn hatch_per_shape() {
+        let list = hatch_patterns("
+            <hatch id='h' pitch='0.1'>
+                <hatchpath stroke='black' d='M 0 0 L 0 10'/>
+            </hatch>", "
+            <rect x='20' y='30' width='100' height='50' fill='url(#h)'/>
+            <rect x='0' y='0' width='50' height='50' fill='url(#h)'/>");
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].0.id, "h");
+        assert_eq!(list[1].0.id, "h-1");
+        assert!(approx(list[0].0.rect.width, 10.0));
+        assert!(approx(list[1].0.rect.width, 5.0));
+    }
+
+    #[test]
+    fn hatch_on_text() {
+        let hatch = "
+            <hatch id='h' pitch='10'>
+                <hatchpath stroke='black' d='M 0 0 L 0 10'/>
+            </hatch>";
+        let text = "<text x='10' y='20' fill='url(#h)'>Text</text>";
+
+        // The text bounding box is unknown.
+        assert!(hatch_patterns(hatch, text).is_empty());
+
+        // The tile covers the whole canvas instead.
+        let hatch = hatch.replace("pitch='10'", "pitch='10' hatchUnits='userSpaceOnUse'");
+        let list = hatch_patterns(&hatch, text);
+        assert_eq!(list.len(), 1);
+        assert_rect(list[0].0.rect, 0.0, 0.0, 10.0, 200.0);
+    }
+
+    fn parse_detect_size(content: &str) -> Result<Tree, ::Error> {
+        let opt = Options {
+            detect_size: true,
+            .. Options::default()
+        };
+
+        Tree::from_str(content, &opt)
+    }
+
+    fn assert_svg_size(tree: &Tree, x: f64, y: f64, width: f64, height: f64) {
+        let svg = tree.svg_node();
+        assert!(svg.size.width.fuzzy_eq(&width) && svg.size.height.fuzzy_eq(&height));
+        assert!(svg.view_box.rect.fuzzy_eq(&Rect::new(x, y, width, height)));
+    }
+
+    #[test]
+    fn detect_size() {
+        let text = "
+        <svg xmlns='http://www.w3.org/2000/svg'>
+            <rect x='10' y='20' width='30' height='40'/>
+        </svg>";
+
+        assert!(Tree::from_str(text, &Options::default()).is_err());
+
+        let tree = parse_detect_size(text).unwrap();
+        assert_svg_size(&tree, 10.0, 20.0, 30.0, 40.0);
+    }
+
+    #[test]
+    fn detect_size_with_view_box() {
+        // The viewBox is used.
+        let tree = parse_detect_size("
+        <svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 50 60'>
+            <rect x='10' y='20' width='300' height='400'/>
+        </svg>").unwrap();
+        assert_svg_size(&tree, 0.0, 0.0, 50.0, 60.0);
+    }
+
+    #[test]
+    fn detect_size_with_width_only() {
+        let tree = parse_detect_size("
+        <svg xmlns='http://www.w3.org/2000/svg' width='200'>
+            <rect x='10' y='20' width='30' height='40'/>
+        </svg>").unwrap();
+        assert_svg_size(&tree, 10.0, 20.0, 30.0, 40.0);
+    }
+
+    #[test]
+    fn detect_size_with_percent() {
+        // Percentage lengths are resolved against the default size, which is 100x150.
+        let tree = parse_detect_size("
+        <svg xmlns='http://www.w3.org/2000/svg'>
+            <rect width='50%' height='50%'/>
+        </svg>").unwrap();
+        assert_svg_size(&tree, 0.0, 0.0, 50.0, 75.0);
+    }
+
+    #[test]
+    fn detect_size_without_content() {
+        // Text is ignored, so the default size is used.
+        let tree = parse_detect_size("
+        <svg xmlns='http://www.w3.org/2000/svg'>
+            <text x='10' y='20'>Text</text>
+        </svg>").unwrap();
+        assert_svg_size(&tree, 0.0, 0.0, 100.0, 150.0);
+    }
+
+    const COLORS_SVG: &str = "
+    <svg xmlns='http://www.w3.org/2000/svg' width='10' height='10'>
+        <linearGradient id='lg1'>
+            <stop offset='0' stop-color='red'/>
+            <stop offset='1' stop-color='blue'/>
+        </linearGradient>
+        <rect width='10' height='10' fill='url(#lg1)' stroke='green'/>
+        <clipPath id='clip1'>
+            <rect width='5' height='5' fill='yellow'/>
+        </clipPath>
+        <rect width='5' height='5' fill='red' clip-path='url(#clip1)'/>
+    </svg>";
+
+    #[test]
+    fn colors() {
+        let tree = parse(COLORS_SVG);
+        assert_eq!(tree.colors(), vec![
+            Color::new(255, 0, 0),
+            Color::new(0, 0, 255),
+            Color::new(0, 128, 0),
+        ]);
+    }
+
+    #[test]
+    fn map_colors() {
+        let mut tree = parse(COLORS_SVG);
+        tree.map_colors(|c| {
+            if c == Color::new(255, 0, 0) {
+                Color::black()
+            } else if c == Color::black() {
+                Color::white()
+            } else {
+                c
+            }
+        });
+        assert_eq!(tree.colors(), vec![
+            Color::black(),
+            Color::new(0, 0, 255),
+            Color::new(0, 128, 0),
+        ]);
+
+        // Clip paths are left untouched.
+        let clip_fill = tree.defs().descendants().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Path(ref path) => path.fill.as_ref().map(|f| f.paint.clone()),
+                _ => None,
+            }
+        }).next();
+
+        match clip_fill {
+            Some(Paint::Color(c)) => assert_eq!(c, Color::black()),
+            _ => panic!("clip path content is missing"),
+        }
+    }
+
+    fn markers(content: &str) -> Vec<MarkerInstance> {
+        let opt = Options {
+            keep_markers: true,
+            .. Options::default()
+        };
+
+        let tree = Tree::from_data(&svg(&format!("
+            <marker id='m1' orient='auto'><rect width='1' height='1'/></marker>
+            <marker id='m2' orient='auto-start-reverse'><rect width='1' height='1'/></marker>
+            {}", content)), &opt).unwrap();
+
+        path_markers(&tree)
+    }
+
+    fn path_markers(tree: &Tree) -> Vec<MarkerInstance> {
+        tree.root().descendants().flat_map(|n| {
+            match *n.borrow() {
+                NodeKind::Path(ref path) => path.markers.clone(),
+                _ => Vec::new(),
+            }
+        }).collect()
+    }
+
+    fn marker_defs(tree: &Tree) -> Vec<Marker> {
+        tree.defs().children().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Marker(ref marker) => Some(marker.clone()),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    fn assert_marker(m: &MarkerInstance, kind: MarkerKind, x: f64, y: f64, angle: f64) {
+        let d = ((m.angle - angle) % 360.0 + 360.0) % 360.0;
+        assert_eq!(m.kind, kind);
+        assert!(approx(m.x, x) && approx(m.y, y), "{:?}", m);
+        assert!(d < 1e-6 || 360.0 - d < 1e-6, "{:?}", m);
+    }
+
+    #[test]
+    fn markers_on_ellipse() {
+        let markers = markers("
+            <ellipse cx='50' cy='50' rx='30' ry='20'
+                     marker-start='url(#m1)' marker-mid='url(#m1)' marker-end='url(#m1)'/>");
+
+        assert_eq!(markers.len(), 6);
+        assert_marker(&markers[0], MarkerKind::Start, 80.0, 50.0, 90.0);
+        assert_marker(&markers[1], MarkerKind::Middle, 50.0, 70.0, 180.0);
+        assert_marker(&markers[2], MarkerKind::Middle, 20.0, 50.0, 270.0);
+        assert_marker(&markers[3], MarkerKind::Middle, 50.0, 30.0, 0.0);
+        assert_marker(&markers[4], MarkerKind::Middle, 80.0, 50.0, 90.0);
+        assert_marker(&markers[5], MarkerKind::End, 80.0, 50.0, 90.0);
+    }
+
+    #[test]
+    fn markers_on_circle() {
+        let markers = markers("<circle cx='50' cy='50' r='20' marker-mid='url(#m1)'/>");
+
+        assert_eq!(markers.len(), 4);
+        assert_marker(&markers[0], MarkerKind::Middle, 50.0, 70.0, 180.0);
+        assert_marker(&markers[1], MarkerKind::Middle, 30.0, 50.0, 270.0);
+        assert_marker(&markers[2], MarkerKind::Middle, 50.0, 30.0, 0.0);
+        assert_marker(&markers[3], MarkerKind::Middle, 70.0, 50.0, 90.0);
+    }
+
+    #[test]
+    fn markers_on_rounded_rect() {
+        let markers = markers("
+            <rect x='10' y='10' width='100' height='50' rx='10'
+                  marker-start='url(#m1)' marker-mid='url(#m1)' marker-end='url(#m1)'/>");
+
+        assert_eq!(markers.len(), 10);
+        assert_marker(&markers[0], MarkerKind::Start, 20.0, 10.0, 0.0);
+        assert_marker(&markers[1], MarkerKind::Middle, 100.0, 10.0, 0.0);
+        assert_marker(&markers[2], MarkerKind::Middle, 110.0, 20.0, 90.0);
+        assert_marker(&markers[3], MarkerKind::Middle, 110.0, 50.0, 90.0);
+        assert_marker(&markers[4], MarkerKind::Middle, 100.0, 60.0, 180.0);
+        assert_marker(&markers[5], MarkerKind::Middle, 20.0, 60.0, 180.0);
+        assert_marker(&markers[6], MarkerKind::Middle, 10.0, 50.0, 270.0);
+        assert_marker(&markers[7], MarkerKind::Middle, 10.0, 20.0, 270.0);
+        assert_marker(&markers[8], MarkerKind::Middle, 20.0, 10.0, 0.0);
+        assert_marker(&markers[9], MarkerKind::End, 20.0, 10.0, 0.0);
+    }
+
+    #[test]
+    fn markers_on_rect() {
+        let markers = markers("
+            <rect x='10' y='10' width='100' height='50' marker-mid='url(#m1)'/>");
+
+        assert_eq!(markers.len(), 3);
+        assert_marker(&markers[0], MarkerKind::Middle, 110.0, 10.0, 45.0);
+        assert_marker(&markers[1], MarkerKind::Middle, 110.0, 60.0, 135.0);
+        assert_marker(&markers[2], MarkerKind::Middle, 10.0, 60.0, 225.0);
+    }
+
+    #[test]
+    fn markers_auto_start_reverse() {
+        let markers = markers("
+            <path d='M 10 10 L 50 10 L 50 50'
+                  marker-start='url(#m2)' marker-mid='url(#m2)' marker-end='url(#m2)'/>
+            <circle cx='50' cy='50' r='20' marker-start='url(#m2)'/>");
+
+        assert_eq!(markers.len(), 4);
+        assert_marker(&markers[0], MarkerKind::Start, 10.0, 10.0, 180.0);
+        assert_marker(&markers[1], MarkerKind::Middle, 50.0, 10.0, 45.0);
+        assert_marker(&markers[2], MarkerKind::End, 50.0, 50.0, 90.0);
+        assert_marker(&markers[3], MarkerKind::Start, 70.0, 50.0, 270.0);
+    }
+
+    const KEEP_MARKERS_SVG: &str = "
+        <marker id='m1' refX='1' refY='2' markerWidth='4' markerHeight='5'
+                markerUnits='userSpaceOnUse' orient='auto-start-reverse' overflow='visible'>
+            <rect width='10' height='10'/>
+        </marker>
+        <marker id='m2' viewBox='0 0 10 10'>
+            <rect width='10' height='10'/>
+        </marker>
+        <path d='M 10 10 L 50 10 L 50 50' stroke='black' stroke-width='2'
+              marker-start='url(#m1)' marker-end='url(#m2)'/>
+        <path d='M 10 10 L 50 10 L 50 50' stroke-width='3' marker-mid='url(#m2)'/>";
+
+    #[test]
+    fn keep_markers() {
+        let opt = Options {
+            keep_markers: true,
+            .. Options::default()
+        };
+        let tree = Tree::from_data(&svg(KEEP_MARKERS_SVG), &opt).unwrap();
+
+        let defs = marker_defs(&tree);
+        assert_eq!(defs.len(), 2);
+
+        assert_eq!(defs[0].id, "m1");
+        assert_eq!(defs[0].units, MarkerUnits::UserSpaceOnUse);
+        assert_rect(defs[0].rect, 1.0, 2.0, 4.0, 5.0);
+        assert_eq!(defs[0].orientation, MarkerOrientation::AutoStartReverse);
+        assert!(defs[0].view_box.is_none());
+        assert!(defs[0].clip_path.is_none());
+
+        assert_eq!(defs[1].id, "m2");
+        assert_eq!(defs[1].units, MarkerUnits::StrokeWidth);
+        assert_rect(defs[1].rect, 0.0, 0.0, 3.0, 3.0);
+        assert_eq!(defs[1].orientation, MarkerOrientation::Angle(0.0));
+        assert!(defs[1].view_box.is_some());
+        assert!(defs[1].clip_path.is_some());
+
+        // Markers are not converted into groups.
+        assert_eq!(paths_count(&tree), 2);
+        assert_eq!(clipped_groups_count(&tree), 0);
+
+        let markers = path_markers(&tree);
+        assert_eq!(markers.len(), 3);
+        assert_eq!(markers[0].id, "m1");
+        assert_marker(&markers[0], MarkerKind::Start, 10.0, 10.0, 180.0);
+        assert_eq!(markers[1].id, "m2");
+        assert_marker(&markers[1], MarkerKind::End, 50.0, 50.0, 0.0);
+        assert!(approx(markers[1].stroke_width, 2.0));
+
+        // A path without a stroke still defines the marker scale.
+        assert_eq!(markers[2].id, "m2");
+        assert_marker(&markers[2], MarkerKind::Middle, 50.0, 10.0, 0.0);
+        assert!(approx(markers[2].stroke_width, 3.0));
+    }
+
+    #[test]
+    fn keep_markers_round_trip() {
+        let opt = Options {
+            keep_markers: true,
+            .. Options::default()
+        };
+        let tree = Tree::from_data(&svg(KEEP_MARKERS_SVG), &opt).unwrap();
+        let new_tree = round_trip(&tree, &opt);
+
+        let defs = marker_defs(&tree);
+        let new_defs = marker_defs(&new_tree);
+        assert_eq!(new_defs.len(), defs.len());
+        for (m1, m2) in defs.iter().zip(new_defs.iter()) {
+            assert_eq!(m1.id, m2.id);
+            assert_eq!(m1.units, m2.units);
+            assert!(m1.rect.fuzzy_eq(&m2.rect));
+            assert_eq!(m1.orientation, m2.orientation);
+            assert_eq!(m1.view_box.is_some(), m2.view_box.is_some());
+            assert_eq!(m1.clip_path.is_some(), m2.clip_path.is_some());
+        }
+
+        let markers = path_markers(&tree);
+        let new_markers = path_markers(&new_tree);
+        assert_eq!(new_markers.len(), markers.len());
+        for (m1, m2) in markers.iter().zip(new_markers.iter()) {
+            assert_eq!(m1.id, m2.id);
+            assert_marker(m2, m1.kind, m1.x, m1.y, m1.angle);
+            assert!(approx(m1.stroke_width, m2.stroke_width));
+        }
+    }
+
+    #[test]
+    fn keep_markers_with_context_paint() {
+        let opt = Options {
+            keep_markers: true,
+            .. Options::default()
+        };
+        let tree = Tree::from_data(&svg("
+            <marker id='m1'>
+                <rect width='10' height='10' fill='context-stroke'/>
+            </marker>
+            <path d='M 10 10 L 50 10' stroke='green' marker-start='url(#m1)'/>"), &opt).unwrap();
+
+        // Markers with a context paint depend on the shape, so they cannot be shared.
+        assert!(marker_defs(&tree).is_empty());
+        assert!(path_markers(&tree).is_empty());
+        assert_eq!(paths_count(&tree), 2);
+    }
+
+    /// Returns `Use` links with absolute transforms.
+    fn uses(tree: &Tree) -> Vec<(String, Transform)> {
+        tree.root().descendants().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Use(ref u) => Some((u.link.clone(), abs_transform(&n))),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    fn use_defs_count(tree: &Tree) -> usize {
+        tree.defs().children().filter(|n| {
+            if let NodeKind::Group(_) = *n.borrow() { true } else { false }
+        }).count()
+    }
+
+    const KEEP_USE_SVG: &str = "
+        <defs>
+            <g id='g1'>
+                <rect width='10' height='10'/>
+            </g>
+        </defs>
+        <use xlink:href='#g1' x='10'/>
+        <use xlink:href='#g1' x='20'/>";
+
+    #[test]
+    fn keep_use() {
+        let opt = Options {
+            keep_use: true,
+            .. Options::default()
+        };
+        let tree = Tree::from_data(&svg(KEEP_USE_SVG), &opt).unwrap();
+
+        // The content is converted only once.
+        assert_eq!(paths_count(&tree), 0);
+        assert_eq!(use_defs_count(&tree), 1);
+
+        let list = uses(&tree);
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].0, list[1].0);
+        assert!(tree.defs_by_id(&list[0].0).is_some());
+        assert_ts(list[0].1, 1.0, 1.0, 10.0, 0.0);
+        assert_ts(list[1].1, 1.0, 1.0, 20.0, 0.0);
+
+        // Without the option, the content is copied.
+        assert_eq!(paths_count(&parse_svg(KEEP_USE_SVG)), 2);
+    }
+
+    #[test]
+    fn keep_use_round_trip() {
+        let opt = Options {
+            keep_use: true,
+            .. Options::default()
+        };
+        let tree = round_trip(&Tree::from_data(&svg(KEEP_USE_SVG), &opt).unwrap(), &opt);
+
+        assert_eq!(paths_count(&tree), 0);
+        assert_eq!(use_defs_count(&tree), 1);
+
+        let list = uses(&tree);
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].0, list[1].0);
+        assert_ts(list[0].1, 1.0, 1.0, 10.0, 0.0);
+        assert_ts(list[1].1, 1.0, 1.0, 20.0, 0.0);
+    }
+
+    #[test]
+    fn keep_use_with_different_context() {
+        let opt = Options {
+            keep_use: true,
+            .. Options::default()
+        };
+        let tree = Tree::from_data(&svg("
+            <defs>
+                <g id='g1'>
+                    <rect width='10' height='10' stroke='red'/>
+                </g>
+            </defs>
+            <use xlink:href='#g1'/>
+            <use xlink:href='#g1' x='10'/>
+            <use xlink:href='#g1' fill='green'/>
+            <use xlink:href='#g1' paint-order='stroke'/>"), &opt).unwrap();
+
+        // Inherited properties are resolved during conversion,
+        // so the content is shared only by instances with the same style.
+        assert_eq!(uses(&tree).len(), 4);
+        assert_eq!(use_defs_count(&tree), 3);
+    }
+
+    fn links(tree: &Tree) -> Vec<(String, Option<String>)> {
+        tree.root().descendants().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Group(ref g) => {
+                    g.link.as_ref().map(|link| (link.href.clone(), link.target.clone()))
+                }
+                _ => None,
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn hyperlinks() {
+        let opt = Options::default();
+        let tree = parse_svg("
+            <a xlink:href='https://example.com/' target='_blank'>
+                <rect width='10' height='10'/>
+            </a>
+            <a xlink:href='page.html'>
+                <rect id='r1' width='10' height='10'/>
+            </a>
+            <a xlink:href='#r1'>
+                <rect width='10' height='10'/>
+            </a>
+            <a>
+                <rect width='10' height='10'/>
+            </a>
+            <text>Some <a xlink:href='page.html'>text</a></text>");
+
+        let list = vec![
+            ("https://example.com/".to_string(), Some("_blank".to_string())),
+            ("page.html".to_string(), None),
+            ("#r1".to_string(), None),
+        ];
+        assert_eq!(links(&tree), list);
+        assert_eq!(paths_count(&tree), 4);
+
+        // A link inside a text is lost, but its content is not.
+        let text = tree.root().descendants().filter_map(|n| {
+            match *n.borrow() {
+                NodeKind::Text(ref text) => {
+                    let spans = text.chunks.iter().flat_map(|c| c.spans.iter());
+                    Some(spans.map(|s| s.text.clone()).collect::<String>())
+                }
+                _ => None,
+            }
+        }).next();
+        assert_eq!(text, Some("Some text".to_string()));
+
+        assert_eq!(links(&round_trip(&tree, &opt)), list);
+    }
 }
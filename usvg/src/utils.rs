@@ -4,6 +4,8 @@
 
 //! Some useful utilities.
 
+use std::path;
+
 // external
 use svgdom::{
     Align,
@@ -14,6 +16,8 @@ use svgdom::{
 // self
 use geom::*;
 use tree;
+use tree::prelude::*;
+use lyon_geom;
 
 
 /// Converts `viewBox` to `Transform`.
@@ -82,3 +86,234 @@ pub fn rect_to_path(rect: Rect) -> Vec<tree::PathSegment> {
         tree::PathSegment::ClosePath,
     ]
 }
+
+/// Calculates path's bounding box.
+///
+/// Width and/or height can be zero.
+pub fn path_bbox(
+    segments: &[tree::PathSegment],
+    stroke: Option<&tree::Stroke>,
+    ts: &Transform,
+) -> Rect {
+    _path_bbox(segments, stroke.map(|s| s.width.value()), ts)
+}
+
+/// Calculates path's bounding box.
+///
+/// Unlike `path_bbox`, the stroke width is scaled by `ts`,
+/// unless it's a non-scaling stroke.
+///
+/// Width and/or height can be zero.
+pub fn path_bbox_with_scaled_stroke(
+    segments: &[tree::PathSegment],
+    stroke: Option<&tree::Stroke>,
+    ts: &Transform,
+) -> Rect {
+    let stroke_width = stroke.map(|stroke| {
+        // A non-scaling stroke width is already in the `ts` coordinates.
+        if stroke.non_scaling {
+            stroke.width.value()
+        } else {
+            let (sx, sy) = ts.get_scale();
+            stroke.width.value() * (sx + sy) / 2.0
+        }
+    });
+
+    _path_bbox(segments, stroke_width, ts)
+}
+
+fn _path_bbox(
+    segments: &[tree::PathSegment],
+    stroke_width: Option<f64>,
+    ts: &Transform,
+) -> Rect {
+    debug_assert!(!segments.is_empty());
+
+    let mut prev_x = 0.0;
+    let mut prev_y = 0.0;
+    let mut minx = 0.0;
+    let mut miny = 0.0;
+    let mut maxx = 0.0;
+    let mut maxy = 0.0;
+
+    if let Some(tree::PathSegment::MoveTo { x, y }) = TransformedPath::new(segments, *ts).next() {
+        let x = x as f32;
+        let y = y as f32;
+
+        prev_x = x;
+        prev_y = y;
+        minx = x;
+        miny = y;
+        maxx = x;
+        maxy = y;
+    }
+
+    for seg in TransformedPath::new(segments, *ts) {
+        match seg {
+              tree::PathSegment::MoveTo { x, y }
+            | tree::PathSegment::LineTo { x, y } => {
+                let x = x as f32;
+                let y = y as f32;
+                prev_x = x;
+                prev_y = y;
+
+                     if x > maxx { maxx = x; }
+                else if x < minx { minx = x; }
+
+                     if y > maxy { maxy = y; }
+                else if y < miny { miny = y; }
+            }
+            tree::PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                let x = x as f32;
+                let y = y as f32;
+
+                let curve = lyon_geom::CubicBezierSegment {
+                    from: lyon_geom::math::Point::new(prev_x, prev_y),
+                    ctrl1: lyon_geom::math::Point::new(x1 as f32, y1 as f32),
+                    ctrl2: lyon_geom::math::Point::new(x2 as f32, y2 as f32),
+                    to: lyon_geom::math::Point::new(x, y),
+                };
+
+                prev_x = x;
+                prev_y = y;
+
+                let r = curve.bounding_rect();
+
+                let right = r.max_x();
+                let bottom = r.max_y();
+                if r.min_x() < minx { minx = r.min_x(); }
+                if right > maxx { maxx = right; }
+                if r.min_y() < miny { miny = r.min_y(); }
+                if bottom > maxy { maxy = bottom; }
+            }
+            tree::PathSegment::ClosePath => {}
+        }
+    }
+
+    // TODO: find a better way
+    // It's an approximation, but it's better than nothing.
+    if let Some(stroke_width) = stroke_width {
+        let w = (stroke_width / 2.0) as f32;
+        minx -= w;
+        miny -= w;
+        maxx += w;
+        maxy += w;
+    }
+
+    let width = maxx - minx;
+    let height = maxy - miny;
+
+    (minx as f64, miny as f64, width as f64, height as f64).into()
+}
+
+/// An iterator over transformed path segments.
+pub struct TransformedPath<'a> {
+    segments: &'a [tree::PathSegment],
+    ts: Transform,
+    idx: usize,
+}
+
+impl<'a> TransformedPath<'a> {
+    /// Creates a new `TransformedPath` iterator.
+    pub fn new(segments: &'a [tree::PathSegment], ts: Transform) -> Self {
+        TransformedPath { segments, ts, idx: 0 }
+    }
+}
+
+impl<'a> Iterator for TransformedPath<'a> {
+    type Item = tree::PathSegment;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx == self.segments.len() {
+            return None;
+        }
+
+        if self.ts.is_default() {
+            self.idx += 1;
+            return self.segments.get(self.idx - 1).cloned();
+        }
+
+        let seg = match self.segments[self.idx] {
+            tree::PathSegment::MoveTo { x, y } => {
+                let (x, y) = self.ts.apply(x, y);
+                tree::PathSegment::MoveTo { x, y }
+            }
+            tree::PathSegment::LineTo { x, y } => {
+                let (x, y) = self.ts.apply(x, y);
+                tree::PathSegment::LineTo { x, y }
+            }
+            tree::PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                let (x1, y1) = self.ts.apply(x1, y1);
+                let (x2, y2) = self.ts.apply(x2, y2);
+                let (x,  y)  = self.ts.apply(x, y);
+                tree::PathSegment::CurveTo { x1, y1, x2, y2, x, y }
+            }
+            tree::PathSegment::ClosePath => tree::PathSegment::ClosePath,
+        };
+
+        self.idx += 1;
+
+        Some(seg)
+    }
+}
+
+/// Returns a canonical directory of a file.
+pub(crate) fn file_dir(path: &path::Path) -> Option<path::PathBuf> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => path::Path::new("."),
+    };
+
+    dir.canonicalize().ok()
+}
+
+/// Resolves a link to a local file.
+///
+/// `href` is resolved relative to the `dir`. The file must exist and must be
+/// inside the `root_dir`, which should be canonical.
+pub(crate) fn resolve_local_path(
+    href: &str,
+    dir: &path::Path,
+    root_dir: &path::Path,
+) -> Option<path::PathBuf> {
+    let path = dir.join(href).canonicalize().ok()?;
+    if path.starts_with(root_dir) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// A temporary directory, which is removed on drop.
+#[cfg(test)]
+pub(crate) struct TempDir(pub path::PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    /// Creates an empty directory with a name unique to the test and the process.
+    pub fn new(name: &str) -> Self {
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("usvg-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    /// Writes a file into the directory, creating subdirectories when needed.
+    pub fn write(&self, name: &str, data: &[u8]) -> path::PathBuf {
+        use std::fs;
+
+        let path = self.0.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, data).unwrap();
+        path
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = ::std::fs::remove_dir_all(&self.0);
+    }
+}